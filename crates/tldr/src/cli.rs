@@ -0,0 +1,444 @@
+use crate::{
+    context::{Context, LoadOptions},
+    error::{BoxedError, TldrError},
+    grammar::parser::parse_script,
+};
+
+use arrow::{
+    csv, json::ArrayWriter, record_batch::RecordBatch, util::pretty::pretty_format_batches,
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use datafusion::parquet::arrow::ArrowWriter;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    mem,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+use tokio::runtime::Runtime;
+
+/// the command line of tldr, without a command the window is opened
+#[derive(Debug, Parser)]
+#[command(name = "tldr", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// loads the model of a tldr script and runs queries against it
+    Run(RunArgs),
+    /// loads the model of a tldr script and reads queries interactively
+    Repl(ReplArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// the tldr script of the model
+    pub script: PathBuf,
+
+    /// a query, e.g. "DimProduct.Color, sum(FactSales.SalesAmount)", may be given more than once
+    #[arg(short, long = "query", value_name = "QUERY")]
+    pub queries: Vec<String>,
+
+    /// a file of queries separated by semicolons, run after those given with --query
+    #[arg(short = 'f', long, value_name = "FILE")]
+    pub query_file: Option<PathBuf>,
+
+    /// a named query of the script, run after all other queries, may be given more than once
+    #[arg(short, long = "named-query", value_name = "NAME")]
+    pub named_queries: Vec<String>,
+
+    /// the value of a parameter of the named queries, e.g. year=2009
+    #[arg(short, long = "param", value_name = "NAME=VALUE", value_parser = parse_param)]
+    pub params: Vec<(String, String)>,
+
+    /// the format of the results
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// the file the results are written to instead of stdout, required for parquet
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// prints the tables of the model to stderr once they are loaded
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// the number of files that are loaded at the same time, by default the number of cores
+    #[arg(short, long, value_name = "N")]
+    pub jobs: Option<NonZeroUsize>,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplArgs {
+    /// the tldr script of the model
+    pub script: PathBuf,
+
+    /// the number of files that are loaded at the same time, by default the number of cores
+    #[arg(short, long, value_name = "N")]
+    pub jobs: Option<NonZeroUsize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Csv,
+    Json,
+    Parquet,
+}
+
+/// a query of the command line
+enum Query<'a> {
+    /// a query in the syntax of the dsl
+    Text(&'a str),
+    /// a named query of the script
+    Named(&'a str),
+}
+
+/// loads the model of the script of `args`, runs its queries and writes their results.
+///
+/// The results of several queries are written one after the other, each table or csv with its
+/// own header and each json result as its own array. Parquet takes a single query.
+pub fn run(args: &RunArgs) -> Result<(), TldrError> {
+    let mut texts = args.queries.clone();
+    if let Some(path) = &args.query_file {
+        texts.extend(split_queries(&read_file(path)?));
+    }
+    let queries = texts
+        .iter()
+        .map(|q| Query::Text(q))
+        .chain(args.named_queries.iter().map(|n| Query::Named(n)))
+        .collect::<Vec<_>>();
+    if queries.is_empty() {
+        return Err(TldrError::TldrNoQueries);
+    }
+    let params = args
+        .params
+        .iter()
+        .map(|(n, v)| (n.as_str(), v.as_str()))
+        .collect::<Vec<_>>();
+
+    let parquet = match (args.format, &args.output, queries.as_slice()) {
+        (OutputFormat::Parquet, Some(path), [query]) => Some((path, query)),
+        (OutputFormat::Parquet, _, _) => return Err(TldrError::TldrParquetNeedsOutput),
+        _ => None,
+    };
+
+    let runtime = Runtime::new().map_err(|e| TldrError::TldrCouldNotStartRuntime { source: e })?;
+    runtime.block_on(async {
+        let context = load_script(&args.script, &load_options(args.jobs))?;
+        if args.verbose {
+            let mut tables = context.tables.keys().collect::<Vec<_>>();
+            tables.sort();
+            for table in tables {
+                eprintln!("loaded table: {}", table);
+            }
+        }
+        check_params(&context, &args.named_queries, &params)?;
+
+        if let Some((path, query)) = parquet {
+            let batches = run_query(&context, query, &params).await?;
+            return write_parquet(path, &batches);
+        }
+
+        let target = args
+            .output
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "stdout".to_string());
+        let out: Box<dyn Write> = match &args.output {
+            Some(path) => Box::new(File::create(path).map_err(output_error(&target))?),
+            None => Box::new(io::stdout()),
+        };
+        let mut out = BufWriter::new(out);
+
+        for query in &queries {
+            let batches = run_query(&context, query, &params).await?;
+            write_results(&mut out, args.format, &batches).map_err(output_error(&target))?;
+        }
+        out.flush().map_err(output_error(&target))
+    })
+}
+
+/// runs a query, a named query gets the values of the parameters it declares
+async fn run_query(
+    context: &Context,
+    query: &Query<'_>,
+    params: &[(&str, &str)],
+) -> Result<Vec<RecordBatch>, TldrError> {
+    match query {
+        Query::Text(query) => context.execute_query(query).await,
+        Query::Named(name) => {
+            let declared = context
+                .queries
+                .iter()
+                .find(|q| q.name == *name)
+                .map(|q| q.parameters.as_slice())
+                .unwrap_or_default();
+            let values = params
+                .iter()
+                .filter(|(n, _)| declared.iter().any(|p| p.name == *n))
+                .copied()
+                .collect::<Vec<_>>();
+            context.run_query(name, &values).await
+        }
+    }
+}
+
+/// every parameter value has to be used by one of the named queries, a misspelt name would go
+/// unnoticed otherwise
+fn check_params(
+    context: &Context,
+    named_queries: &[String],
+    params: &[(&str, &str)],
+) -> Result<(), TldrError> {
+    let declared = |name: &str| {
+        context
+            .queries
+            .iter()
+            .filter(|q| named_queries.contains(&q.name))
+            .any(|q| q.parameters.iter().any(|p| p.name == name))
+    };
+
+    match params.iter().find(|(n, _)| !declared(*n)) {
+        Some((parameter, _)) => Err(TldrError::TldrUnknownParameter {
+            query: named_queries.join(", "),
+            parameter: parameter.to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn parse_param(param: &str) -> Result<(String, String), String> {
+    param
+        .split_once('=')
+        .map(|(n, v)| (n.trim().to_string(), v.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got {}", param))
+}
+
+/// parses the tldr script `path` and loads its model as given by `options`
+pub fn load_script(path: &Path, options: &LoadOptions) -> Result<Context, TldrError> {
+    let script = read_file(path)?;
+    let ast = parse_script(&script)?;
+    Context::convert_ast_with_options(&ast, options)
+}
+
+/// the options of loading a script with `jobs` files at the same time
+pub fn load_options(jobs: Option<NonZeroUsize>) -> LoadOptions {
+    match jobs {
+        Some(jobs) => LoadOptions {
+            parallelism: jobs.get(),
+        },
+        None => LoadOptions::default(),
+    }
+}
+
+fn read_file(path: &Path) -> Result<String, TldrError> {
+    if !path.is_file() {
+        return Err(TldrError::TldrFileNotfound {
+            path: path.display().to_string(),
+        });
+    }
+    fs::read_to_string(path).map_err(|e| TldrError::TldrCouldNotReadFile {
+        path: path.display().to_string(),
+        source: e.into(),
+    })
+}
+
+/// splits the queries of a query file at the semicolons outside of strings and quoted names
+pub(crate) fn split_queries(text: &str) -> Vec<String> {
+    let mut queries = Vec::new();
+    let mut query = String::new();
+    let mut quote = None;
+    let mut escaped = false;
+
+    for c in text.chars() {
+        match quote {
+            None if c == ';' => {
+                queries.push(mem::take(&mut query));
+                continue;
+            }
+            None if c == '\'' || c == '"' => quote = Some(c),
+            // the sql of a sql statement escapes its double quotes
+            Some('"') if c == '\\' && !escaped => {
+                escaped = true;
+                query.push(c);
+                continue;
+            }
+            Some(q) if c == q && !escaped => quote = None,
+            _ => {}
+        }
+        escaped = false;
+        query.push(c);
+    }
+    queries.push(query);
+
+    queries
+        .iter()
+        .map(|q| q.trim())
+        .filter(|q| !q.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// writes the results of a query as a table, csv or json
+fn write_results(
+    out: &mut dyn Write,
+    format: OutputFormat,
+    batches: &[RecordBatch],
+) -> Result<(), BoxedError> {
+    match format {
+        OutputFormat::Table => writeln!(out, "{}", pretty_format_batches(batches)?)?,
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::new(&mut *out);
+            for batch in batches {
+                writer.write(batch)?;
+            }
+        }
+        OutputFormat::Json => {
+            {
+                let mut writer = ArrayWriter::new(&mut *out);
+                writer.write_batches(&batches.iter().collect::<Vec<_>>())?;
+                writer.finish()?;
+            }
+            writeln!(out)?;
+        }
+        OutputFormat::Parquet => unreachable!("parquet is written by write_parquet"),
+    }
+    Ok(())
+}
+
+fn write_parquet(path: &Path, batches: &[RecordBatch]) -> Result<(), TldrError> {
+    let target = path.display().to_string();
+    let schema = batches[0].schema();
+    let file = File::create(path).map_err(output_error(&target))?;
+    let mut writer =
+        ArrowWriter::try_new(file, schema.clone(), None).map_err(output_error(&target))?;
+    for batch in batches {
+        // the batches of a plan do not necessarily have the same schema
+        let batch = RecordBatch::try_new(schema.clone(), batch.columns().to_vec())
+            .map_err(output_error(&target))?;
+        writer.write(&batch).map_err(output_error(&target))?;
+    }
+    writer.close().map_err(output_error(&target))?;
+    Ok(())
+}
+
+pub(crate) fn output_error<E: Into<BoxedError>>(path: &str) -> impl FnOnce(E) -> TldrError + '_ {
+    move |e| TldrError::TldrCouldNotWriteOutput {
+        path: path.to_string(),
+        source: e.into(),
+    }
+}
+
+#[test]
+fn split_queries_test() {
+    let text = "DimProduct.Color, sum(FactSales.SalesAmount);
+        DimProduct.Color where DimProduct.ColorName = 'a;b' ;
+        \"odd;name\".Color;;
+        sql \"SELECT \\\"a;b\\\" FROM t\";
+        ";
+    assert_eq!(
+        split_queries(text),
+        vec![
+            "DimProduct.Color, sum(FactSales.SalesAmount)",
+            "DimProduct.Color where DimProduct.ColorName = 'a;b'",
+            "\"odd;name\".Color",
+            "sql \"SELECT \\\"a;b\\\" FROM t\"",
+        ]
+    );
+}
+
+#[test]
+fn run_test() {
+    let dir = std::env::temp_dir().join(format!("tldr_run_test_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let script = dir.join("model.tldr");
+    fs::write(
+        &script,
+        "load_files (
+            (csv_file_name: \"fixtures/FactSales.csv\"),
+            (csv_file_name: \"fixtures/DimProduct.csv\"),
+        )
+        relationships (
+            FactSales.ProductKey -> DimProduct.ProductKey,
+        )
+        queries (
+            \"sales_of\"(color: string): count(FactSales.SalesKey) where DimProduct.Color = $color;
+        )",
+    )
+    .unwrap();
+    let query_file = dir.join("queries.tldrq");
+    fs::write(
+        &query_file,
+        "DimProduct.Color, count(FactSales.SalesKey) where DimProduct.Color = 'Red';",
+    )
+    .unwrap();
+
+    let args = |format, output: &str| RunArgs {
+        script: script.clone(),
+        queries: vec!["count(FactSales.SalesKey) where DimProduct.Color = 'Blue'".to_string()],
+        query_file: Some(query_file.clone()),
+        named_queries: vec![],
+        params: vec![],
+        format,
+        output: Some(dir.join(output)),
+        verbose: false,
+        jobs: NonZeroUsize::new(1),
+    };
+
+    run(&args(OutputFormat::Csv, "results.csv")).unwrap();
+    let csv = fs::read_to_string(dir.join("results.csv")).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4, "{}", csv);
+    assert_eq!(lines[1], "0");
+    assert!(lines[2].starts_with("Color,"));
+    assert_eq!(lines[3], "Red,1");
+
+    run(&args(OutputFormat::Json, "results.json")).unwrap();
+    let json = fs::read_to_string(dir.join("results.json")).unwrap();
+    let results = json
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[1][0]["Color"], "Red");
+
+    assert!(matches!(
+        run(&args(OutputFormat::Parquet, "results.parquet")),
+        Err(TldrError::TldrParquetNeedsOutput)
+    ));
+    let mut parquet = args(OutputFormat::Parquet, "results.parquet");
+    parquet.query_file = None;
+    run(&parquet).unwrap();
+    assert!(dir.join("results.parquet").is_file());
+
+    let mut named = args(OutputFormat::Csv, "named.csv");
+    named.queries.clear();
+    named.query_file = None;
+    named.named_queries = vec!["sales_of".to_string()];
+    named.params = vec![("color".to_string(), "Black".to_string())];
+    run(&named).unwrap();
+    let csv = fs::read_to_string(dir.join("named.csv")).unwrap();
+    assert_eq!(csv.lines().nth(1), Some("3"));
+
+    named
+        .params
+        .push(("colour".to_string(), "Black".to_string()));
+    assert!(matches!(
+        run(&named),
+        Err(TldrError::TldrUnknownParameter { parameter, .. }) if parameter == "colour"
+    ));
+
+    let mut missing = args(OutputFormat::Table, "results.txt");
+    missing.queries.clear();
+    missing.query_file = None;
+    assert!(matches!(run(&missing), Err(TldrError::TldrNoQueries)));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
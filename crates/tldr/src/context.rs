@@ -1,170 +1,2076 @@
 use crate::{
-    error::TldrError,
-    grammar::ast::{Ast, DataTypeDescriptor, FileDescriptorData},
+    error::{BoxedError, TldrError},
+    grammar::ast::{
+        ArrowData, Ast, AvroData, CSVData, CSVDialect, Cardinality, ColumnReference, Compression,
+        DataTypeDescriptor, FileDescriptorData, FilterDirection, JSONData, ODBCData, ParquetData,
+        RelationshipData, SQLiteData, SQLiteSource, TableOptions, XLSXData,
+    },
+    lazy_table::LazyTable,
+    query::{add_calculated_columns, compile_measures},
 };
 
-use arrow_csv::infer_schema_from_files;
-use datafusion::{datasource::MemTable, execution::context::SessionContext, sql::TableReference};
+use arrow_odbc::{
+    odbc_api::{self, ConnectionOptions, Environment},
+    OdbcReaderBuilder,
+};
+use datafusion::{
+    catalog::{schema::MemorySchemaProvider, CatalogProvider},
+    common::Column,
+    datasource::{
+        avro_to_arrow::{read_avro_schema_from_reader, ReaderBuilder as AvroReaderBuilder},
+        file_format::{
+            avro::AvroFormat, csv::CsvFormat, file_compression_type::FileCompressionType,
+            json::JsonFormat, parquet::ParquetFormat, FileFormat,
+        },
+        listing::{ListingOptions, ListingTable, ListingTableConfig, ListingTableUrl},
+        provider_as_source,
+        view::ViewTable,
+        MemTable, TableProvider,
+    },
+    error::DataFusionError,
+    execution::context::SessionContext,
+    logical_expr::{lit, Expr, LogicalPlanBuilder},
+    parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ProjectionMask},
+    sql::TableReference,
+};
 
 use arrow::{
-    csv::ReaderBuilder,
-    datatypes::{DataType, Field, Schema},
+    array::{
+        Array, ArrayRef, ArrowPrimitiveType, BinaryArray, BooleanArray, Float64Array, Int64Array,
+        PrimitiveArray, StringArray,
+    },
+    compute::cast,
+    csv::{reader::Format, ReaderBuilder},
+    datatypes::{
+        DataType, Date32Type, Field, Schema, SchemaRef, Time64NanosecondType, TimeUnit,
+        TimestampNanosecondType,
+    },
+    error::ArrowError,
+    ipc::reader::{FileReader, StreamReader},
+    json::{
+        self,
+        reader::{infer_json_schema_from_iterator, infer_json_schema_from_seekable},
+    },
+    record_batch::{RecordBatch, RecordBatchReader},
+    util::display::array_value_to_string,
+};
+use bzip2::read::BzDecoder;
+use calamine::{open_workbook, Data, Reader, Xlsx};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use encoding_rs::Encoding;
+use flate2::read::MultiGzDecoder;
+use regex::Regex;
+use rusqlite::{types::Value, Connection, OpenFlags};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    ffi::OsStr,
+    fmt::Write,
+    fs::{self, File},
+    io::{BufRead, BufReader, Cursor, Read, Seek},
+    num::NonZeroUsize,
+    panic,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+    thread,
 };
-use std::{ffi::OsStr, fs::File, path::Path, sync::Arc};
+use xz2::read::XzDecoder;
+
+/// number of json records that are decoded into one record batch
+const JSON_BATCH_SIZE: usize = 1024;
+
+/// number of avro records that are decoded into one record batch
+const AVRO_BATCH_SIZE: usize = 1024;
+
+/// the extensions of the files that are loaded from a directory
+const CSV_EXTENSIONS: &[&str] = &["csv", "CSV"];
+const PARQUET_EXTENSIONS: &[&str] = &["parquet"];
+const JSON_EXTENSIONS: &[&str] = &["json", "ndjson", "jsonl"];
+const AVRO_EXTENSIONS: &[&str] = &["avro"];
+const ARROW_EXTENSIONS: &[&str] = &["arrow", "feather", "ipc", "arrows"];
+const XLSX_EXTENSIONS: &[&str] = &["xlsx", "xlsm"];
+
+/// the extensions of compressed files, they follow the extension of the file type
+const COMPRESSION_EXTENSIONS: &[(&str, Compression)] = &[
+    ("gz", Compression::Gzip),
+    ("gzip", Compression::Gzip),
+    ("zst", Compression::Zstd),
+    ("bz2", Compression::Bzip2),
+    ("xz", Compression::Xz),
+];
+
+/// the first bytes of an arrow ipc file, a stream starts without them
+const ARROW_FILE_MAGIC: &[u8; 6] = b"ARROW1";
 
 pub struct TableColumn<'a> {
     pub table: &'a str,
     pub column: &'a str,
 }
 
-pub struct Context {
-    pub ctx: SessionContext,
+/// the registered tables by name
+pub type Tables = HashMap<String, Arc<dyn TableProvider>>;
+
+/// the references of the registered tables in the session by their name.
+///
+/// A table in a schema is named schema.table
+pub type TableReferences = HashMap<String, TableReference<'static>>;
+
+pub struct Context {
+    pub ctx: SessionContext,
+    pub tables: Tables,
+    pub table_references: TableReferences,
+    pub relationships: Vec<Relationship>,
+    pub measures: Vec<Measure>,
+    pub queries: Vec<NamedQuery>,
+    /// the measures without a view in the schema `measures` with the reason, by name
+    pub missing_measure_views: HashMap<String, String>,
+}
+
+/// a checked relationship between two registered tables
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relationship {
+    pub from_table: String,
+    pub from_column: String,
+    pub to_table: String,
+    pub to_column: String,
+    pub cardinality: Cardinality,
+    pub filter_direction: FilterDirection,
+}
+
+/// a measure of the model, compiled into a datafusion expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measure {
+    pub name: String,
+    pub expr: Expr,
+    /// the expression as written in the dsl, other measures are referenced by their name
+    pub definition: String,
+}
+
+/// a named query of the model, compiled with a placeholder for each parameter
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedQuery {
+    pub name: String,
+    pub parameters: Vec<QueryParameter>,
+    pub exprs: Vec<Expr>,
+    pub filters: Vec<Expr>,
+    /// the query as written in the dsl, with its name and parameters
+    pub definition: String,
+}
+
+/// a typed parameter of a named query
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryParameter {
+    pub name: String,
+    pub data_type: DataType,
+    /// the format string a Date, Time or Datetime value is parsed with
+    pub format: Option<String>,
+}
+
+/// how the files of a script are loaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadOptions {
+    /// the number of files that are loaded at the same time
+    pub parallelism: usize,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions {
+            parallelism: thread::available_parallelism()
+                .map(NonZeroUsize::get)
+                .unwrap_or(1),
+        }
+    }
+}
+
+impl<'a> Context {
+    pub fn convert_ast(ast: &'a Ast) -> Result<Context, TldrError> {
+        Context::convert_ast_with_options(ast, &LoadOptions::default())
+    }
+
+    /// like `convert_ast`, loads the files as given by `options`
+    pub fn convert_ast_with_options(
+        ast: &'a Ast,
+        options: &LoadOptions,
+    ) -> Result<Context, TldrError> {
+        let (ctx, mut tables, table_references) = load_base_tables(&ast.file_descriptors, options)?;
+        add_calculated_columns(
+            &ctx,
+            &mut tables,
+            &table_references,
+            &ast.calculated_columns,
+        )?;
+        let relationships = check_relationships(&tables, &ast.relationships)?;
+        let measures = compile_measures(&tables, &ast.measures)?;
+
+        let mut context = Context {
+            ctx,
+            tables,
+            table_references,
+            relationships,
+            measures,
+            queries: Vec::new(),
+            missing_measure_views: HashMap::new(),
+        };
+        // the queries are planned against the complete model
+        context.queries = context.compile_queries(&ast.queries)?;
+        context.register_measure_views()?;
+        Ok(context)
+    }
+}
+
+/// checks that both columns of every relationship exist and that they have the same type
+fn check_relationships(
+    tables: &Tables,
+    relationships: &[RelationshipData],
+) -> Result<Vec<Relationship>, TldrError> {
+    let field_of = |c: &ColumnReference| {
+        let table = tables
+            .get(c.table)
+            .ok_or_else(|| TldrError::TldrUnknownTable {
+                table: c.table.to_string(),
+            })?;
+        table
+            .schema()
+            .field_with_name(c.column)
+            .cloned()
+            .map_err(|_| TldrError::TldrUnknownColumn {
+                table: c.table.to_string(),
+                column: c.column.to_string(),
+            })
+    };
+
+    relationships
+        .iter()
+        .map(|r| {
+            let from = field_of(&r.from)?;
+            let to = field_of(&r.to)?;
+            if from.data_type() != to.data_type() {
+                return Err(TldrError::TldrKeyTypesDiffer {
+                    from: format!("{}.{}", r.from.table, r.from.column),
+                    from_type: from.data_type().clone(),
+                    to: format!("{}.{}", r.to.table, r.to.column),
+                    to_type: to.data_type().clone(),
+                });
+            }
+
+            Ok(Relationship {
+                from_table: r.from.table.to_string(),
+                from_column: r.from.column.to_string(),
+                to_table: r.to.table.to_string(),
+                to_column: r.to.column.to_string(),
+                cardinality: r.cardinality,
+                filter_direction: r.filter_direction,
+            })
+        })
+        .collect()
+}
+
+// load csv, parquet, json, avro, arrow, odbc and sqlite tables...
+//
+// The files are loaded in parallel. Every file that cannot be loaded is reported, not just
+// the first one. Two tables with the same name in the same schema are an error.
+fn load_base_tables(
+    loadable_filenames: &[FileDescriptorData],
+    options: &LoadOptions,
+) -> Result<(SessionContext, Tables, TableReferences), TldrError> {
+    let ret = SessionContext::new();
+    let mut tables = Tables::new();
+    let mut references = TableReferences::new();
+    let mut sources = HashMap::new();
+    let mut errors = Vec::new();
+
+    let loaded = parallel_map(loadable_filenames, options.parallelism, load_table);
+
+    for (descriptor, loaded) in loadable_filenames.iter().zip(loaded) {
+        let (table_name, m) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+
+        let schema = descriptor.table_options().schema;
+        let (name, reference) = match schema {
+            Some(schema) => (
+                format!("{}.{}", schema, table_name),
+                TableReference::partial(schema.to_string(), table_name),
+            ),
+            None => (table_name.clone(), TableReference::bare(table_name)),
+        };
+
+        match sources.entry(name.clone()) {
+            Entry::Occupied(first) => {
+                errors.push(TldrError::TldrDuplicateTable {
+                    table: name,
+                    first: first.get().to_string(),
+                    second: descriptor.file_path().to_string(),
+                });
+                continue;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(descriptor.file_path());
+            }
+        }
+
+        let registered = match schema {
+            Some(schema) => register_schema(&ret, schema),
+            None => Ok(()),
+        }
+        .and_then(|_| ret.register_table(reference.clone(), m.clone()));
+        if let Err(e) = registered {
+            errors.push(TldrError::TldrCouldNotRegisterTable {
+                table: name,
+                source: e,
+            });
+            continue;
+        }
+
+        tables.insert(name.clone(), m);
+        references.insert(name, reference);
+    }
+
+    match errors.len() {
+        0 => Ok((ret, tables, references)),
+        1 => Err(errors.remove(0)),
+        _ => Err(TldrError::TldrCouldNotLoadFiles { errors }),
+    }
+}
+
+/// adds `schema` to the default catalog of the session unless it already exists
+pub(crate) fn register_schema(ctx: &SessionContext, schema: &str) -> Result<(), DataFusionError> {
+    let catalog_name = ctx.state().config_options().catalog.default_catalog.clone();
+    let catalog = ctx
+        .catalog(&catalog_name)
+        .ok_or_else(|| DataFusionError::Plan(format!("unknown catalog {}", catalog_name)))?;
+    if catalog.schema(schema).is_none() {
+        catalog.register_schema(schema, Arc::new(MemorySchemaProvider::new()))?;
+    }
+    Ok(())
+}
+
+/// loads the table of a single file descriptor and returns it with its name.
+///
+/// A single csv file needs a csv extension, like sales.csv or sales.csv.gz, or a compression
+/// block. A sqlite table is named like the table in the database, a worksheet like the
+/// worksheet if a sheet is given. The result set of a database query has no name, it needs a
+/// table_name block.
+fn load_table(
+    descriptor: &FileDescriptorData,
+) -> Result<(String, Arc<dyn TableProvider>), TldrError> {
+    let file_path = descriptor.file_path();
+    let path = Path::new(file_path);
+    if let FileDescriptorData::CSV(data) = descriptor {
+        if path.is_file() && data.compression.is_none() && !has_extension(path, CSV_EXTENSIONS) {
+            return Err(TldrError::TldrNotACsvFile {
+                path: file_path.to_string(),
+            });
+        }
+    }
+
+    let options = descriptor.table_options();
+    let table_name = match (options.table_name, descriptor) {
+        (Some(table_name), _) => Some(table_name),
+        (None, FileDescriptorData::ODBC(_)) => None,
+        (None, FileDescriptorData::SQLite(data)) => match data.source {
+            SQLiteSource::Table(table) => Some(table),
+            SQLiteSource::Query(_) => None,
+        },
+        (None, FileDescriptorData::XLSX(data)) if data.sheet.is_some() => data.sheet,
+        (None, _) if is_glob(file_path) => None,
+        (None, _) => strip_compression(path).file_stem().and_then(OsStr::to_str),
+    }
+    .ok_or_else(|| TldrError::TldrFileNameWithoutStem {
+        path: file_path.to_string(),
+    })?;
+
+    let files = match descriptor {
+        FileDescriptorData::CSV(data) => {
+            load_csv_files(&source_files(file_path, CSV_EXTENSIONS)?, data)?
+        }
+        FileDescriptorData::Parquet(data) => {
+            load_parquet_files(&source_files(file_path, PARQUET_EXTENSIONS)?, data)?
+        }
+        FileDescriptorData::JSON(data) => {
+            load_json_files(&source_files(file_path, JSON_EXTENSIONS)?, data)?
+        }
+        FileDescriptorData::Avro(data) => {
+            load_avro_files(&source_files(file_path, AVRO_EXTENSIONS)?, data)?
+        }
+        FileDescriptorData::Arrow(data) => {
+            load_arrow_files(&source_files(file_path, ARROW_EXTENSIONS)?, data)?
+        }
+        FileDescriptorData::ODBC(data) => vec![(file_path.to_string(), load_odbc_table(data)?)],
+        FileDescriptorData::SQLite(data) => {
+            vec![(file_path.to_string(), load_sqlite_table(data)?)]
+        }
+        FileDescriptorData::XLSX(data) => {
+            load_xlsx_files(&source_files(file_path, XLSX_EXTENSIONS)?, data)?
+        }
+    };
+
+    let table = union_files(file_path, table_name, files, options)?;
+    Ok((table_name.to_string(), table))
+}
+
+fn is_glob(file_path: &str) -> bool {
+    file_path.contains(['*', '?', '['])
+}
+
+/// whether the file has one of `extensions`, a compressed file like sales.csv.gz has the
+/// extension csv
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    strip_compression(path)
+        .extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|e| extensions.contains(&e))
+}
+
+/// the compression of a file by its extension
+fn path_compression(path: &Path) -> Option<Compression> {
+    let extension = path.extension().and_then(OsStr::to_str)?;
+    COMPRESSION_EXTENSIONS
+        .iter()
+        .find(|(e, _)| *e == extension)
+        .map(|(_, compression)| *compression)
+}
+
+/// the file name of a compressed file without the extension of its compression, or the path of
+/// a file that is not compressed
+fn strip_compression(path: &Path) -> &Path {
+    match (path_compression(path), path.file_stem()) {
+        (Some(_), Some(stem)) => Path::new(stem),
+        _ => path,
+    }
+}
+
+/// the compression of a file of a descriptor, `compression` if the descriptor gives one
+fn file_compression(file: &str, compression: Option<Compression>) -> Option<Compression> {
+    compression.or_else(|| path_compression(Path::new(file)))
+}
+
+/// opens a file and decompresses it while it is read
+fn open_file(file: &str, compression: Option<Compression>) -> Result<Box<dyn Read>, TldrError> {
+    let reader = File::open(file).map_err(read_error(file))?;
+    Ok(match compression {
+        None => Box::new(reader),
+        Some(Compression::Gzip) => Box::new(MultiGzDecoder::new(reader)),
+        Some(Compression::Zstd) => Box::new(zstd::Decoder::new(reader).map_err(read_error(file))?),
+        Some(Compression::Bzip2) => Box::new(BzDecoder::new(reader)),
+        Some(Compression::Xz) => Box::new(XzDecoder::new(reader)),
+    })
+}
+
+/// the compression type of a listing table that scans a file with `compression`
+fn file_compression_type(compression: Option<Compression>) -> FileCompressionType {
+    match compression {
+        None => FileCompressionType::UNCOMPRESSED,
+        Some(Compression::Gzip) => FileCompressionType::GZIP,
+        Some(Compression::Zstd) => FileCompressionType::ZSTD,
+        Some(Compression::Bzip2) => FileCompressionType::BZIP2,
+        Some(Compression::Xz) => FileCompressionType::XZ,
+    }
+}
+
+/// the files of a file descriptor, sorted by their path.
+///
+/// `file_path` is a single file, a glob pattern or a directory. The files of a directory
+/// and its subdirectories are taken if they have one of `extensions`.
+fn source_files(file_path: &str, extensions: &[&str]) -> Result<Vec<String>, TldrError> {
+    let path = Path::new(file_path);
+    let mut files = if is_glob(file_path) {
+        glob::glob(file_path)
+            .map_err(read_error(file_path))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(read_error(file_path))?
+            .into_iter()
+            .filter(|p| p.is_file())
+            .collect()
+    } else if path.is_dir() {
+        let mut files = Vec::new();
+        collect_files(path, extensions, &mut files).map_err(read_error(file_path))?;
+        files
+    } else if path.exists() {
+        vec![path.to_path_buf()]
+    } else {
+        Vec::new()
+    };
+
+    if files.is_empty() {
+        return Err(TldrError::TldrFileNotfound {
+            path: file_path.to_string(),
+        });
+    }
+    files.sort();
+
+    files
+        .into_iter()
+        .map(|p| {
+            p.to_str()
+                .map(str::to_string)
+                .ok_or_else(|| read_error(file_path)(format!("{} is not valid UTF-8", p.display())))
+        })
+        .collect()
+}
+
+fn collect_files(dir: &Path, extensions: &[&str], files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, extensions, files)?;
+        } else if has_extension(&path, extensions) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// the table of a file descriptor, the union of the tables of its files.
+///
+/// The path of the file and its partition values are added as columns if `options` asks for
+/// them. Every file has to be in a directory for every partition column.
+fn union_files(
+    file_path: &str,
+    table_name: &str,
+    mut files: Vec<(String, Arc<dyn TableProvider>)>,
+    options: &TableOptions,
+) -> Result<Arc<dyn TableProvider>, TldrError> {
+    if files.len() == 1
+        && options.file_name_column.is_none()
+        && options.partition_columns.is_empty()
+    {
+        if let Some((_, table)) = files.pop() {
+            return Ok(table);
+        }
+    }
+
+    let mut union: Option<LogicalPlanBuilder> = None;
+    for (file, table) in files {
+        let mut projection = table
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| {
+                Expr::Column(Column::new(
+                    Some(TableReference::bare(table_name.to_string())),
+                    f.name(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        if let Some(column) = options.file_name_column {
+            projection.push(lit(file.as_str()).alias(column));
+        }
+        for column in &options.partition_columns {
+            let value = partition_value(&file, column).ok_or_else(|| {
+                TldrError::TldrMissingPartitionValue {
+                    path: file.clone(),
+                    column: column.to_string(),
+                }
+            })?;
+            projection.push(lit(value).alias(*column));
+        }
+
+        let plan = LogicalPlanBuilder::scan(
+            TableReference::bare(table_name.to_string()),
+            provider_as_source(table),
+            None,
+        )
+        .and_then(|b| b.project(projection))
+        .and_then(|b| b.build())
+        .map_err(mem_table_error(file_path))?;
+
+        union = Some(match union {
+            Some(union) => union.union(plan).map_err(mem_table_error(file_path))?,
+            None => LogicalPlanBuilder::from(plan),
+        });
+    }
+
+    let view = union
+        .ok_or_else(|| TldrError::TldrFileNotfound {
+            path: file_path.to_string(),
+        })?
+        .build()
+        .and_then(|plan| ViewTable::try_new(plan, None))
+        .map_err(mem_table_error(file_path))?;
+    Ok(Arc::new(view))
+}
+
+/// the value of the hive-style partition directory column=value in the path of `file`
+fn partition_value(file: &str, column: &str) -> Option<String> {
+    Path::new(file).parent()?.components().find_map(|c| {
+        c.as_os_str()
+            .to_str()?
+            .strip_prefix(column)?
+            .strip_prefix('=')
+            .map(str::to_string)
+    })
+}
+
+/// calls `f` for every item on up to `parallelism` threads.
+///
+/// The results are in the order of `items`.
+fn parallel_map<T: Sync, R: Send>(
+    items: &[T],
+    parallelism: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let next = &AtomicUsize::new(0);
+    let f = &f;
+
+    let mut results = thread::scope(|s| {
+        let workers = (0..parallelism.clamp(1, items.len().max(1)))
+            .map(|_| {
+                s.spawn(move || {
+                    let mut results = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        match items.get(i) {
+                            Some(item) => results.push((i, f(item))),
+                            None => return results,
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect::<Vec<_>>()
+    });
+
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+/// loads csv files with the schema inferred from all of them
+fn load_csv_files(
+    files: &[String],
+    data: &CSVData,
+) -> Result<Vec<(String, Arc<dyn TableProvider>)>, TldrError> {
+    let schema = infer_csv_schema(files, data)?;
+
+    files
+        .iter()
+        .map(|file| Ok((file.clone(), load_csv_file(file, &schema, data)?)))
+        .collect()
+}
+
+fn load_csv_file(
+    file: &str,
+    schema: &Schema,
+    data: &CSVData,
+) -> Result<Arc<dyn TableProvider>, TldrError> {
+    // the listing table of a lazy table reads the file itself, it only knows quotes and escapes
+    if !data.materialize && !rewrites_csv(&data.dialect) && data.dialect.null_values.is_empty() {
+        let format = CsvFormat::default()
+            .with_has_header(data.has_header)
+            .with_delimiter(data.delimiter)
+            .with_quote(data.dialect.quote)
+            .with_escape(data.dialect.escape)
+            .with_file_compression_type(file_compression_type(file_compression(
+                file,
+                data.compression,
+            )));
+        return lazy_table(file, Arc::new(format), schema, &data.field_types);
+    }
+
+    // get the types right ...
+    let read_schema = Arc::new(override_field_types(
+        file,
+        schema,
+        &data.field_types,
+        read_field,
+    )?);
+    let schema = Arc::new(override_field_types(
+        file,
+        schema,
+        &data.field_types,
+        table_field,
+    )?);
+
+    let csv_reader = ReaderBuilder::new(read_schema)
+        .with_format(csv_format(data)?)
+        .build(open_csv(file, data)?)
+        .map_err(read_error(file))?;
+
+    // line number of the first record of the next batch
+    let mut first_line = data.dialect.skip_rows + if data.has_header { 2 } else { 1 };
+    let mut batches = Vec::new();
+    for batch in csv_reader {
+        let batch = batch.map_err(read_error(file))?;
+        let num_rows = batch.num_rows();
+        batches.push(convert_columns(
+            file,
+            &data.field_types,
+            schema.clone(),
+            batch,
+            Some(first_line),
+        )?);
+        first_line += num_rows;
+    }
+
+    mem_table(file, schema, batches)
+}
+
+/// infers the schema of csv files like `infer_schema_from_files` of arrow, with the dialect
+/// of the descriptor.
+///
+/// The schemas of the files are merged, at most `max_read_records` records are read from all
+/// files together.
+fn infer_csv_schema(files: &[String], data: &CSVData) -> Result<Schema, TldrError> {
+    let format = csv_format(data)?;
+    let mut records_to_read = data.max_read_records.unwrap_or(usize::MAX);
+    let mut schemas = Vec::new();
+    for file in files {
+        let (schema, records_read) = format
+            .infer_schema(open_csv(file, data)?, Some(records_to_read))
+            .map_err(schema_error(file))?;
+        if records_read == 0 {
+            continue;
+        }
+        schemas.push(schema);
+        records_to_read -= records_read;
+        if records_to_read == 0 {
+            break;
+        }
+    }
+
+    Schema::try_merge(schemas).map_err(schema_error(data.csv_file_path))
+}
+
+/// the format the arrow csv reader reads the files of a descriptor with.
+///
+/// Files that `open_csv` rewrites have no comments left and quotes doubled instead of escaped.
+fn csv_format(data: &CSVData) -> Result<Format, TldrError> {
+    let dialect = &data.dialect;
+    let mut format = Format::default()
+        .with_header(data.has_header)
+        .with_delimiter(data.delimiter)
+        .with_quote(dialect.quote);
+    if let (Some(escape), false) = (dialect.escape, rewrites_csv(dialect)) {
+        format = format.with_escape(escape);
+    }
+    if !dialect.null_values.is_empty() {
+        let values = dialect
+            .null_values
+            .iter()
+            .map(|v| regex::escape(v))
+            .collect::<Vec<_>>();
+        let null_regex = Regex::new(&format!("^({})$", values.join("|")))
+            .map_err(schema_error(data.csv_file_path))?;
+        format = format.with_null_regex(null_regex);
+    }
+    Ok(format)
+}
+
+/// whether a csv file has to be rewritten by `open_csv` before arrow can read it
+fn rewrites_csv(dialect: &CSVDialect) -> bool {
+    dialect.encoding.is_some()
+        || dialect.skip_rows > 0
+        || dialect.comment.is_some()
+        || dialect.truncated_rows
+}
+
+/// opens a csv file for the arrow csv reader, a compressed file is decompressed.
+///
+/// A file in another encoding than utf-8, with lines to skip, comments or truncated rows is
+/// read into memory and rewritten as utf-8 without them, truncated rows are filled up with
+/// the first null value.
+fn open_csv(file: &str, data: &CSVData) -> Result<Box<dyn Read>, TldrError> {
+    let dialect = &data.dialect;
+    let mut reader = open_file(file, file_compression(file, data.compression))?;
+    if !rewrites_csv(dialect) {
+        return Ok(reader);
+    }
+
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).map_err(read_error(file))?;
+    let text = match dialect.encoding {
+        Some(label) => {
+            let encoding = Encoding::for_label(label.as_bytes())
+                .ok_or_else(|| read_error(file)(format!("unknown encoding {}", label)))?;
+            encoding.decode_without_bom_handling(&bytes).0
+        }
+        None => String::from_utf8(bytes).map_err(read_error(file))?.into(),
+    };
+    let mut text: &str = &text;
+    for _ in 0..dialect.skip_rows {
+        text = text.split_once('\n').map_or("", |(_, rest)| rest);
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(data.delimiter)
+        .quote(dialect.quote)
+        .escape(dialect.escape)
+        .comment(dialect.comment)
+        .from_reader(text.as_bytes());
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(data.delimiter)
+        .quote(dialect.quote)
+        .from_writer(Vec::new());
+    let null_value = dialect.null_values.first().copied().unwrap_or_default();
+    let mut width = None;
+    for record in reader.records() {
+        let mut record = record.map_err(read_error(file))?;
+        // the header, or the first record, gives the number of values of a row
+        let width = *width.get_or_insert(record.len());
+        if dialect.truncated_rows {
+            while record.len() < width {
+                record.push_field(null_value);
+            }
+        }
+        writer.write_record(&record).map_err(read_error(file))?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| read_error(file)(e.to_string()))?;
+
+    Ok(Box::new(Cursor::new(bytes)))
+}
+
+/// loads parquet files with the merged schema of all of them
+fn load_parquet_files(
+    files: &[String],
+    data: &ParquetData,
+) -> Result<Vec<(String, Arc<dyn TableProvider>)>, TldrError> {
+    let schemas = files
+        .iter()
+        .map(|file| parquet_schema(file, data))
+        .collect::<Result<Vec<_>, _>>()?;
+    let schema = Schema::try_merge(schemas).map_err(schema_error(data.parquet_file_path))?;
+
+    files
+        .iter()
+        .map(|file| Ok((file.clone(), load_parquet_file(file, &schema, data)?)))
+        .collect()
+}
+
+fn parquet_reader_builder(file: &str) -> Result<ParquetRecordBatchReaderBuilder<File>, TldrError> {
+    let file_handle = File::open(file).map_err(read_error(file))?;
+    ParquetRecordBatchReaderBuilder::try_new(file_handle).map_err(schema_error(file))
+}
+
+/// the indices of `columns` in `schema`, in the order of the schema
+fn column_indices(schema: &Schema, columns: &[&str]) -> Result<Vec<usize>, ArrowError> {
+    let mut indices = columns
+        .iter()
+        .map(|c| schema.index_of(c))
+        .collect::<Result<Vec<_>, _>>()?;
+    indices.sort();
+    Ok(indices)
+}
+
+/// the schema of a parquet file, restricted to the columns of the descriptor
+fn parquet_schema(file: &str, data: &ParquetData) -> Result<Schema, TldrError> {
+    let builder = parquet_reader_builder(file)?;
+    // the metadata of the files may differ, it would prevent merging their schemas
+    let schema = Schema::new(builder.schema().fields().clone());
+
+    match &data.columns {
+        Some(columns) => column_indices(&schema, columns)
+            .and_then(|indices| schema.project(&indices))
+            .map_err(schema_error(file)),
+        None => Ok(schema),
+    }
+}
+
+fn load_parquet_file(
+    file: &str,
+    schema: &Schema,
+    data: &ParquetData,
+) -> Result<Arc<dyn TableProvider>, TldrError> {
+    if !data.materialize {
+        return lazy_table(
+            file,
+            Arc::new(ParquetFormat::default()),
+            schema,
+            &data.field_types,
+        );
+    }
+
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|f| f.name().as_str())
+        .collect::<Vec<_>>();
+    let builder = parquet_reader_builder(file)?;
+    let indices = column_indices(builder.schema(), &columns).map_err(schema_error(file))?;
+    let mask = ProjectionMask::roots(builder.parquet_schema(), indices);
+    let parquet_reader = builder
+        .with_projection(mask)
+        .build()
+        .map_err(read_error(file))?;
+
+    // the columns of the file may be in another order than those of the merged schema
+    let order = columns
+        .iter()
+        .map(|c| parquet_reader.schema().index_of(c))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(schema_error(file))?;
+
+    let schema = Arc::new(override_field_types(
+        file,
+        schema,
+        &data.field_types,
+        table_field,
+    )?);
+
+    // parquet has no line numbers, we report the row number instead
+    let mut first_row = 1;
+    let mut batches = Vec::new();
+    for batch in parquet_reader {
+        let batch = batch
+            .and_then(|b| b.project(&order))
+            .map_err(read_error(file))?;
+        let num_rows = batch.num_rows();
+        batches.push(convert_columns(
+            file,
+            &data.field_types,
+            schema.clone(),
+            batch,
+            Some(first_row),
+        )?);
+        first_row += num_rows;
+    }
+
+    mem_table(file, schema, batches)
+}
+
+/// loads json files with the merged schema of all of them
+fn load_json_files(
+    files: &[String],
+    data: &JSONData,
+) -> Result<Vec<(String, Arc<dyn TableProvider>)>, TldrError> {
+    let schemas = files
+        .iter()
+        .map(|file| {
+            let (mut reader, values) = read_json(file, data)?;
+            infer_json_schema(file, &mut reader, &values, data)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let schema = Schema::try_merge(schemas).map_err(schema_error(data.json_file_path))?;
+
+    files
+        .iter()
+        .map(|file| Ok((file.clone(), load_json_file(file, &schema, data)?)))
+        .collect()
+}
+
+/// a reader that can be rewound, a file or a decompressed file in memory
+trait SeekableRead: BufRead + Seek {}
+
+impl<T: BufRead + Seek> SeekableRead for T {}
+
+/// opens a json file. A json array is read as a whole, newline-delimited json is streamed
+/// from the returned reader later on.
+///
+/// A compressed file is decompressed into memory, the schema inference has to rewind it.
+fn read_json(
+    file: &str,
+    data: &JSONData,
+) -> Result<(Box<dyn SeekableRead>, Option<Vec<serde_json::Value>>), TldrError> {
+    let mut reader: Box<dyn SeekableRead> = match file_compression(file, data.compression) {
+        None => Box::new(BufReader::new(File::open(file).map_err(read_error(file))?)),
+        compression => {
+            let mut bytes = Vec::new();
+            open_file(file, compression)?
+                .read_to_end(&mut bytes)
+                .map_err(read_error(file))?;
+            Box::new(Cursor::new(bytes))
+        }
+    };
+
+    let is_array = starts_with_array(&mut reader).map_err(read_error(file))?;
+    let values = if is_array {
+        Some(serde_json::from_reader(&mut reader).map_err(read_error(file))?)
+    } else {
+        None
+    };
+
+    Ok((reader, values))
+}
+
+fn infer_json_schema(
+    file: &str,
+    reader: &mut dyn SeekableRead,
+    values: &Option<Vec<serde_json::Value>>,
+    data: &JSONData,
+) -> Result<Schema, TldrError> {
+    match values {
+        Some(values) => infer_json_schema_from_iterator(
+            values
+                .iter()
+                .take(data.max_read_records.unwrap_or(usize::MAX))
+                .map(Ok),
+        ),
+        None => {
+            infer_json_schema_from_seekable(reader, data.max_read_records).map(|(schema, _)| schema)
+        }
+    }
+    .map_err(schema_error(file))
+}
+
+fn load_json_file(
+    file: &str,
+    schema: &Schema,
+    data: &JSONData,
+) -> Result<Arc<dyn TableProvider>, TldrError> {
+    let (reader, values) = read_json(file, data)?;
+
+    if !data.materialize && values.is_none() {
+        let format = JsonFormat::default().with_file_compression_type(file_compression_type(
+            file_compression(file, data.compression),
+        ));
+        return lazy_table(file, Arc::new(format), schema, &data.field_types);
+    }
+
+    // get the types right ...
+    let read_schema = Arc::new(override_field_types(
+        file,
+        schema,
+        &data.field_types,
+        read_field,
+    )?);
+    let schema = Arc::new(override_field_types(
+        file,
+        schema,
+        &data.field_types,
+        table_field,
+    )?);
+
+    let mut raw_batches = Vec::new();
+    match values {
+        Some(values) => {
+            let mut decoder = json::ReaderBuilder::new(read_schema.clone())
+                .build_decoder()
+                .map_err(read_error(file))?;
+            for chunk in values.chunks(JSON_BATCH_SIZE) {
+                decoder.serialize(chunk).map_err(read_error(file))?;
+                if let Some(batch) = decoder.flush().map_err(read_error(file))? {
+                    raw_batches.push(batch);
+                }
+            }
+        }
+        None => {
+            let json_reader = json::ReaderBuilder::new(read_schema.clone())
+                .with_batch_size(JSON_BATCH_SIZE)
+                .build(reader)
+                .map_err(read_error(file))?;
+            for batch in json_reader {
+                raw_batches.push(batch.map_err(read_error(file))?);
+            }
+        }
+    }
+
+    // we report the number of the record, which is the line for newline-delimited json
+    let mut first_record = 1;
+    let mut batches = Vec::new();
+    for batch in raw_batches {
+        let num_rows = batch.num_rows();
+        batches.push(convert_columns(
+            file,
+            &data.field_types,
+            schema.clone(),
+            batch,
+            Some(first_record),
+        )?);
+        first_record += num_rows;
+    }
+
+    mem_table(file, schema, batches)
+}
+
+/// loads avro files with the merged schema of all of them
+fn load_avro_files(
+    files: &[String],
+    data: &AvroData,
+) -> Result<Vec<(String, Arc<dyn TableProvider>)>, TldrError> {
+    let schemas = files
+        .iter()
+        .map(|file| avro_schema(file))
+        .collect::<Result<Vec<_>, _>>()?;
+    let schema = Schema::try_merge(schemas).map_err(schema_error(data.avro_file_path))?;
+
+    files
+        .iter()
+        .map(|file| Ok((file.clone(), load_avro_file(file, &schema, data)?)))
+        .collect()
+}
+
+/// the arrow schema of the avro schema of a file
+fn avro_schema(file: &str) -> Result<Schema, TldrError> {
+    let mut file_handle = File::open(file).map_err(read_error(file))?;
+    let schema = read_avro_schema_from_reader(&mut file_handle).map_err(schema_error(file))?;
+    // the metadata of the files may differ, it would prevent merging their schemas
+    Ok(Schema::new(schema.fields().clone()))
+}
+
+fn load_avro_file(
+    file: &str,
+    schema: &Schema,
+    data: &AvroData,
+) -> Result<Arc<dyn TableProvider>, TldrError> {
+    if !data.materialize {
+        return lazy_table(
+            file,
+            Arc::new(AvroFormat::default()),
+            schema,
+            &data.field_types,
+        );
+    }
+
+    let avro_reader = AvroReaderBuilder::new()
+        .with_schema(Arc::new(schema.clone()))
+        .with_batch_size(AVRO_BATCH_SIZE)
+        .build(File::open(file).map_err(read_error(file))?)
+        .map_err(read_error(file))?;
+
+    let schema = Arc::new(override_field_types(
+        file,
+        schema,
+        &data.field_types,
+        table_field,
+    )?);
+
+    // avro has no line numbers, we report the record number instead
+    let mut first_record = 1;
+    let mut batches = Vec::new();
+    for batch in avro_reader {
+        let batch = batch.map_err(read_error(file))?;
+        let num_rows = batch.num_rows();
+        batches.push(convert_columns(
+            file,
+            &data.field_types,
+            schema.clone(),
+            batch,
+            Some(first_record),
+        )?);
+        first_record += num_rows;
+    }
+
+    mem_table(file, schema, batches)
+}
+
+fn load_arrow_files(
+    files: &[String],
+    data: &ArrowData,
+) -> Result<Vec<(String, Arc<dyn TableProvider>)>, TldrError> {
+    files
+        .iter()
+        .map(|file| Ok((file.clone(), load_arrow_file(file, data)?)))
+        .collect()
+}
+
+/// loads an arrow ipc file into memory, the types of its columns are kept unless `field_types`
+/// overrides them
+fn load_arrow_file(file: &str, data: &ArrowData) -> Result<Arc<dyn TableProvider>, TldrError> {
+    let (schema, batches) = read_ipc(file)?;
+    let table_schema = Arc::new(override_field_types(
+        file,
+        &schema,
+        &data.field_types,
+        table_field,
+    )?);
+
+    // row number of the first row of the next batch
+    let mut first_line = 1;
+    let mut converted = Vec::new();
+    for batch in batches {
+        let num_rows = batch.num_rows();
+        converted.push(convert_columns(
+            file,
+            &data.field_types,
+            table_schema.clone(),
+            batch,
+            Some(first_line),
+        )?);
+        first_line += num_rows;
+    }
+
+    mem_table(file, table_schema, converted)
+}
+
+/// reads the schema and all batches of an arrow ipc file, either in the file or the stream format
+fn read_ipc(file: &str) -> Result<(SchemaRef, Vec<RecordBatch>), TldrError> {
+    let mut reader = BufReader::new(File::open(file).map_err(read_error(file))?);
+    let mut magic = [0u8; 6];
+    let is_file_format = reader.read_exact(&mut magic).is_ok() && &magic == ARROW_FILE_MAGIC;
+    reader.rewind().map_err(read_error(file))?;
+
+    let reader: Box<dyn RecordBatchReader> = if is_file_format {
+        Box::new(FileReader::try_new(reader, None).map_err(read_error(file))?)
+    } else {
+        Box::new(StreamReader::try_new(reader, None).map_err(read_error(file))?)
+    };
+    let schema = reader.schema();
+    let batches = reader
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read_error(file))?;
+
+    Ok((schema, batches))
+}
+
+/// the odbc environment of the process, it is shared by all connections
+fn odbc_environment() -> Result<&'static Environment, odbc_api::Error> {
+    static ENVIRONMENT: OnceLock<Environment> = OnceLock::new();
+    if let Some(environment) = ENVIRONMENT.get() {
+        return Ok(environment);
+    }
+    let environment = Environment::new()?;
+    Ok(ENVIRONMENT.get_or_init(|| environment))
+}
+
+/// runs the query of an odbc descriptor and loads its result set into memory.
+///
+/// The schema is taken from the column types the driver reports for the result set.
+fn load_odbc_table(data: &ODBCData) -> Result<Arc<dyn TableProvider>, TldrError> {
+    let connection = odbc_environment()
+        .and_then(|environment| {
+            environment.connect_with_connection_string(
+                data.connection_string,
+                ConnectionOptions::default(),
+            )
+        })
+        .map_err(query_error(data.query))?;
+    let cursor = connection
+        .execute(data.query, ())
+        .map_err(query_error(data.query))?
+        .ok_or_else(|| query_error(data.query)("the query returned no result set"))?;
+
+    let mut builder = OdbcReaderBuilder::new();
+    builder.with_max_num_rows_per_batch(data.batch_size);
+    if let Some(max_text_size) = data.max_text_size {
+        builder.with_max_text_size(max_text_size);
+    }
+    let reader = builder.build(cursor).map_err(query_error(data.query))?;
+
+    let schema = reader.schema();
+    let batches = reader
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(query_error(data.query))?;
+    mem_table(data.query, schema, batches)
+}
+
+/// loads a table or the result set of a query of a sqlite database into memory.
+///
+/// The column types are derived from the declared types and overridden by `field_types`.
+/// Dates and times stored as text are parsed with the format string of their field type.
+fn load_sqlite_table(data: &SQLiteData) -> Result<Arc<dyn TableProvider>, TldrError> {
+    let file = data.sqlite_file_path;
+
+    if !Path::new(file).is_file() {
+        return Err(TldrError::TldrFileNotfound {
+            path: file.to_string(),
+        });
+    }
+
+    let connection = Connection::open_with_flags(
+        file,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(read_error(file))?;
+    let query = match data.source {
+        SQLiteSource::Table(table) => format!("SELECT * FROM \"{}\"", table.replace('"', "\"\"")),
+        SQLiteSource::Query(query) => query.to_string(),
+    };
+    let mut statement = connection.prepare(&query).map_err(read_error(file))?;
+    let columns = statement
+        .columns()
+        .iter()
+        .map(|c| (c.name().to_string(), c.decl_type().map(str::to_uppercase)))
+        .collect::<Vec<_>>();
+
+    let mut values = vec![Vec::new(); columns.len()];
+    let mut rows = statement.query([]).map_err(read_error(file))?;
+    while let Some(row) = rows.next().map_err(read_error(file))? {
+        for (i, column) in values.iter_mut().enumerate() {
+            column.push(row.get::<_, Value>(i).map_err(read_error(file))?);
+        }
+    }
+
+    let schema = Schema::new(
+        columns
+            .iter()
+            .zip(&values)
+            .map(|((name, declared), values)| {
+                Field::new(name, sqlite_data_type(declared.as_deref(), values), true)
+            })
+            .collect::<Vec<_>>(),
+    );
+    let table_schema = Arc::new(override_field_types(
+        file,
+        &schema,
+        &data.field_types,
+        table_field,
+    )?);
+
+    // temporal columns are read as text and parsed by convert_columns, other overridden
+    // columns are read as their type where possible, e.g. text in an INTEGER column as string
+    let read_schema = Arc::new(Schema::new(
+        schema
+            .fields()
+            .iter()
+            .map(|f| match data.field_types.get(f.name().as_str()) {
+                Some(descriptor) if descriptor.is_temporal() => read_field(f.name(), descriptor),
+                Some(descriptor) if is_sqlite_type(&data_type_of(descriptor)) => {
+                    Field::new(f.name(), data_type_of(descriptor), true)
+                }
+                _ => Field::clone(f),
+            })
+            .collect::<Vec<_>>(),
+    ));
+
+    // the table a value is loaded into, for errors
+    let table = match (data.table_options.table_name, data.source) {
+        (Some(table), _) | (None, SQLiteSource::Table(table)) => table,
+        (None, SQLiteSource::Query(query)) => query,
+    };
+    let arrays = read_schema
+        .fields()
+        .iter()
+        .zip(&values)
+        .map(|(f, values)| {
+            // only an override declares a column nullable, otherwise only NULL is null
+            let nullable = data
+                .field_types
+                .get(f.name().as_str())
+                .is_some_and(DataTypeDescriptor::is_nullable);
+            sqlite_column(values, f.data_type(), nullable).map_err(|row| {
+                TldrError::TldrCouldNotParseValue {
+                    path: format!("{}, table {}", file, table),
+                    column: f.name().to_string(),
+                    line: row + 1,
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let batch = RecordBatch::try_new(read_schema, arrays).map_err(read_error(file))?;
+    let batch = convert_columns(
+        file,
+        &data.field_types,
+        table_schema.clone(),
+        batch,
+        Some(1),
+    )?;
+
+    mem_table(file, table_schema, vec![batch])
+}
+
+/// the arrow type of a sqlite column with the declared type `declared`.
+///
+/// The type affinity rules of sqlite are followed. A column without a declared type, like an
+/// expression, or with a numeric declared type like DECIMAL takes the type of its values.
+fn sqlite_data_type(declared: Option<&str>, values: &[Value]) -> DataType {
+    let declared = declared.unwrap_or_default();
+    let declares = |names: &[&str]| names.iter().any(|n| declared.contains(n));
+
+    if declares(&["INT"]) {
+        DataType::Int64
+    } else if declares(&["CHAR", "CLOB", "TEXT"]) {
+        DataType::Utf8
+    } else if declares(&["BLOB"]) {
+        DataType::Binary
+    } else if declares(&["REAL", "FLOA", "DOUB"]) {
+        DataType::Float64
+    } else if declares(&["BOOL"]) {
+        DataType::Boolean
+    } else {
+        let data_type = values.iter().fold(DataType::Null, |data_type, value| {
+            match (data_type, value) {
+                (data_type, Value::Null) => data_type,
+                (DataType::Null | DataType::Int64, Value::Integer(_)) => DataType::Int64,
+                (DataType::Null | DataType::Int64 | DataType::Float64, Value::Real(_))
+                | (DataType::Float64, Value::Integer(_)) => DataType::Float64,
+                (DataType::Null | DataType::Binary, Value::Blob(_)) => DataType::Binary,
+                _ => DataType::Utf8,
+            }
+        });
+        match data_type {
+            DataType::Null => DataType::Utf8,
+            data_type => data_type,
+        }
+    }
+}
+
+/// the values of a sqlite column as an array of `data_type`.
+///
+/// Sqlite does not enforce the declared type of a column, so a value may not convert, like
+/// text in an INTEGER column. Such a value becomes null if `nullable` is set, otherwise the
+/// index of its row is returned.
+fn sqlite_column(
+    values: &[Value],
+    data_type: &DataType,
+    nullable: bool,
+) -> Result<ArrayRef, usize> {
+    fn convert<'a, T, A: FromIterator<Option<T>>>(
+        values: &'a [Value],
+        nullable: bool,
+        f: impl Fn(&'a Value) -> Option<T>,
+    ) -> Result<A, usize> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| match (v, f(v)) {
+                (Value::Null, _) => Ok(None),
+                (_, Some(v)) => Ok(Some(v)),
+                (_, None) if nullable => Ok(None),
+                (_, None) => Err(i),
+            })
+            .collect()
+    }
+
+    Ok(match data_type {
+        DataType::Int64 => Arc::new(convert::<_, Int64Array>(values, nullable, |v| match v {
+            Value::Integer(i) => Some(*i),
+            Value::Real(f) if f.fract() == 0.0 => Some(*f as i64),
+            Value::Text(s) => s.trim().parse().ok(),
+            _ => None,
+        })?),
+        DataType::Float64 => Arc::new(convert::<_, Float64Array>(values, nullable, |v| match v {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Real(f) => Some(*f),
+            Value::Text(s) => s.trim().parse().ok(),
+            _ => None,
+        })?),
+        DataType::Boolean => Arc::new(convert::<_, BooleanArray>(values, nullable, |v| match v {
+            Value::Integer(i) => Some(*i != 0),
+            _ => None,
+        })?),
+        DataType::Binary => Arc::new(convert::<_, BinaryArray>(values, nullable, |v| match v {
+            Value::Blob(b) => Some(b.as_slice()),
+            Value::Text(s) => Some(s.as_bytes()),
+            _ => None,
+        })?),
+        _ => Arc::new(convert::<_, StringArray>(values, nullable, |v| match v {
+            Value::Text(s) => Some(s.clone()),
+            Value::Integer(i) => Some(i.to_string()),
+            Value::Real(f) => Some(f.to_string()),
+            Value::Blob(b) => String::from_utf8(b.clone()).ok(),
+            Value::Null => None,
+        })?),
+    })
+}
+
+/// whether `sqlite_column` converts the values of a column to `data_type` itself
+fn is_sqlite_type(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Int64 | DataType::Float64 | DataType::Boolean | DataType::Binary | DataType::Utf8
+    )
+}
+
+/// the cells of a worksheet below its header row
+struct Worksheet {
+    /// the names of the columns, taken from the header row or column_1, column_2, ...
+    names: Vec<String>,
+    rows: Vec<Vec<Data>>,
+    /// the name of the worksheet
+    sheet: String,
+    /// the row number of the first row of `rows` in the worksheet
+    first_line: usize,
+    /// the column of the first column of `rows` in the worksheet, counted from zero
+    first_column: usize,
+}
+
+/// loads the same worksheet of excel workbooks, the type of a column is inferred from its
+/// cells in all of them
+fn load_xlsx_files(
+    files: &[String],
+    data: &XLSXData,
+) -> Result<Vec<(String, Arc<dyn TableProvider>)>, TldrError> {
+    let worksheets = files
+        .iter()
+        .map(|file| read_worksheet(file, data))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut columns: Vec<(&str, DataType)> = Vec::new();
+    for worksheet in &worksheets {
+        for (i, name) in worksheet.names.iter().enumerate() {
+            let data_type = cell_data_type(worksheet.rows.iter().map(|row| &row[i]));
+            match columns.iter_mut().find(|(n, _)| *n == name.as_str()) {
+                Some((_, t)) => *t = merge_cell_types(t.clone(), data_type),
+                None => columns.push((name.as_str(), data_type)),
+            }
+        }
+    }
+    let schema = Schema::new(
+        columns
+            .into_iter()
+            .map(|(name, data_type)| match data_type {
+                DataType::Null => Field::new(name, DataType::Utf8, true),
+                data_type => Field::new(name, data_type, true),
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    files
+        .iter()
+        .zip(&worksheets)
+        .map(|(file, worksheet)| {
+            Ok((
+                file.clone(),
+                load_worksheet(file, worksheet, &schema, data)?,
+            ))
+        })
+        .collect()
+}
+
+/// reads the cells of the worksheet of `data`, or of the first worksheet, from a workbook
+fn read_worksheet(file: &str, data: &XLSXData) -> Result<Worksheet, TldrError> {
+    let mut workbook: Xlsx<_> = open_workbook(file).map_err(read_error(file))?;
+    let sheet = match data.sheet {
+        Some(sheet) => sheet.to_string(),
+        None => workbook
+            .sheet_names()
+            .into_iter()
+            .next()
+            .ok_or_else(|| read_error(file)("the workbook has no worksheets"))?,
+    };
+    let mut range = workbook.worksheet_range(&sheet).map_err(read_error(file))?;
+    if let Some(cells) = data.range {
+        range = range.range(cells.start, cells.end);
+    }
+
+    // the used cells of a worksheet do not necessarily start in the first row or column
+    let (first_row, first_column) = range
+        .start()
+        .map_or((0, 0), |(row, column)| (row as usize, column as usize));
+    let mut rows = range.rows().map(<[Data]>::to_vec).collect::<Vec<_>>();
+    // a range may reach beyond the used cells
+    while rows
+        .last()
+        .is_some_and(|row| row.iter().all(|c| matches!(c, Data::Empty)))
+    {
+        rows.pop();
+    }
+    if rows.is_empty() {
+        return Err(schema_error(file)(format!(
+            "the worksheet {} is empty",
+            sheet
+        )));
+    }
+
+    let names = (1..=rows[0].len()).map(|i| format!("column_{}", i));
+    let names = if data.has_header {
+        rows.remove(0)
+            .iter()
+            .zip(names)
+            .map(|(cell, name)| match cell {
+                Data::Empty => name,
+                cell => cell.to_string(),
+            })
+            .collect()
+    } else {
+        names.collect()
+    };
+
+    Ok(Worksheet {
+        names,
+        rows,
+        sheet,
+        first_line: first_row + 1 + usize::from(data.has_header),
+        first_column,
+    })
+}
+
+/// the arrow type of a column with the cells `cells`.
+///
+/// Excel stores all numbers as floats, a column of whole numbers is an integer column and a
+/// column of dates at midnight a date column. Empty cells and errors are left out, a column of
+/// cells of different kinds is a string column. Null if all cells are left out.
+fn cell_data_type<'a>(cells: impl Iterator<Item = &'a Data>) -> DataType {
+    cells.fold(DataType::Null, |data_type, cell| {
+        let cell_type = match cell {
+            Data::Empty | Data::Error(_) => return data_type,
+            Data::Int(_) => DataType::Int64,
+            Data::Float(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => DataType::Int64,
+            Data::Float(_) => DataType::Float64,
+            Data::Bool(_) => DataType::Boolean,
+            Data::DateTime(dt) if dt.is_datetime() => match dt.as_datetime() {
+                Some(dt) if dt.time() == NaiveTime::MIN => DataType::Date32,
+                Some(_) => DataType::Timestamp(TimeUnit::Nanosecond, None),
+                None => DataType::Utf8,
+            },
+            _ => DataType::Utf8,
+        };
+        merge_cell_types(data_type, cell_type)
+    })
+}
+
+/// the type of a column with cells of both types
+fn merge_cell_types(a: DataType, b: DataType) -> DataType {
+    match (a, b) {
+        (DataType::Null, t) | (t, DataType::Null) => t,
+        (a, b) if a == b => a,
+        (DataType::Int64, DataType::Float64) | (DataType::Float64, DataType::Int64) => {
+            DataType::Float64
+        }
+        (DataType::Date32, t @ DataType::Timestamp(_, _))
+        | (t @ DataType::Timestamp(_, _), DataType::Date32) => t,
+        _ => DataType::Utf8,
+    }
+}
+
+/// loads the cells of a worksheet into memory, the columns of `schema` that the worksheet
+/// lacks are null
+fn load_worksheet(
+    file: &str,
+    worksheet: &Worksheet,
+    schema: &Schema,
+    data: &XLSXData,
+) -> Result<Arc<dyn TableProvider>, TldrError> {
+    let table_schema = Arc::new(override_field_types(
+        file,
+        schema,
+        &data.field_types,
+        table_field,
+    )?);
+
+    // temporal columns are read as text and parsed by convert_columns, numbers, booleans and
+    // strings are read as their type so that a cell that does not fit is reported by address
+    let read_schema = Arc::new(Schema::new(
+        schema
+            .fields()
+            .iter()
+            .map(|f| match data.field_types.get(f.name().as_str()) {
+                Some(descriptor) if descriptor.is_temporal() => read_field(f.name(), descriptor),
+                Some(descriptor) => match data_type_of(descriptor) {
+                    data_type @ (DataType::Int64
+                    | DataType::Float64
+                    | DataType::Boolean
+                    | DataType::Utf8) => Field::new(f.name(), data_type, true),
+                    _ => Field::clone(f),
+                },
+                _ => Field::clone(f),
+            })
+            .collect::<Vec<_>>(),
+    ));
+    let arrays = read_schema
+        .fields()
+        .iter()
+        .map(|f| {
+            let format = match data.field_types.get(f.name().as_str()) {
+                Some(
+                    DataTypeDescriptor::Date(_, format)
+                    | DataTypeDescriptor::Time(_, format)
+                    | DataTypeDescriptor::Datetime(_, format),
+                ) => Some(*format),
+                _ => None,
+            };
+            let column = worksheet.names.iter().position(|n| n == f.name());
+            let cells = worksheet
+                .rows
+                .iter()
+                .map(|row| column.map(|i| &row[i]))
+                .collect::<Vec<_>>();
+            cell_column(&cells, f.data_type(), format).map_err(|row| {
+                // the cell is in a column of the worksheet, otherwise all cells are null
+                let i = column.unwrap_or_default();
+                TldrError::TldrCouldNotConvertCell {
+                    path: file.to_string(),
+                    sheet: worksheet.sheet.clone(),
+                    cell: format!(
+                        "{}{}",
+                        cell_column_name(worksheet.first_column + i),
+                        worksheet.first_line + row
+                    ),
+                    value: worksheet.rows[row][i].to_string(),
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let batch = RecordBatch::try_new(read_schema, arrays).map_err(read_error(file))?;
+    let batch = convert_columns(
+        file,
+        &data.field_types,
+        table_schema.clone(),
+        batch,
+        Some(worksheet.first_line),
+    )?;
+
+    mem_table(file, table_schema, vec![batch])
+}
+
+/// the cells of a column as an array of `data_type`, only empty cells are null.
+///
+/// Dates in a string column are written with `format`, the format string of the field type
+/// they are parsed with afterwards. The index of the first cell that does not fit the type is
+/// returned as the error, like text in a column of numbers.
+fn cell_column(
+    cells: &[Option<&Data>],
+    data_type: &DataType,
+    format: Option<&str>,
+) -> Result<ArrayRef, usize> {
+    fn convert<T, A: FromIterator<Option<T>>>(
+        cells: &[Option<&Data>],
+        f: impl Fn(&Data) -> Option<T>,
+    ) -> Result<A, usize> {
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, c)| match c {
+                None | Some(Data::Empty) => Ok(None),
+                Some(cell) => f(cell).map(Some).ok_or(i),
+            })
+            .collect()
+    }
+    let datetime = |cell: &Data| match cell {
+        Data::DateTime(dt) if dt.is_datetime() => dt.as_datetime(),
+        _ => None,
+    };
+
+    Ok(match data_type {
+        DataType::Int64 => Arc::new(convert::<_, Int64Array>(cells, |c| match c {
+            Data::Int(i) => Some(*i),
+            Data::Float(f) if f.fract() == 0.0 => Some(*f as i64),
+            _ => None,
+        })?),
+        DataType::Float64 => Arc::new(convert::<_, Float64Array>(cells, |c| match c {
+            Data::Int(i) => Some(*i as f64),
+            Data::Float(f) => Some(*f),
+            _ => None,
+        })?),
+        DataType::Boolean => Arc::new(convert::<_, BooleanArray>(cells, |c| match c {
+            Data::Bool(b) => Some(*b),
+            _ => None,
+        })?),
+        DataType::Date32 => Arc::new(convert::<_, PrimitiveArray<Date32Type>>(cells, |c| {
+            datetime(c).map(|dt| Date32Type::from_naive_date(dt.date()))
+        })?),
+        DataType::Timestamp(_, _) => Arc::new(
+            convert::<_, PrimitiveArray<TimestampNanosecondType>>(cells, |c| {
+                datetime(c).and_then(|dt| dt.and_utc().timestamp_nanos_opt())
+            })?,
+        ),
+        _ => Arc::new(convert::<_, StringArray>(cells, |c| {
+            match (c, datetime(c)) {
+                (Data::Error(_), _) => None,
+                (Data::String(s), _) => Some(s.clone()),
+                (_, Some(dt)) => {
+                    let mut s = String::new();
+                    match format {
+                        Some(format) => write!(s, "{}", dt.format(format)).ok().map(|_| s),
+                        None => Some(dt.to_string()),
+                    }
+                }
+                (cell, None) => Some(cell.to_string()),
+            }
+        })?),
+    })
+}
+
+/// the name of a column of a worksheet counted from zero, like A, Z or AA
+fn cell_column_name(mut column: usize) -> String {
+    let mut name = String::new();
+    loop {
+        name.insert(0, char::from(b'A' + (column % 26) as u8));
+        if column < 26 {
+            return name;
+        }
+        column = column / 26 - 1;
+    }
+}
+
+fn mem_table(
+    file_path: &str,
+    schema: SchemaRef,
+    batches: Vec<RecordBatch>,
+) -> Result<Arc<dyn TableProvider>, TldrError> {
+    let table = MemTable::try_new(schema, vec![batches]).map_err(mem_table_error(file_path))?;
+    Ok(Arc::new(table))
+}
+
+/// a table that reads the file whenever a query scans it, instead of loading it into memory.
+///
+/// The schema is inferred and overridden by `field_types` just like for a materialized table.
+/// A value of a temporal column that cannot be parsed is reported without its line, the file
+/// may be scanned in several partitions.
+fn lazy_table(
+    file_path: &str,
+    format: Arc<dyn FileFormat>,
+    schema: &Schema,
+    field_types: &HashMap<&str, DataTypeDescriptor>,
+) -> Result<Arc<dyn TableProvider>, TldrError> {
+    let read_schema = Arc::new(override_field_types(
+        file_path,
+        schema,
+        field_types,
+        read_field,
+    )?);
+    let schema = Arc::new(override_field_types(
+        file_path,
+        schema,
+        field_types,
+        table_field,
+    )?);
+
+    let url = ListingTableUrl::parse(file_path).map_err(read_error(file_path))?;
+    let config = ListingTableConfig::new(url)
+        .with_listing_options(ListingOptions::new(format))
+        .with_schema(read_schema);
+    let source = Arc::new(ListingTable::try_new(config).map_err(mem_table_error(file_path))?);
+
+    // temporal columns are read as strings and have to be parsed while they are scanned
+    if field_types.values().any(|d| d.is_temporal()) {
+        Ok(Arc::new(LazyTable::new(
+            file_path,
+            source,
+            schema,
+            field_types,
+        )))
+    } else {
+        Ok(source)
+    }
+}
+
+fn read_error<E: Into<BoxedError>>(path: &str) -> impl FnOnce(E) -> TldrError + '_ {
+    move |e| TldrError::TldrCouldNotReadFile {
+        path: path.to_string(),
+        source: e.into(),
+    }
+}
+
+fn schema_error<E: Into<BoxedError>>(path: &str) -> impl FnOnce(E) -> TldrError + '_ {
+    move |e| TldrError::TldrCouldNotReadSchema {
+        path: path.to_string(),
+        source: e.into(),
+    }
+}
+
+fn query_error<E: Into<BoxedError>>(query: &str) -> impl FnOnce(E) -> TldrError + '_ {
+    move |e| TldrError::TldrCouldNotQueryDatabase {
+        query: query.to_string(),
+        source: e.into(),
+    }
+}
+
+fn mem_table_error(path: &str) -> impl FnOnce(DataFusionError) -> TldrError + '_ {
+    move |e| TldrError::TldrCouldNotCreateMemTable {
+        path: path.to_string(),
+        source: e,
+    }
+}
+
+/// checks whether the first non-whitespace character of the file is a '['.
+/// The reader is rewound afterwards.
+fn starts_with_array(reader: &mut dyn SeekableRead) -> std::io::Result<bool> {
+    let mut byte = [0u8];
+    let mut is_array = false;
+    while reader.read(&mut byte)? == 1 {
+        if !byte[0].is_ascii_whitespace() {
+            is_array = byte[0] == b'[';
+            break;
+        }
+    }
+    reader.rewind()?;
+    Ok(is_array)
 }
 
-impl<'a> Context {
-    pub fn convert_ast(ast: &'a Ast) -> Result<Context, TldrError> {
-        let ctx = load_base_tables(&ast.file_descriptors)?;
+/// maps a type descriptor of the dsl onto the arrow type of the loaded column
+pub(crate) fn data_type_of(descriptor: &DataTypeDescriptor) -> DataType {
+    match descriptor {
+        DataTypeDescriptor::Time(_, _) => DataType::Time64(TimeUnit::Nanosecond),
+        DataTypeDescriptor::Date(_, _) => DataType::Date32,
+        DataTypeDescriptor::Datetime(_, _) => DataType::Timestamp(TimeUnit::Nanosecond, None),
+        DataTypeDescriptor::UInt8(_) => DataType::UInt8,
+        DataTypeDescriptor::UInt16(_) => DataType::UInt16,
+        DataTypeDescriptor::UInt32(_) => DataType::UInt32,
+        DataTypeDescriptor::UInt64(_) => DataType::UInt64,
+        DataTypeDescriptor::Int8(_) => DataType::Int8,
+        DataTypeDescriptor::Int16(_) => DataType::Int16,
+        DataTypeDescriptor::Int32(_) => DataType::Int32,
+        DataTypeDescriptor::Int64(_) => DataType::Int64,
+        DataTypeDescriptor::Float32(_) => DataType::Float32,
+        DataTypeDescriptor::Float64(_) => DataType::Float64,
+        DataTypeDescriptor::String(_) => DataType::Utf8,
+        DataTypeDescriptor::Binary(_) => DataType::Binary,
+        DataTypeDescriptor::Duration(_, tu) => DataType::Duration(tu.clone()),
+        DataTypeDescriptor::Boolean(_) => DataType::Boolean,
+        DataTypeDescriptor::Null => DataType::Null,
+    }
+}
 
-        Ok(Context { ctx })
+/// the field a column is read with.
+///
+/// Temporal columns are read as strings first and parsed afterwards with the format
+/// string given in the descriptor.
+fn read_field(name: &str, descriptor: &DataTypeDescriptor) -> Field {
+    if descriptor.is_temporal() {
+        Field::new(name, DataType::Utf8, true)
+    } else {
+        table_field(name, descriptor)
     }
 }
 
-// load csv, parquet, and json tables...
-fn load_base_tables(
-    loadable_filenames: &Vec<FileDescriptorData>,
-) -> Result<SessionContext, TldrError> {
-    let ret = SessionContext::new();
+/// the field a column has in the registered table
+fn table_field(name: &str, descriptor: &DataTypeDescriptor) -> Field {
+    Field::new(name, data_type_of(descriptor), descriptor.is_nullable())
+}
 
-    for filename in loadable_filenames {
-        if let FileDescriptorData::CSV(data) = filename {
-            let path = Path::new(&data.csv_file_path);
-            if !path.exists() {
-                let s = format!("{}", path.display());
-                return Err(TldrError::TldrFileNotfound(s));
-            }
-            if path.extension() == Some(OsStr::new("csv"))
-                || path.extension() == Some(OsStr::new("CSV"))
-            {
-                println!("reading file: {}", path.display());
-
-                let schema = infer_schema_from_files(
-                    &[data.csv_file_path.to_string()],
-                    data.delimiter,
-                    data.max_read_records,
-                    data.has_header,
-                );
-                if schema.is_err() {
-                    return Err(TldrError::TldrCouldNotReadSchema(
-                        data.csv_file_path.to_string(),
-                    ));
-                }
-                let schema = schema.unwrap();
-
-                // get the types right ...
-                let mod_schema = Schema::new(
-                    data.field_types
-                        .iter()
-                        .map(|(k, v)| {
-                            let dtype = match v {
-                                DataTypeDescriptor::Time(_, _)
-                                | DataTypeDescriptor::Date(_, _)
-                                | DataTypeDescriptor::Datetime(_, _) => DataType::Utf8,
-                                DataTypeDescriptor::UInt8(_) => DataType::UInt8,
-                                DataTypeDescriptor::UInt16(_) => DataType::UInt16,
-                                DataTypeDescriptor::UInt32(_) => DataType::UInt32,
-                                DataTypeDescriptor::UInt64(_) => DataType::UInt64,
-                                DataTypeDescriptor::Int8(_) => DataType::Int8,
-                                DataTypeDescriptor::Int16(_) => DataType::Int16,
-                                DataTypeDescriptor::Int32(_) => DataType::Int32,
-                                DataTypeDescriptor::Int64(_) => DataType::Int64,
-                                DataTypeDescriptor::Float32(_) => DataType::Float32,
-                                DataTypeDescriptor::Float64(_) => DataType::Float64,
-                                DataTypeDescriptor::String(_) => DataType::Utf8,
-                                DataTypeDescriptor::Binary(_) => DataType::Binary,
-                                DataTypeDescriptor::Duration(_, tu) => {
-                                    DataType::Duration(tu.clone())
-                                }
-                                DataTypeDescriptor::Boolean(_) => DataType::Boolean,
-                                DataTypeDescriptor::Null => DataType::Null,
-                            };
-                            Field::new(k.to_string(), dtype, v.is_nullable())
-                        })
-                        .collect::<Vec<_>>(),
-                );
+/// replaces the inferred field of every column mentioned in `field_types`.
+///
+/// Naming a column that does not exist in the inferred schema is an error.
+fn override_field_types(
+    file_path: &str,
+    schema: &Schema,
+    field_types: &HashMap<&str, DataTypeDescriptor>,
+    to_field: impl Fn(&str, &DataTypeDescriptor) -> Field,
+) -> Result<Schema, TldrError> {
+    for column in field_types.keys() {
+        schema
+            .field_with_name(column)
+            .map_err(|e| TldrError::TldrCouldNotMergeSchemas {
+                path: file_path.to_string(),
+                column: column.to_string(),
+                source: e,
+            })?;
+    }
 
-                let schema = Schema::try_merge([schema, mod_schema]);
-                if schema.is_err() {
-                    return Err(TldrError::TldrCouldNotMergeSchemas(
-                        data.csv_file_path.to_string(),
-                    ));
-                }
+    Ok(Schema::new(
+        schema
+            .fields()
+            .iter()
+            .map(|f| match field_types.get(f.name().as_str()) {
+                Some(v) => to_field(f.name().as_str(), v),
+                None => Field::clone(f),
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
 
-                let schema = Arc::new(schema.unwrap());
-                let file = File::open(path).unwrap();
-                let csv_reader = ReaderBuilder::new(schema.clone()).build(file).unwrap();
+/// converts the columns of `batch` that are mentioned in `field_types` into the arrow
+/// types given by `schema`.
+///
+/// String columns that are described as Date, Time or Datetime are parsed with the format
+/// string of the descriptor, all other columns are cast.
+///
+/// `first_line` is the line number of the first row of `batch` in the file. It is used
+/// to report values that cannot be parsed in a temporal column that is not nullable, and
+/// values that cannot be cast in any other column. If the line is not known, like for a file
+/// that is scanned in several partitions, the value is reported instead.
+pub(crate) fn convert_columns(
+    file_path: &str,
+    field_types: &HashMap<&str, DataTypeDescriptor>,
+    schema: SchemaRef,
+    batch: RecordBatch,
+    first_line: Option<usize>,
+) -> Result<RecordBatch, TldrError> {
+    let mut columns = batch.columns().to_vec();
 
-                let mut batches = Vec::new();
-                for batch in csv_reader {
-                    if batch.is_err() {
-                        return Err(TldrError::TldrCouldNotReadFile(
-                            data.csv_file_path.to_string(),
-                        ));
+    for (i, field) in schema.fields().iter().enumerate() {
+        let descriptor = match field_types.get(field.name().as_str()) {
+            Some(d) => d,
+            None => continue,
+        };
+        let error = |row: usize| match first_line {
+            Some(first_line) => TldrError::TldrCouldNotParseValue {
+                path: file_path.to_string(),
+                column: field.name().to_string(),
+                line: first_line + row,
+            },
+            None => TldrError::TldrCouldNotParseScannedValue {
+                path: file_path.to_string(),
+                column: field.name().to_string(),
+                value: array_value_to_string(&columns[i], row).unwrap_or_default(),
+            },
+        };
+        let values = match columns[i].as_any().downcast_ref::<StringArray>() {
+            Some(values) if descriptor.is_temporal() => values,
+            _ => {
+                if columns[i].data_type() != field.data_type() {
+                    let column = cast(&columns[i], field.data_type()).map_err(|e| {
+                        TldrError::TldrCouldNotMergeSchemas {
+                            path: file_path.to_string(),
+                            column: field.name().to_string(),
+                            source: e,
+                        }
+                    })?;
+                    // a value that does not convert, like abc in an integer column, is null
+                    // after the cast
+                    if column.null_count() != columns[i].null_count() {
+                        let row = (0..column.len())
+                            .find(|&row| column.is_null(row) && !columns[i].is_null(row))
+                            .unwrap_or_default();
+                        return Err(error(row));
                     }
-                    let batch = batch.unwrap();
-                    batches.push(batch);
+                    columns[i] = column;
                 }
-                let m = MemTable::try_new(schema, vec![batches]).map_err(|_| {
-                    TldrError::TldrCouldNotCreateMemTable(data.csv_file_path.to_string())
-                })?;
-
-                ret.register_table(
-                    TableReference::bare(path.file_stem().unwrap().to_str().unwrap()),
-                    Arc::new(m),
-                )
-                .map_err(|_| {
-                    TldrError::TldrCouldNotRegisterTable(data.csv_file_path.to_string())
-                })?;
-
-                // TODO: Cast Date and Time types into the proper type
+                continue;
             }
-        }
+        };
 
-        /*         if path.extension() == Some(OsStr::new("parquet")) {
-            let file = std::fs::File::open(path);
-            if file.is_err() {
-                let s = format!("{}", path.display());
-                return Err(PlbiError::PlbiCouldNotReadFile(s));
-            }
-            let df = ParquetReader::new(&mut file.unwrap()).finish();
-            if df.is_err() {
-                let s = format!("{}", path.display());
-                return Err(PlbiError::PlbiCouldNotReadFile(s));
+        let column: ArrayRef = match descriptor {
+            DataTypeDescriptor::Date(nullable, format) => Arc::new(parse_column::<Date32Type>(
+                values,
+                *nullable,
+                |s| {
+                    NaiveDate::parse_from_str(s, format)
+                        .ok()
+                        .map(Date32Type::from_naive_date)
+                },
+                &error,
+            )?),
+            DataTypeDescriptor::Time(nullable, format) => {
+                Arc::new(parse_column::<Time64NanosecondType>(
+                    values,
+                    *nullable,
+                    |s| {
+                        NaiveTime::parse_from_str(s, format)
+                            .ok()
+                            .map(nanoseconds_since_midnight)
+                    },
+                    &error,
+                )?)
             }
-            ret.push(df.unwrap());
-
-            continue;
-        }
-
-        if path.extension() == Some(OsStr::new("json")) {
-            let file = std::fs::File::open(path);
-            if file.is_err() {
-                let s = format!("{}", path.display());
-                return Err(PlbiError::PlbiCouldNotReadFile(s));
+            DataTypeDescriptor::Datetime(nullable, format) => {
+                Arc::new(parse_column::<TimestampNanosecondType>(
+                    values,
+                    *nullable,
+                    |s| {
+                        NaiveDateTime::parse_from_str(s, format)
+                            .ok()
+                            .and_then(|dt| dt.and_utc().timestamp_nanos_opt())
+                    },
+                    &error,
+                )?)
             }
+            _ => continue,
+        };
+        columns[i] = column;
+    }
 
-            let df = JsonReader::new(&mut file.unwrap()).finish();
-            if df.is_err() {
-                let s = format!("{}", path.display());
-                return Err(PlbiError::PlbiCouldNotReadFile(s));
-            }
-            ret.push(df.unwrap());
+    RecordBatch::try_new(schema, columns).map_err(read_error(file_path))
+}
 
-            continue;
-        }
-        */
-    }
+/// parses every value of `values` with `parse`.
+///
+/// Values that cannot be parsed become null if `nullable` is set, otherwise `error`
+/// is called with the index of the offending row.
+fn parse_column<T: ArrowPrimitiveType>(
+    values: &StringArray,
+    nullable: bool,
+    parse: impl Fn(&str) -> Option<T::Native>,
+    error: impl Fn(usize) -> TldrError,
+) -> Result<PrimitiveArray<T>, TldrError> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| match v.and_then(&parse) {
+            Some(v) => Ok(Some(v)),
+            None if nullable => Ok(None),
+            None => Err(error(i)),
+        })
+        .collect()
+}
 
-    Ok(ret)
+pub(crate) fn nanoseconds_since_midnight(t: NaiveTime) -> i64 {
+    t.num_seconds_from_midnight() as i64 * 1_000_000_000 + t.nanosecond() as i64
 }
 
 #[test]
@@ -183,6 +2089,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimChannel.csv",
@@ -190,6 +2100,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimCurrency.csv",
@@ -197,6 +2111,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimCustomer.csv",
@@ -204,6 +2122,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimDate.csv",
@@ -211,6 +2133,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimEmployee.csv",
@@ -218,6 +2144,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimEntity.csv",
@@ -225,6 +2155,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimGeography.csv",
@@ -232,6 +2166,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimMachine.csv",
@@ -239,6 +2177,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimOutage.csv",
@@ -246,6 +2188,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimProduct.csv",
@@ -253,6 +2199,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimProductCategory.csv",
@@ -260,6 +2210,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimProductSubcategory.csv",
@@ -267,6 +2221,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimPromotion.csv",
@@ -274,6 +2232,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimSalesTerritory.csv",
@@ -281,6 +2243,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimScenario.csv",
@@ -288,6 +2254,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimStore.csv",
@@ -295,6 +2265,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactExchangeRate.csv",
@@ -302,6 +2276,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactInventory.csv",
@@ -309,6 +2287,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactITMachine.csv",
@@ -316,6 +2298,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactITSLA.csv",
@@ -323,6 +2309,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactOnlineSales.csv",
@@ -330,6 +2320,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactSales.csv",
@@ -337,6 +2331,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactSalesQuota.csv",
@@ -344,6 +2342,10 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactStrategyPlan.csv",
@@ -351,8 +2353,13 @@ fn generate_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
         ],
+        ..Default::default()
     };
 
     assert!(Context::convert_ast(&ast).is_ok());
@@ -388,7 +2395,12 @@ fn datetime_format_test() {
             delimiter: (";".as_bytes())[0],
             max_read_records: Some(100),
             has_header: true,
+            dialect: CSVDialect::default(),
+            compression: None,
+            materialize: true,
+            table_options: TableOptions::default(),
         })],
+        ..Default::default()
     };
 
     if parse_result.is_err() {
@@ -425,7 +2437,12 @@ fn date_format_test() {
             delimiter: (";".as_bytes())[0],
             max_read_records: Some(100),
             has_header: true,
+            dialect: CSVDialect::default(),
+            compression: None,
+            materialize: true,
+            table_options: TableOptions::default(),
         })],
+        ..Default::default()
     };
 
     if parse_result.is_err() {
@@ -490,6 +2507,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimChannel.csv",
@@ -497,6 +2518,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimCurrency.csv",
@@ -504,6 +2529,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimCustomer.csv",
@@ -511,6 +2540,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimDate.csv",
@@ -518,6 +2551,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimEmployee.csv",
@@ -525,6 +2562,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimEntity.csv",
@@ -532,6 +2573,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimGeography.csv",
@@ -539,6 +2584,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimMachine.csv",
@@ -546,6 +2595,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimOutage.csv",
@@ -553,6 +2606,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimProduct.csv",
@@ -560,6 +2617,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimProductCategory.csv",
@@ -567,6 +2628,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimProductSubcategory.csv",
@@ -574,6 +2639,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimPromotion.csv",
@@ -581,6 +2650,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimSalesTerritory.csv",
@@ -588,6 +2661,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimScenario.csv",
@@ -595,6 +2672,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/DimStore.csv",
@@ -602,6 +2683,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactExchangeRate.csv",
@@ -609,6 +2694,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactInventory.csv",
@@ -616,6 +2705,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactITMachine.csv",
@@ -623,6 +2716,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactITSLA.csv",
@@ -630,6 +2727,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactOnlineSales.csv",
@@ -637,6 +2738,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactSales.csv",
@@ -644,6 +2749,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactSalesQuota.csv",
@@ -651,6 +2760,10 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
             FileDescriptorData::CSV(CSVData {
                 csv_file_path: "contoso/FactStrategyPlan.csv",
@@ -658,8 +2771,13 @@ fn parse_to_context_test() {
                 delimiter: (";".as_bytes())[0],
                 max_read_records: Some(100),
                 has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
             }),
         ],
+        ..Default::default()
     };
 
     if parse_result.is_err() {
@@ -670,3 +2788,1235 @@ fn parse_to_context_test() {
     let (_, ast) = parse_result.unwrap();
     assert!(Context::convert_ast(&ast).is_ok());
 }
+
+#[tokio::test]
+async fn temporal_types_test() {
+    use crate::grammar::ast::*;
+
+    let mut field_types = HashMap::new();
+    field_types.insert("OutageDate", DataTypeDescriptor::Date(true, "%Y-%m-%d"));
+    field_types.insert("OutageStart", DataTypeDescriptor::Time(true, "%H:%M:%S"));
+    field_types.insert(
+        "OutageStartTime",
+        DataTypeDescriptor::Datetime(false, "%Y-%m-%d %H:%M:%S"),
+    );
+
+    let ast = Ast {
+        file_descriptors: vec![FileDescriptorData::CSV(CSVData {
+            field_types,
+            ..CSVData::new("fixtures/Outages.csv")
+        })],
+        ..Default::default()
+    };
+
+    let context = Context::convert_ast(&ast).unwrap();
+    let df = context.ctx.table("Outages").await.unwrap();
+    let schema = df.schema();
+
+    assert_eq!(
+        schema
+            .field_with_unqualified_name("OutageDate")
+            .unwrap()
+            .data_type(),
+        &DataType::Date32
+    );
+    assert_eq!(
+        schema
+            .field_with_unqualified_name("OutageStart")
+            .unwrap()
+            .data_type(),
+        &DataType::Time64(TimeUnit::Nanosecond)
+    );
+    assert_eq!(
+        schema
+            .field_with_unqualified_name("OutageStartTime")
+            .unwrap()
+            .data_type(),
+        &DataType::Timestamp(TimeUnit::Nanosecond, None)
+    );
+
+    let batches = df.collect().await.unwrap();
+    let dates = batches[0].column(1);
+    assert_eq!(dates.null_count(), 1);
+    assert!(dates.is_null(2));
+}
+
+#[test]
+fn temporal_types_not_nullable_test() {
+    use crate::grammar::ast::*;
+
+    let mut field_types = HashMap::new();
+    field_types.insert("OutageDate", DataTypeDescriptor::Date(false, "%Y-%m-%d"));
+
+    let ast = Ast {
+        file_descriptors: vec![FileDescriptorData::CSV(CSVData {
+            field_types,
+            ..CSVData::new("fixtures/Outages.csv")
+        })],
+        ..Default::default()
+    };
+
+    match Context::convert_ast(&ast) {
+        Err(TldrError::TldrCouldNotParseValue { path, column, line }) => {
+            assert_eq!(path, "fixtures/Outages.csv");
+            assert_eq!(column, "OutageDate");
+            assert_eq!(line, 4);
+        }
+        _ => panic!("expected a parse error in line 4"),
+    }
+}
+
+#[test]
+fn cast_error_test() {
+    let mut field_types = HashMap::new();
+    field_types.insert("Quantity", DataTypeDescriptor::Int64(true));
+
+    let text_field = Field::new("Quantity", DataType::Utf8, true);
+    let field = Field::new("Quantity", DataType::Int64, true);
+    let read_schema = Arc::new(Schema::new(vec![text_field]));
+    let schema = Arc::new(Schema::new(vec![field]));
+    let values: ArrayRef = Arc::new(StringArray::from(vec![Some("1"), None, Some("abc")]));
+    let batch = RecordBatch::try_new(read_schema, vec![values]).unwrap();
+
+    // a value that cannot be cast is an error even in a nullable column, only nulls stay null
+    match convert_columns("Sales.csv", &field_types, schema, batch, Some(2)) {
+        Err(TldrError::TldrCouldNotParseValue { path, column, line }) => {
+            assert_eq!(path, "Sales.csv");
+            assert_eq!(column, "Quantity");
+            assert_eq!(line, 4);
+        }
+        _ => panic!("expected a parse error in line 4"),
+    }
+}
+
+#[tokio::test]
+async fn csv_dialect_test() {
+    use crate::grammar::parser::parse_script;
+    use arrow::util::pretty::pretty_format_batches;
+
+    for materialize in [true, false] {
+        let script = format!(
+            "load_files (
+                (
+                    csv_file_name: \"fixtures/Kunden.csv\",
+                    delimiter: \";\",
+                    skip_rows: 2,
+                    comment: \"#\",
+                    null_values: (\"\", \"n/a\"),
+                    truncated_rows: true,
+                    encoding: \"latin1\",
+                    materialize: {},
+                ),
+            )",
+            materialize
+        );
+        let ast = parse_script(&script).unwrap();
+        let context = Context::convert_ast(&ast).unwrap();
+
+        let batches = context
+            .ctx
+            .sql("SELECT * FROM \"Kunden\"")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            pretty_format_batches(&batches).unwrap().to_string(),
+            "\
++---------------+------------+--------+------------------+
+| Kunde         | Ort        | Umsatz | Bemerkung        |
++---------------+------------+--------+------------------+
+| Müller; Söhne | Köln       | 1200.5 | sagt \"grüß Gott\" |
+| Bäckerei Groß | Düsseldorf |        | keine            |
+| Café Ängel    | Straßburg  | 310.25 |                  |
++---------------+------------+--------+------------------+"
+        );
+    }
+
+    // without truncated_rows the last row is an error
+    let script = "load_files (
+            (
+                csv_file_name: \"fixtures/Kunden.csv\",
+                delimiter: \";\",
+                skip_rows: 2,
+                comment: \"#\",
+                encoding: \"cp1252\",
+            ),
+        )";
+    let ast = parse_script(script).unwrap();
+    assert!(Context::convert_ast(&ast).is_err());
+}
+
+#[tokio::test]
+async fn compression_test() {
+    use crate::grammar::parser::parse_script;
+
+    let script = "load_files (
+            (csv_file_name: \"fixtures/compressed/FactSales.csv.gz\"),
+            (csv_file_name: \"fixtures/compressed\", table_name: \"AllSales\", file_name_column: \"SourceFile\"),
+            (csv_file_name: \"fixtures/compressed/FactSales.csv.zst\", table_name: \"LazySales\", materialize: false),
+            (json_file_name: \"fixtures/compressed/Events.ndjson.zst\"),
+            (json_file_name: \"fixtures/compressed/EventArray.json.gz\"),
+            (
+                json_file_name: \"fixtures/compressed/Events.ndjson.gzip\",
+                compression: gzip,
+                table_name: \"GzipEvents\",
+                materialize: false,
+            ),
+        )";
+    let ast = parse_script(script).unwrap();
+    let context = Context::convert_ast(&ast).unwrap();
+
+    let mut counts = Vec::new();
+    for (table, column) in [
+        ("FactSales", "SalesKey"),
+        ("AllSales", "SourceFile"),
+        ("LazySales", "SalesKey"),
+        ("Events", "EventKey"),
+        ("EventArray", "EventKey"),
+        ("GzipEvents", "EventKey"),
+    ] {
+        let batches = context
+            .ctx
+            .sql(&format!(
+                "SELECT count(*), count(DISTINCT \"{}\") FROM \"{}\"",
+                column, table
+            ))
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let count = |i: usize| {
+            batches[0]
+                .column(i)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap()
+                .value(0)
+        };
+        counts.push((table, count(0), count(1)));
+    }
+    assert_eq!(
+        counts,
+        vec![
+            ("FactSales", 5, 5),
+            // gz, bz2, xz and zst
+            ("AllSales", 20, 4),
+            ("LazySales", 5, 5),
+            ("Events", 2, 2),
+            ("EventArray", 2, 2),
+            ("GzipEvents", 2, 2),
+        ]
+    );
+
+    // a compressed csv file is recognised by a gzip extension or by its compression block
+    let dir = std::env::temp_dir().join(format!("tldr_compression_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for name in ["Sales.csv.gzip", "Sales.dat"] {
+        fs::copy("fixtures/compressed/FactSales.csv.gz", dir.join(name)).unwrap();
+    }
+    fs::copy("fixtures/FactSales.csv", dir.join("Sales.txt")).unwrap();
+    let dir = dir.to_str().unwrap();
+
+    let script = format!(
+        "load_files (
+            (csv_file_name: \"{0}/Sales.csv.gzip\"),
+            (csv_file_name: \"{0}/Sales.dat\", compression: gzip, table_name: \"DatSales\"),
+        )",
+        dir
+    );
+    let ast = parse_script(&script).unwrap();
+    let context = Context::convert_ast(&ast).unwrap();
+    for table in ["Sales", "DatSales"] {
+        let batches = context
+            .ctx
+            .sql(&format!("SELECT count(*) FROM \"{}\"", table))
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let count = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap()
+            .value(0);
+        assert_eq!(count, 5);
+    }
+
+    // any other file is an error instead of being skipped
+    let script = format!("load_files ( (csv_file_name: \"{}/Sales.txt\") )", dir);
+    let ast = parse_script(&script).unwrap();
+    assert!(matches!(
+        Context::convert_ast(&ast),
+        Err(TldrError::TldrNotACsvFile { .. })
+    ));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn parquet_test() {
+    use crate::grammar::ast::*;
+    use arrow::array::{Float64Array, Int32Array};
+    use datafusion::parquet::arrow::ArrowWriter;
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("ProductKey", DataType::Int32, false),
+        Field::new("Color", DataType::Utf8, true),
+        Field::new("SalesAmount", DataType::Float64, true),
+    ]));
+    let batch = RecordBatch::try_new(
+        schema.clone(),
+        vec![
+            Arc::new(Int32Array::from(vec![1, 2, 3])),
+            Arc::new(StringArray::from(vec!["Red", "Blue", "Red"])),
+            Arc::new(Float64Array::from(vec![10.0, 20.0, 30.0])),
+        ],
+    )
+    .unwrap();
+
+    let path =
+        std::env::temp_dir().join(format!("tldr_parquet_test_{}.parquet", std::process::id()));
+    let mut writer = ArrowWriter::try_new(File::create(&path).unwrap(), schema, None).unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+
+    for materialize in [true, false] {
+        let mut field_types = HashMap::new();
+        field_types.insert("ProductKey", DataTypeDescriptor::Int64(false));
+
+        let ast = Ast {
+            file_descriptors: vec![FileDescriptorData::Parquet(ParquetData {
+                parquet_file_path: path.to_str().unwrap(),
+                columns: Some(vec!["ProductKey", "SalesAmount"]),
+                field_types,
+                materialize,
+                table_options: TableOptions::default(),
+            })],
+            ..Default::default()
+        };
+
+        let context = Context::convert_ast(&ast).unwrap();
+        let df = context.ctx.table("tldr_parquet_test").await.unwrap();
+
+        assert_eq!(df.schema().fields().len(), 2);
+        assert_eq!(
+            df.schema()
+                .field_with_unqualified_name("ProductKey")
+                .unwrap()
+                .data_type(),
+            &DataType::Int64
+        );
+        assert!(df.schema().field_with_unqualified_name("Color").is_err());
+
+        let batches = df.collect().await.unwrap();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 3);
+    }
+}
+
+#[tokio::test]
+async fn avro_test() {
+    use crate::grammar::ast::*;
+
+    for materialize in [true, false] {
+        let mut field_types = HashMap::new();
+        field_types.insert(
+            "Timestamp",
+            DataTypeDescriptor::Datetime(false, "%Y-%m-%dT%H:%M:%S"),
+        );
+        field_types.insert("Duration", DataTypeDescriptor::Float32(true));
+
+        let ast = Ast {
+            file_descriptors: vec![FileDescriptorData::Avro(AvroData {
+                avro_file_path: "fixtures/Events.avro",
+                field_types,
+                materialize,
+                table_options: TableOptions::default(),
+            })],
+            ..Default::default()
+        };
+
+        let context = Context::convert_ast(&ast).unwrap();
+        let fields = context.tables["Events"]
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| (f.name().clone(), f.data_type().clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                ("EventKey".to_string(), DataType::Int64),
+                ("Kind".to_string(), DataType::Utf8),
+                (
+                    "Timestamp".to_string(),
+                    DataType::Timestamp(TimeUnit::Nanosecond, None)
+                ),
+                ("Duration".to_string(), DataType::Float32),
+            ]
+        );
+
+        let batches = context
+            .ctx
+            .sql("SELECT count(*) FROM \"Events\" WHERE \"Kind\" = 'login' AND \"Timestamp\" > '2024-03-01T12:00:00'")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let count = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap()
+            .value(0);
+        assert_eq!(count, 1);
+    }
+}
+
+#[tokio::test]
+async fn arrow_test() {
+    use crate::grammar::ast::*;
+    use arrow::{
+        array::Int32Array,
+        ipc::writer::{FileWriter, StreamWriter},
+    };
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("OutageKey", DataType::Int32, false),
+        Field::new("OutageDate", DataType::Utf8, true),
+    ]));
+    let batch = RecordBatch::try_new(
+        schema.clone(),
+        vec![
+            Arc::new(Int32Array::from(vec![1, 2, 3])),
+            Arc::new(StringArray::from(vec![
+                Some("2009-01-01"),
+                Some("2009-01-02"),
+                None,
+            ])),
+        ],
+    )
+    .unwrap();
+
+    let file_path =
+        std::env::temp_dir().join(format!("tldr_arrow_test_{}.arrow", std::process::id()));
+    let mut writer = FileWriter::try_new(File::create(&file_path).unwrap(), &schema).unwrap();
+    writer.write(&batch).unwrap();
+    writer.finish().unwrap();
+
+    let stream_path = std::env::temp_dir().join(format!(
+        "tldr_arrow_stream_test_{}.arrows",
+        std::process::id()
+    ));
+    let mut writer = StreamWriter::try_new(File::create(&stream_path).unwrap(), &schema).unwrap();
+    writer.write(&batch).unwrap();
+    writer.finish().unwrap();
+
+    for path in [&file_path, &stream_path] {
+        let mut field_types = HashMap::new();
+        field_types.insert("OutageKey", DataTypeDescriptor::Int64(false));
+        field_types.insert("OutageDate", DataTypeDescriptor::Date(true, "%Y-%m-%d"));
+
+        let ast = Ast {
+            file_descriptors: vec![FileDescriptorData::Arrow(ArrowData {
+                arrow_file_path: path.to_str().unwrap(),
+                field_types,
+                table_options: TableOptions {
+                    table_name: Some("Outages"),
+                    ..Default::default()
+                },
+            })],
+            ..Default::default()
+        };
+
+        let context = Context::convert_ast(&ast).unwrap();
+        let schema = context.tables["Outages"].schema();
+        assert_eq!(schema.field(0).data_type(), &DataType::Int64);
+        assert_eq!(schema.field(1).data_type(), &DataType::Date32);
+
+        let batches = context
+            .ctx
+            .table("\"Outages\"")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(batches[0].num_rows(), 3);
+        assert_eq!(batches[0].column(1).null_count(), 1);
+    }
+}
+
+#[tokio::test]
+async fn json_test() {
+    use crate::grammar::ast::*;
+
+    for (file, table) in [
+        ("fixtures/Events.ndjson", "Events"),
+        ("fixtures/EventArray.json", "EventArray"),
+    ] {
+        let mut field_types = HashMap::new();
+        field_types.insert(
+            "Timestamp",
+            DataTypeDescriptor::Datetime(false, "%Y-%m-%dT%H:%M:%S"),
+        );
+
+        let ast = Ast {
+            file_descriptors: vec![FileDescriptorData::JSON(JSONData {
+                json_file_path: file,
+                field_types,
+                max_read_records: Some(100),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
+            })],
+            ..Default::default()
+        };
+
+        let context = Context::convert_ast(&ast).unwrap();
+        let df = context.ctx.table(table).await.unwrap();
+
+        assert_eq!(
+            df.schema()
+                .field_with_unqualified_name("EventKey")
+                .unwrap()
+                .data_type(),
+            &DataType::Int64
+        );
+        assert_eq!(
+            df.schema()
+                .field_with_unqualified_name("Timestamp")
+                .unwrap()
+                .data_type(),
+            &DataType::Timestamp(TimeUnit::Nanosecond, None)
+        );
+
+        let batches = df.collect().await.unwrap();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 2);
+    }
+}
+
+#[tokio::test]
+async fn lazy_table_test() {
+    use crate::grammar::ast::*;
+    use arrow::compute::concat_batches;
+
+    let load = |file_descriptor: FileDescriptorData, table: &'static str| async move {
+        let ast = Ast {
+            file_descriptors: vec![file_descriptor],
+            ..Default::default()
+        };
+        let context = Context::convert_ast(&ast).unwrap();
+        let df = context.ctx.table(table).await.unwrap();
+        let batches = df.collect().await.unwrap();
+        concat_batches(&batches[0].schema(), &batches).unwrap()
+    };
+
+    for materialize in [true, false] {
+        let mut field_types = HashMap::new();
+        field_types.insert("OutageDate", DataTypeDescriptor::Date(true, "%Y-%m-%d"));
+        field_types.insert(
+            "OutageStartTime",
+            DataTypeDescriptor::Datetime(false, "%Y-%m-%d %H:%M:%S"),
+        );
+        field_types.insert("OutageKey", DataTypeDescriptor::Int32(false));
+
+        let outages = load(
+            FileDescriptorData::CSV(CSVData {
+                field_types,
+                materialize,
+                table_options: TableOptions::default(),
+                ..CSVData::new("fixtures/Outages.csv")
+            }),
+            "Outages",
+        )
+        .await;
+        assert_eq!(outages.num_rows(), 3);
+        assert_eq!(outages.schema().field(0).data_type(), &DataType::Int32);
+        assert_eq!(outages.schema().field(1).data_type(), &DataType::Date32);
+        assert!(outages.column(1).is_null(2));
+
+        let mut field_types = HashMap::new();
+        field_types.insert(
+            "Timestamp",
+            DataTypeDescriptor::Datetime(false, "%Y-%m-%dT%H:%M:%S"),
+        );
+
+        let events = load(
+            FileDescriptorData::JSON(JSONData {
+                json_file_path: "fixtures/Events.ndjson",
+                field_types,
+                max_read_records: Some(100),
+                compression: None,
+                materialize,
+                table_options: TableOptions::default(),
+            }),
+            "Events",
+        )
+        .await;
+        assert_eq!(events.num_rows(), 2);
+        assert_eq!(
+            events
+                .schema()
+                .field_with_name("Timestamp")
+                .unwrap()
+                .data_type(),
+            &DataType::Timestamp(TimeUnit::Nanosecond, None)
+        );
+    }
+
+    // values that cannot be parsed are reported when the table is scanned
+    let mut field_types = HashMap::new();
+    field_types.insert("OutageDate", DataTypeDescriptor::Date(false, "%Y-%m-%d"));
+
+    let ast = Ast {
+        file_descriptors: vec![FileDescriptorData::CSV(CSVData {
+            field_types,
+            materialize: false,
+            table_options: TableOptions::default(),
+            ..CSVData::new("fixtures/Outages.csv")
+        })],
+        ..Default::default()
+    };
+
+    let context = Context::convert_ast(&ast).unwrap();
+    let df = context.ctx.table("Outages").await.unwrap();
+    // the partitions of a scan do not know their lines, the value is reported instead
+    let error = df.collect().await.unwrap_err().to_string();
+    assert!(error.contains("could not parse the value n/a of column OutageDate"));
+}
+
+#[test]
+fn parallel_loading_test() {
+    use crate::grammar::ast::*;
+
+    let ast = Ast {
+        file_descriptors: vec![
+            FileDescriptorData::CSV(CSVData::new("fixtures/FactSales.csv")),
+            FileDescriptorData::CSV(CSVData::new("fixtures/Missing.csv")),
+            FileDescriptorData::CSV(CSVData::new("fixtures/DimProduct.csv")),
+            FileDescriptorData::CSV(CSVData::new("fixtures/DimMissing.csv")),
+        ],
+        ..Default::default()
+    };
+
+    for parallelism in [1, 2, 8] {
+        match Context::convert_ast_with_options(&ast, &LoadOptions { parallelism }) {
+            Err(TldrError::TldrCouldNotLoadFiles { errors }) => {
+                let paths = errors
+                    .iter()
+                    .map(|e| match e {
+                        TldrError::TldrFileNotfound { path } => path.as_str(),
+                        _ => panic!("expected a missing file, got {:?}", e),
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    paths,
+                    vec!["fixtures/Missing.csv", "fixtures/DimMissing.csv"]
+                );
+            }
+            _ => panic!("expected both missing files to be reported"),
+        }
+    }
+
+    let ast = Ast {
+        file_descriptors: vec![
+            FileDescriptorData::CSV(CSVData::new("fixtures/FactSales.csv")),
+            FileDescriptorData::CSV(CSVData::new("fixtures/DimProduct.csv")),
+            FileDescriptorData::CSV(CSVData::new("fixtures/DimStore.csv")),
+            FileDescriptorData::CSV(CSVData::new("fixtures/DimDate.csv")),
+        ],
+        ..Default::default()
+    };
+
+    let context = Context::convert_ast_with_options(&ast, &LoadOptions { parallelism: 3 }).unwrap();
+    let mut tables = context.tables.keys().collect::<Vec<_>>();
+    tables.sort();
+    assert_eq!(
+        tables,
+        vec!["DimDate", "DimProduct", "DimStore", "FactSales"]
+    );
+}
+
+#[tokio::test]
+async fn file_patterns_test() {
+    use crate::grammar::ast::*;
+    use arrow::util::pretty::pretty_format_batches;
+
+    for materialize in [true, false] {
+        let ast = Ast {
+            file_descriptors: vec![
+                FileDescriptorData::CSV(CSVData {
+                    materialize,
+                    table_options: TableOptions {
+                        table_name: Some("DailySales"),
+                        file_name_column: Some("SourceFile"),
+                        partition_columns: vec!["year", "month"],
+                        ..Default::default()
+                    },
+                    ..CSVData::new("fixtures/daily/*/*/Sales_*.csv")
+                }),
+                FileDescriptorData::CSV(CSVData {
+                    materialize,
+                    ..CSVData::new("fixtures/daily")
+                }),
+            ],
+            ..Default::default()
+        };
+
+        let context = Context::convert_ast(&ast).unwrap();
+        let batches = context
+            .ctx
+            .sql("SELECT \"SourceFile\", year, month, \"SalesKey\" FROM \"DailySales\" ORDER BY \"SalesKey\"")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            pretty_format_batches(&batches).unwrap().to_string(),
+            [
+                "+--------------------------------------------------------+------+-------+----------+",
+                "| SourceFile                                             | year | month | SalesKey |",
+                "+--------------------------------------------------------+------+-------+----------+",
+                "| fixtures/daily/year=2008/month=12/Sales_2008-12-31.csv | 2008 | 12    | 1        |",
+                "| fixtures/daily/year=2009/month=01/Sales_2009-01-01.csv | 2009 | 01    | 2        |",
+                "| fixtures/daily/year=2009/month=01/Sales_2009-01-01.csv | 2009 | 01    | 3        |",
+                "| fixtures/daily/year=2009/month=01/Sales_2009-01-02.csv | 2009 | 01    | 4        |",
+                "| fixtures/daily/year=2009/month=01/Sales_2009-01-02.csv | 2009 | 01    | 5        |",
+                "+--------------------------------------------------------+------+-------+----------+",
+            ]
+            .join("\n")
+        );
+
+        let batches = context
+            .ctx
+            .sql("SELECT sum(\"SalesAmount\") FROM daily")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(batches[0].num_rows(), 1);
+    }
+
+    // a glob pattern has no file name the table could be named after
+    let ast = Ast {
+        file_descriptors: vec![FileDescriptorData::CSV(CSVData::new(
+            "fixtures/daily/*/*/Sales_*.csv",
+        ))],
+        ..Default::default()
+    };
+    assert!(matches!(
+        Context::convert_ast(&ast),
+        Err(TldrError::TldrFileNameWithoutStem { .. })
+    ));
+
+    // every file has to be in a partition directory
+    let ast = Ast {
+        file_descriptors: vec![FileDescriptorData::CSV(CSVData {
+            table_options: TableOptions {
+                partition_columns: vec!["region"],
+                ..Default::default()
+            },
+            ..CSVData::new("fixtures/daily")
+        })],
+        ..Default::default()
+    };
+    assert!(matches!(
+        Context::convert_ast(&ast),
+        Err(TldrError::TldrMissingPartitionValue { .. })
+    ));
+}
+
+/// the library of the sqlite odbc driver, from TLDR_SQLITE_ODBC_DRIVER (set by the dev shell)
+/// or the places the usual packages install it to
+#[cfg(test)]
+fn sqlite_odbc_driver() -> Option<String> {
+    if let Ok(driver) = std::env::var("TLDR_SQLITE_ODBC_DRIVER") {
+        return Some(driver);
+    }
+    [
+        "/usr/lib/x86_64-linux-gnu/odbc/libsqlite3odbc.so",
+        "/usr/lib/aarch64-linux-gnu/odbc/libsqlite3odbc.so",
+        "/usr/lib64/libsqlite3odbc.so",
+        "/usr/lib/libsqlite3odbc.so",
+        "/usr/local/lib/libsqlite3odbc.so",
+        "/opt/homebrew/lib/libsqlite3odbc.dylib",
+        "/usr/local/lib/libsqlite3odbc.dylib",
+    ]
+    .into_iter()
+    .find(|path| Path::new(path).is_file())
+    .map(str::to_string)
+}
+
+#[tokio::test]
+async fn odbc_test() {
+    use crate::grammar::parser::parse_script;
+
+    // the driver is named by its library, so it does not have to be registered with the
+    // driver manager
+    let driver = match sqlite_odbc_driver() {
+        Some(driver) => driver,
+        None => {
+            eprintln!("skipping odbc_test: no sqlite odbc driver, set TLDR_SQLITE_ODBC_DRIVER");
+            return;
+        }
+    };
+    let connection_string = format!("Driver={{{}}};Database=fixtures/contoso.db", driver);
+
+    let script = format!(
+        "load_files (
+            (csv_file_name: \"fixtures/FactSales.csv\"),
+            odbc(
+                connection_string: \"{}\",
+                query: \"SELECT ProductKey, ProductName, Color FROM DimProduct\",
+                batch_size: 2,
+                table_name: \"DimProduct\",
+            ),
+        )
+        relationships (
+            FactSales.ProductKey -> DimProduct.ProductKey,
+        )",
+        connection_string
+    );
+    let ast = parse_script(&script).unwrap();
+    let context = Context::convert_ast(&ast).unwrap();
+
+    assert!(context
+        .plan_query("DimProduct.Color, sum(FactSales.SalesAmount)")
+        .is_ok());
+
+    let batches = context
+        .ctx
+        .sql("SELECT sum(\"SalesAmount\") FROM \"FactSales\" JOIN \"DimProduct\" USING (\"ProductKey\") WHERE \"Color\" = 'Black'")
+        .await
+        .unwrap()
+        .collect()
+        .await
+        .unwrap();
+    let total = batches[0]
+        .column(0)
+        .as_any()
+        .downcast_ref::<arrow::array::Float64Array>()
+        .unwrap()
+        .value(0);
+    assert_eq!(total, 350.0);
+
+    // the name of a database table cannot be derived from its query
+    let script = format!(
+        "load_files (
+            odbc(
+                connection_string: \"{}\",
+                query: \"SELECT * FROM DimStore\",
+            ),
+        )",
+        connection_string
+    );
+    let ast = parse_script(&script).unwrap();
+    assert!(matches!(
+        Context::convert_ast(&ast),
+        Err(TldrError::TldrFileNameWithoutStem { .. })
+    ));
+
+    let script = format!(
+        "load_files (
+            odbc(
+                connection_string: \"{}\",
+                query: \"SELECT * FROM DimMissing\",
+                table_name: \"DimMissing\",
+            ),
+        )",
+        connection_string
+    );
+    let ast = parse_script(&script).unwrap();
+    assert!(matches!(
+        Context::convert_ast(&ast),
+        Err(TldrError::TldrCouldNotQueryDatabase { .. })
+    ));
+}
+
+#[tokio::test]
+async fn sqlite_test() {
+    use crate::grammar::parser::parse_script;
+
+    let script = "load_files (
+            (csv_file_name: \"fixtures/FactSales.csv\"),
+            sqlite( path: \"fixtures/contoso.db\", table: \"DimProduct\" ),
+            sqlite(
+                path: \"fixtures/contoso.db\",
+                table: \"DimPromotion\",
+                field_types: (
+                    \"PromotionKey\": int32(is_nullable: false),
+                    \"StartDate\": date(format: \"%Y-%m-%d\"),
+                ),
+            ),
+            sqlite(
+                path: \"fixtures/contoso.db\",
+                query: \"SELECT ProductKey, upper(ProductName) AS Name FROM DimProduct WHERE Color = 'Black'\",
+                table_name: \"BlackProducts\",
+            ),
+        )
+        relationships (
+            FactSales.ProductKey -> DimProduct.ProductKey,
+        )";
+    let ast = parse_script(script).unwrap();
+    let context = Context::convert_ast(&ast).unwrap();
+
+    let schema = context.tables["DimPromotion"].schema();
+    let fields = schema
+        .fields()
+        .iter()
+        .map(|f| (f.name().as_str(), f.data_type().clone(), f.is_nullable()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        vec![
+            ("PromotionKey", DataType::Int32, false),
+            ("PromotionName", DataType::Utf8, true),
+            ("StartDate", DataType::Date32, true),
+            ("DiscountPercent", DataType::Float64, true),
+            ("IsActive", DataType::Boolean, true),
+            // no declared type, text and integer values
+            ("Note", DataType::Utf8, true),
+        ]
+    );
+
+    let schema = context.tables["BlackProducts"].schema();
+    assert_eq!(schema.field(0).data_type(), &DataType::Int64);
+    assert_eq!(schema.field(1).data_type(), &DataType::Utf8);
+
+    let batches = context
+        .ctx
+        .sql("SELECT sum(\"SalesAmount\") FROM \"FactSales\" JOIN \"BlackProducts\" USING (\"ProductKey\")")
+        .await
+        .unwrap()
+        .collect()
+        .await
+        .unwrap();
+    let total = batches[0]
+        .column(0)
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .unwrap()
+        .value(0);
+    assert_eq!(total, 350.0);
+
+    let script = "load_files ( sqlite( path: \"fixtures/contoso.db\", table: \"DimMissing\" ) )";
+    let ast = parse_script(script).unwrap();
+    assert!(matches!(
+        Context::convert_ast(&ast),
+        Err(TldrError::TldrCouldNotReadFile { .. })
+    ));
+
+    let script = "load_files ( sqlite( path: \"fixtures/missing.db\", table: \"DimProduct\" ) )";
+    let ast = parse_script(script).unwrap();
+    assert!(matches!(
+        Context::convert_ast(&ast),
+        Err(TldrError::TldrFileNotfound { .. })
+    ));
+}
+
+#[tokio::test]
+async fn sqlite_mismatched_values_test() {
+    use crate::grammar::parser::parse_script;
+
+    // sqlite keeps text in an INTEGER column
+    let path = std::env::temp_dir().join(format!(
+        "tldr_sqlite_mismatched_test_{}.db",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let connection = Connection::open(&path).unwrap();
+    connection
+        .execute_batch(
+            "CREATE TABLE Readings (Id INTEGER, Value INTEGER);
+            INSERT INTO Readings VALUES (1, 10), (2, NULL), (3, 'n/a');",
+        )
+        .unwrap();
+    drop(connection);
+    let path = path.to_str().unwrap();
+
+    let script = format!(
+        "load_files ( sqlite( path: \"{}\", table: \"Readings\" ) )",
+        path
+    );
+    let ast = parse_script(&script).unwrap();
+    match Context::convert_ast(&ast) {
+        Err(TldrError::TldrCouldNotParseValue {
+            path: p,
+            column,
+            line,
+        }) => {
+            assert_eq!(p, format!("{}, table Readings", path));
+            assert_eq!(column, "Value");
+            assert_eq!(line, 3);
+        }
+        _ => panic!("expected a parse error in row 3"),
+    }
+
+    // a nullable override turns the text into null, a string override keeps it
+    for (field_type, data_type, nulls) in [
+        ("int64(is_nullable: true)", DataType::Int64, 2),
+        ("string", DataType::Utf8, 1),
+    ] {
+        let script = format!(
+            "load_files ( sqlite(
+                path: \"{}\",
+                table: \"Readings\",
+                field_types: ( \"Value\": {} ),
+            ) )",
+            path, field_type
+        );
+        let ast = parse_script(&script).unwrap();
+        let context = Context::convert_ast(&ast).unwrap();
+        let batches = context
+            .ctx
+            .sql("SELECT \"Value\" FROM \"Readings\"")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(batches[0].column(0).data_type(), &data_type);
+        assert_eq!(batches[0].column(0).null_count(), nulls);
+    }
+}
+
+#[tokio::test]
+async fn xlsx_test() {
+    use crate::grammar::parser::parse_script;
+    use arrow::util::pretty::pretty_format_batches;
+
+    let script = "load_files (
+            xlsx(
+                path: \"fixtures/Budget.xlsx\",
+                field_types: (
+                    \"Planned\": float64(is_nullable: true),
+                    \"Closed\": datetime(format: \"%Y-%m-%d %H:%M\"),
+                ),
+            ),
+            xlsx( path: \"fixtures/Budget.xlsx\", sheet: \"Notes\", range: \"B3:C5\" ),
+            xlsx(
+                path: \"fixtures/Budget.xlsx\",
+                sheet: \"Notes\",
+                range: \"B3:C5\",
+                has_header: false,
+                table_name: \"RawNotes\",
+            ),
+            xlsx(
+                path: \"fixtures/Budget.xlsx\",
+                table_name: \"Starts\",
+                field_types: ( \"Start\": date(format: \"%d.%m.%Y\") ),
+            ),
+        )";
+    let ast = parse_script(script).unwrap();
+    let context = Context::convert_ast(&ast).unwrap();
+
+    let types = |table: &str| {
+        context.tables[table]
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| (f.name().clone(), f.data_type().clone()))
+            .collect::<Vec<_>>()
+    };
+    let timestamp = DataType::Timestamp(TimeUnit::Nanosecond, None);
+    assert_eq!(
+        types("Budget"),
+        vec![
+            ("Region".to_string(), DataType::Utf8),
+            ("Planned".to_string(), DataType::Float64),
+            ("Actual".to_string(), DataType::Float64),
+            ("Start".to_string(), DataType::Date32),
+            ("Closed".to_string(), timestamp),
+            ("Approved".to_string(), DataType::Boolean),
+        ]
+    );
+    assert_eq!(
+        types("Notes"),
+        vec![
+            ("Key".to_string(), DataType::Int64),
+            ("Note".to_string(), DataType::Utf8),
+        ]
+    );
+    assert_eq!(
+        types("RawNotes"),
+        vec![
+            ("column_1".to_string(), DataType::Utf8),
+            ("column_2".to_string(), DataType::Utf8),
+        ]
+    );
+    assert_eq!(types("Starts")[3].1, DataType::Date32);
+
+    let batches = context
+        .ctx
+        .sql("SELECT * FROM \"Budget\"")
+        .await
+        .unwrap()
+        .collect()
+        .await
+        .unwrap();
+    assert_eq!(
+        pretty_format_batches(&batches).unwrap().to_string(),
+        "\
++--------+---------+---------+------------+---------------------+----------+
+| Region | Planned | Actual  | Start      | Closed              | Approved |
++--------+---------+---------+------------+---------------------+----------+
+| North  | 1000.0  | 950.5   | 2009-01-01 | 2009-01-31T18:00:00 | true     |
+| South  | 2000.0  | 2100.25 | 2009-02-01 | 2009-02-28T17:30:00 | false    |
+| West   | 1500.0  |         | 2009-03-01 |                     | true     |
++--------+---------+---------+------------+---------------------+----------+"
+    );
+
+    let batches = context
+        .ctx
+        .sql("SELECT count(*) FROM \"Starts\" WHERE \"Start\" >= DATE '2009-02-01'")
+        .await
+        .unwrap()
+        .collect()
+        .await
+        .unwrap();
+    let count = batches[0]
+        .column(0)
+        .as_any()
+        .downcast_ref::<Int64Array>()
+        .unwrap()
+        .value(0);
+    assert_eq!(count, 2);
+
+    // a cell that does not fit the type of its column is an error, not a null
+    let script = "load_files ( xlsx(
+            path: \"fixtures/Budget.xlsx\",
+            sheet: \"Notes\",
+            range: \"B3:C7\",
+            field_types: ( \"Key\": int64 ),
+        ) )";
+    let ast = parse_script(script).unwrap();
+    match Context::convert_ast(&ast) {
+        Err(TldrError::TldrCouldNotConvertCell {
+            sheet, cell, value, ..
+        }) => {
+            assert_eq!(sheet, "Notes");
+            assert_eq!(cell, "B7");
+            assert_eq!(value, "total");
+        }
+        _ => panic!("expected the cell B7 not to convert"),
+    }
+
+    let script = "load_files ( xlsx( path: \"fixtures/Budget.xlsx\", sheet: \"Missing\" ) )";
+    let ast = parse_script(script).unwrap();
+    assert!(matches!(
+        Context::convert_ast(&ast),
+        Err(TldrError::TldrCouldNotReadFile { .. })
+    ));
+
+    let script = "load_files ( xlsx( path: \"fixtures/Budget.xlsx\", range: \"K1:L9\" ) )";
+    let ast = parse_script(script).unwrap();
+    assert!(matches!(
+        Context::convert_ast(&ast),
+        Err(TldrError::TldrCouldNotReadSchema { .. })
+    ));
+}
+
+#[tokio::test]
+async fn table_names_test() {
+    use crate::grammar::parser::parse_script;
+
+    let script = "load_files (
+            (csv_file_name: \"fixtures/FactSales.csv\", schema: \"contoso\"),
+            (csv_file_name: \"fixtures/FactSales.csv\", schema: \"archive\", alias: \"Sales\"),
+            (csv_file_name: \"fixtures/DimProduct.csv\", table_name: \"Products\"),
+        )
+        relationships (
+            \"contoso.FactSales\".ProductKey -> Products.ProductKey,
+        )";
+    let ast = parse_script(script).unwrap();
+    let context = Context::convert_ast(&ast).unwrap();
+
+    let mut tables = context.tables.keys().collect::<Vec<_>>();
+    tables.sort();
+    assert_eq!(
+        tables,
+        vec!["Products", "archive.Sales", "contoso.FactSales"]
+    );
+    assert_eq!(context.relationships[0].from_table, "contoso.FactSales");
+
+    let batches = context
+        .ctx
+        .sql("SELECT count(*) FROM contoso.\"FactSales\" JOIN archive.\"Sales\" USING (\"SalesKey\")")
+        .await
+        .unwrap()
+        .collect()
+        .await
+        .unwrap();
+    assert_eq!(batches[0].num_rows(), 1);
+
+    // the same name in the same schema
+    let script = "load_files (
+            (csv_file_name: \"fixtures/FactSales.csv\"),
+            (csv_file_name: \"fixtures/daily\", table_name: \"FactSales\"),
+        )";
+    let ast = parse_script(script).unwrap();
+    match Context::convert_ast(&ast) {
+        Err(TldrError::TldrDuplicateTable {
+            table,
+            first,
+            second,
+        }) => {
+            assert_eq!(table, "FactSales");
+            assert_eq!(first, "fixtures/FactSales.csv");
+            assert_eq!(second, "fixtures/daily");
+        }
+        _ => panic!("expected a duplicate table"),
+    }
+}
+
+#[test]
+fn relationships_test() {
+    use crate::grammar::{ast::*, parser::ast_parser};
+
+    let model = |relationships: &str| {
+        format!(
+            "load_files (
+                (csv_file_name: \"fixtures/FactSales.csv\"),
+                (csv_file_name: \"fixtures/DimProduct.csv\"),
+            )
+            relationships ( {} )",
+            relationships
+        )
+    };
+
+    let script = model("FactSales.ProductKey -> DimProduct.ProductKey");
+    let (_, ast) = ast_parser(Span::new(&script)).unwrap();
+    let context = Context::convert_ast(&ast).unwrap();
+    assert_eq!(
+        context.relationships,
+        vec![Relationship {
+            from_table: "FactSales".to_string(),
+            from_column: "ProductKey".to_string(),
+            to_table: "DimProduct".to_string(),
+            to_column: "ProductKey".to_string(),
+            cardinality: Cardinality::ManyToOne,
+            filter_direction: FilterDirection::Single,
+        }]
+    );
+
+    let script = model("FactSales.ProductId -> DimProduct.ProductKey");
+    let (_, ast) = ast_parser(Span::new(&script)).unwrap();
+    assert!(matches!(
+        Context::convert_ast(&ast),
+        Err(TldrError::TldrUnknownColumn { .. })
+    ));
+
+    let script = model("FactSales.SalesAmount -> DimProduct.ProductKey");
+    let (_, ast) = ast_parser(Span::new(&script)).unwrap();
+    assert!(matches!(
+        Context::convert_ast(&ast),
+        Err(TldrError::TldrKeyTypesDiffer { .. })
+    ));
+}
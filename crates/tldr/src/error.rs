@@ -1,12 +1,367 @@
+use arrow::{datatypes::DataType, error::ArrowError};
+use datafusion::error::DataFusionError;
+use miette::{Diagnostic, SourceSpan};
 use std::string::String;
+use thiserror::Error;
 
-#[derive(Debug)]
+/// a tldr script that could not be parsed
+#[derive(Debug, Error, Diagnostic)]
+#[error("could not parse the script at line {line}, column {column}: expected {expected}")]
+#[diagnostic(code(tldr::parse_error))]
+pub struct TldrParseError {
+    #[source_code]
+    pub script: String,
+    #[label("expected {expected}")]
+    pub span: SourceSpan,
+    pub line: u32,
+    pub column: usize,
+    pub expected: String,
+    #[help]
+    pub help: Option<String>,
+}
+
+/// the error that caused a tldr error, e.g. an io, arrow or parquet error
+pub type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Error, Diagnostic)]
 pub enum TldrError {
-    TldrFileNotfound(String),
-    TldrCouldNotReadFile(String),
-    TldrFileNameWithoutStem(String),
-    TldrCouldNotReadSchema(String),
-    TldrCouldNotMergeSchemas(String),
-    TldrCouldNotCreateMemTable(String),
-    TldrCouldNotRegisterTable(String),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    TldrParseError(#[from] TldrParseError),
+
+    #[error("file not found: {path}")]
+    #[diagnostic(
+        code(tldr::file_not_found),
+        help("relative paths are resolved against the working directory")
+    )]
+    TldrFileNotfound { path: String },
+
+    #[error("could not read {path}")]
+    #[diagnostic(code(tldr::could_not_read_file))]
+    TldrCouldNotReadFile {
+        path: String,
+        #[source]
+        source: BoxedError,
+    },
+
+    #[error("{path} is not a csv file")]
+    #[diagnostic(
+        code(tldr::not_a_csv_file),
+        help("give the file a csv extension or declare its compression with a compression block")
+    )]
+    TldrNotACsvFile { path: String },
+
+    #[error("could not derive a table name from {path}")]
+    #[diagnostic(
+        code(tldr::file_name_without_stem),
+        help("name the table with a table_name block")
+    )]
+    TldrFileNameWithoutStem { path: String },
+
+    #[error("the table {table} is loaded from both {first} and {second}")]
+    #[diagnostic(
+        code(tldr::duplicate_table),
+        help("name the tables with table_name blocks or put them into different schemas")
+    )]
+    TldrDuplicateTable {
+        table: String,
+        first: String,
+        second: String,
+    },
+
+    #[error("could not run the query {query} against the database")]
+    #[diagnostic(
+        code(tldr::could_not_query_database),
+        help("check the connection string and that the ODBC driver of the database is installed")
+    )]
+    TldrCouldNotQueryDatabase {
+        query: String,
+        #[source]
+        source: BoxedError,
+    },
+
+    #[error("could not read the schema of {path}")]
+    #[diagnostic(code(tldr::could_not_read_schema))]
+    TldrCouldNotReadSchema {
+        path: String,
+        #[source]
+        source: BoxedError,
+    },
+
+    #[error("could not apply the field type of column {column} to {path}")]
+    #[diagnostic(
+        code(tldr::could_not_merge_schemas),
+        help("the column has to exist in the file and its values have to fit the field type")
+    )]
+    TldrCouldNotMergeSchemas {
+        path: String,
+        column: String,
+        #[source]
+        source: ArrowError,
+    },
+
+    #[error("could not create a table from {path}")]
+    #[diagnostic(code(tldr::could_not_create_mem_table))]
+    TldrCouldNotCreateMemTable {
+        path: String,
+        #[source]
+        source: DataFusionError,
+    },
+
+    #[error("could not register table {table}")]
+    #[diagnostic(code(tldr::could_not_register_table))]
+    TldrCouldNotRegisterTable {
+        table: String,
+        #[source]
+        source: DataFusionError,
+    },
+
+    #[error("could not parse the value of column {column} in line {line} of {path}")]
+    #[diagnostic(
+        code(tldr::could_not_parse_value),
+        help("check the format of the field type, or declare the column as nullable")
+    )]
+    TldrCouldNotParseValue {
+        path: String,
+        column: String,
+        line: usize,
+    },
+
+    #[error("could not convert the cell {cell} of the worksheet {sheet} of {path}: {value}")]
+    #[diagnostic(
+        code(tldr::could_not_convert_cell),
+        help("empty the cell or declare the column as a string")
+    )]
+    TldrCouldNotConvertCell {
+        path: String,
+        sheet: String,
+        cell: String,
+        value: String,
+    },
+
+    #[error("could not parse the value {value} of column {column} in {path}")]
+    #[diagnostic(
+        code(tldr::could_not_parse_scanned_value),
+        help("check the format of the field type, or declare the column as nullable")
+    )]
+    TldrCouldNotParseScannedValue {
+        path: String,
+        column: String,
+        value: String,
+    },
+
+    #[error("{path} is not in a directory {column}=value")]
+    #[diagnostic(
+        code(tldr::missing_partition_value),
+        help("every file of the table has to be in a directory for each partition column")
+    )]
+    TldrMissingPartitionValue { path: String, column: String },
+
+    #[error("could not load {} files", .errors.len())]
+    #[diagnostic(code(tldr::could_not_load_files))]
+    TldrCouldNotLoadFiles {
+        #[related]
+        errors: Vec<TldrError>,
+    },
+
+    #[error("could not write the snapshot file {path}")]
+    #[diagnostic(code(tldr::could_not_write_snapshot))]
+    TldrCouldNotWriteSnapshot {
+        path: String,
+        #[source]
+        source: BoxedError,
+    },
+
+    #[error("the name {name} cannot be written to a snapshot")]
+    #[diagnostic(
+        code(tldr::unquotable_name),
+        help("rename the table or column without double quotes")
+    )]
+    TldrUnquotableName { name: String },
+
+    #[error("could not write the results to {path}")]
+    #[diagnostic(code(tldr::could_not_write_output))]
+    TldrCouldNotWriteOutput {
+        path: String,
+        #[source]
+        source: BoxedError,
+    },
+
+    #[error("no queries were given")]
+    #[diagnostic(
+        code(tldr::no_queries),
+        help("pass queries with --query or in a file with --query-file")
+    )]
+    TldrNoQueries,
+
+    #[error("parquet results need an output file and a single query")]
+    #[diagnostic(
+        code(tldr::parquet_needs_output),
+        help("write the results of each query to its own file with --output")
+    )]
+    TldrParquetNeedsOutput,
+
+    #[error("could not start the runtime")]
+    #[diagnostic(code(tldr::could_not_start_runtime))]
+    TldrCouldNotStartRuntime {
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("could not read the input")]
+    #[diagnostic(code(tldr::could_not_read_input))]
+    TldrCouldNotReadInput {
+        #[source]
+        source: rustyline::error::ReadlineError,
+    },
+
+    #[error("unknown command {command}")]
+    #[diagnostic(code(tldr::unknown_command), help(".help lists the commands"))]
+    TldrUnknownCommand { command: String },
+
+    #[error("unknown table {table}")]
+    #[diagnostic(code(tldr::unknown_table))]
+    TldrUnknownTable { table: String },
+
+    #[error("unknown column {table}.{column}")]
+    #[diagnostic(code(tldr::unknown_column))]
+    TldrUnknownColumn { table: String, column: String },
+
+    #[error("the key columns {from} ({from_type}) and {to} ({to_type}) have different types")]
+    #[diagnostic(
+        code(tldr::key_types_differ),
+        help("use field_types to load both columns with the same type")
+    )]
+    TldrKeyTypesDiffer {
+        from: String,
+        from_type: DataType,
+        to: String,
+        to_type: DataType,
+    },
+
+    #[error("could not parse the query {query}")]
+    #[diagnostic(code(tldr::could_not_parse_query))]
+    TldrCouldNotParseQuery { query: String },
+
+    #[error("the tables can be joined in more than one way: {}", .paths.join("; "))]
+    #[diagnostic(
+        code(tldr::ambiguous_join_path),
+        help("add a column of the table that should be joined on to the query")
+    )]
+    TldrAmbiguousJoinPath { paths: Vec<String> },
+
+    #[error("the tables {} cannot be joined", .tables.join(", "))]
+    #[diagnostic(
+        code(tldr::no_join_path),
+        help("declare the relationships between the tables")
+    )]
+    TldrNoJoinPath { tables: Vec<String> },
+
+    #[error("could not plan the query")]
+    #[diagnostic(code(tldr::could_not_plan_query))]
+    TldrCouldNotPlanQuery {
+        #[source]
+        source: DataFusionError,
+    },
+
+    #[error("could not run the query {query}")]
+    #[diagnostic(code(tldr::could_not_run_query))]
+    TldrCouldNotRunQuery {
+        query: String,
+        #[source]
+        source: DataFusionError,
+    },
+
+    #[error("invalid filter {filter}")]
+    #[diagnostic(
+        code(tldr::invalid_filter),
+        help("a filter must not aggregate and must only use the queried tables or a single other table")
+    )]
+    TldrInvalidFilter { filter: String },
+
+    #[error("the filter {filter} on {table} does not reach the queried tables")]
+    #[diagnostic(
+        code(tldr::unrelated_filter),
+        help("declare a relationship between the table of the filter and the queried tables")
+    )]
+    TldrUnrelatedFilter { filter: String, table: String },
+
+    #[error("the column {column} has to be given with its table")]
+    #[diagnostic(code(tldr::unqualified_column))]
+    TldrUnqualifiedColumn { column: String },
+
+    #[error("invalid calculated column {column}: {reason}")]
+    #[diagnostic(code(tldr::invalid_calculated_column))]
+    TldrInvalidCalculatedColumn { column: String, reason: String },
+
+    #[error("unknown measure [{measure}]")]
+    #[diagnostic(code(tldr::unknown_measure))]
+    TldrUnknownMeasure { measure: String },
+
+    #[error("the measure [{measure}] is defined more than once")]
+    #[diagnostic(code(tldr::duplicate_measure))]
+    TldrDuplicateMeasure { measure: String },
+
+    #[error("the measure [{measure}] has no view: {reason}")]
+    #[diagnostic(
+        code(tldr::missing_measure_view),
+        help("a measure has a view only if its tables can be joined")
+    )]
+    TldrMissingMeasureView {
+        measure: String,
+        reason: String,
+        #[source]
+        source: DataFusionError,
+    },
+
+    #[error("the measure [{measure}] references itself")]
+    #[diagnostic(code(tldr::cyclic_measure))]
+    TldrCyclicMeasure { measure: String },
+
+    #[error("the measure [{measure}] uses a column outside of an aggregate")]
+    #[diagnostic(code(tldr::measure_not_aggregated))]
+    TldrMeasureNotAggregated { measure: String },
+
+    #[error("the parameter ${parameter} can only be used in a named query")]
+    #[diagnostic(code(tldr::unexpected_parameter))]
+    TldrUnexpectedParameter { parameter: String },
+
+    #[error("invalid query {query}: {reason}")]
+    #[diagnostic(code(tldr::invalid_query))]
+    TldrInvalidQuery { query: String, reason: String },
+
+    #[error("unknown query {query}")]
+    #[diagnostic(
+        code(tldr::unknown_query),
+        help("named queries are defined in the queries section of the script")
+    )]
+    TldrUnknownQuery { query: String },
+
+    #[error("the query {query} has no parameter {parameter}")]
+    #[diagnostic(code(tldr::unknown_parameter))]
+    TldrUnknownParameter { query: String, parameter: String },
+
+    #[error("the query {query} needs a value for its parameter {parameter}")]
+    #[diagnostic(code(tldr::missing_parameter))]
+    TldrMissingParameter { query: String, parameter: String },
+
+    #[error("the value {value} of the parameter {parameter} of the query {query} is not a valid {data_type}")]
+    #[diagnostic(
+        code(tldr::invalid_parameter_value),
+        help("dates and times are parsed with the format string of the parameter")
+    )]
+    TldrInvalidParameterValue {
+        query: String,
+        parameter: String,
+        value: String,
+        data_type: DataType,
+    },
+
+    #[error("the measure [{measure}] has no valid type")]
+    #[diagnostic(code(tldr::measure_type_error))]
+    TldrMeasureTypeError {
+        measure: String,
+        #[source]
+        source: DataFusionError,
+    },
 }
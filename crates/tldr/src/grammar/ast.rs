@@ -1,5 +1,5 @@
 use arrow::datatypes::TimeUnit;
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap, fmt};
 
 use nom_locate::LocatedSpan;
 
@@ -55,6 +55,124 @@ impl<'a> DataTypeDescriptor<'a> {
             DataTypeDescriptor::Null => true,
         }
     }
+
+    /// Date, Time and Datetime are parsed from strings with a format string
+    pub fn is_temporal(&self) -> bool {
+        matches!(
+            self,
+            DataTypeDescriptor::Time(_, _)
+                | DataTypeDescriptor::Date(_, _)
+                | DataTypeDescriptor::Datetime(_, _)
+        )
+    }
+}
+
+/// the type in the syntax of the dsl
+impl<'a> fmt::Display for DataTypeDescriptor<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DataTypeDescriptor::UInt8(_) => "uint8",
+            DataTypeDescriptor::UInt16(_) => "uint16",
+            DataTypeDescriptor::UInt32(_) => "uint32",
+            DataTypeDescriptor::UInt64(_) => "uint64",
+            DataTypeDescriptor::Int8(_) => "int8",
+            DataTypeDescriptor::Int16(_) => "int16",
+            DataTypeDescriptor::Int32(_) => "int32",
+            DataTypeDescriptor::Int64(_) => "int64",
+            DataTypeDescriptor::Float32(_) => "float32",
+            DataTypeDescriptor::Float64(_) => "float64",
+            DataTypeDescriptor::Null => "null",
+            DataTypeDescriptor::Boolean(_) => "boolean",
+            DataTypeDescriptor::Binary(_) => "binary",
+            DataTypeDescriptor::String(_) => "string",
+            DataTypeDescriptor::Duration(_, _) => "duration",
+            DataTypeDescriptor::Time(_, _) => "time",
+            DataTypeDescriptor::Date(_, _) => "date",
+            DataTypeDescriptor::Datetime(_, _) => "datetime",
+        };
+
+        let mut parameters = Vec::new();
+        match self {
+            DataTypeDescriptor::Duration(_, unit) => {
+                let unit = match unit {
+                    TimeUnit::Nanosecond => "nanoseconds",
+                    TimeUnit::Microsecond => "microseconds",
+                    // seconds cannot be written in the dsl
+                    TimeUnit::Millisecond | TimeUnit::Second => "milliseconds",
+                };
+                parameters.push(format!("time_unit: {}", unit));
+            }
+            DataTypeDescriptor::Time(_, format)
+            | DataTypeDescriptor::Date(_, format)
+            | DataTypeDescriptor::Datetime(_, format) => {
+                parameters.push(format!("format: \"{}\"", format))
+            }
+            _ => {}
+        }
+        if !self.is_nullable() {
+            parameters.push("is_nullable: false".to_string());
+        }
+
+        if parameters.is_empty() {
+            write!(f, "{}", name)
+        } else {
+            write!(f, "{}({})", name, parameters.join(", "))
+        }
+    }
+}
+
+/// how the files of a file descriptor become a table, the same for all file types
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct TableOptions<'a> {
+    /// the name of the table, derived from the file path if None
+    pub table_name: Option<&'a str>,
+    /// the schema the table is registered in, the default schema if None
+    pub schema: Option<&'a str>,
+    /// a column that holds the path of the file each row was read from
+    pub file_name_column: Option<&'a str>,
+    /// hive-style partition columns, the values are taken from directories like year=2024
+    pub partition_columns: Vec<&'a str>,
+}
+
+/// the compression of a csv or json file
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+/// how the values of a csv file are written, beyond its delimiter
+#[derive(PartialEq, Debug)]
+pub struct CSVDialect<'a> {
+    pub quote: u8,
+    /// the character that escapes a quote in a quoted value, quotes are doubled if None
+    pub escape: Option<u8>,
+    /// lines starting with this character are skipped
+    pub comment: Option<u8>,
+    /// the values that are read as null, the empty value is null if there are none
+    pub null_values: Vec<&'a str>,
+    /// the number of lines before the header, or the first record if there is no header
+    pub skip_rows: usize,
+    /// whether rows with fewer values than the header are filled up with nulls
+    pub truncated_rows: bool,
+    /// the label of the encoding of the file like latin1 or cp1252, utf-8 if None
+    pub encoding: Option<&'a str>,
+}
+
+impl<'a> Default for CSVDialect<'a> {
+    fn default() -> Self {
+        CSVDialect {
+            quote: b'"',
+            escape: None,
+            comment: None,
+            null_values: Vec::new(),
+            skip_rows: 0,
+            truncated_rows: false,
+            encoding: None,
+        }
+    }
 }
 
 #[derive(PartialEq, Debug)]
@@ -64,16 +182,361 @@ pub struct CSVData<'a> {
     pub delimiter: u8,
     pub max_read_records: Option<usize>,
     pub has_header: bool,
+    pub dialect: CSVDialect<'a>,
+    /// the compression of the files, taken from their extension if None
+    pub compression: Option<Compression>,
+    /// whether the file is read into memory when it is loaded or whenever a query scans it
+    pub materialize: bool,
+    pub table_options: TableOptions<'a>,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct ParquetData<'a> {
+    pub parquet_file_path: &'a str,
+    /// the columns to load, all columns are loaded if None
+    pub columns: Option<Vec<&'a str>>,
+    pub field_types: HashMap<&'a str, DataTypeDescriptor<'a>>,
+    pub materialize: bool,
+    pub table_options: TableOptions<'a>,
+}
+
+/// newline-delimited json or a json array of objects
+#[derive(PartialEq, Debug)]
+pub struct JSONData<'a> {
+    pub json_file_path: &'a str,
+    pub field_types: HashMap<&'a str, DataTypeDescriptor<'a>>,
+    pub max_read_records: Option<usize>,
+    /// the compression of the files, taken from their extension if None
+    pub compression: Option<Compression>,
+    /// a json array is always materialized, as it has to be read as a whole
+    pub materialize: bool,
+    pub table_options: TableOptions<'a>,
+}
+
+/// an avro object container file
+#[derive(PartialEq, Debug)]
+pub struct AvroData<'a> {
+    pub avro_file_path: &'a str,
+    pub field_types: HashMap<&'a str, DataTypeDescriptor<'a>>,
+    pub materialize: bool,
+    pub table_options: TableOptions<'a>,
+}
+
+/// an arrow ipc file, in the file or the stream format
+#[derive(PartialEq, Debug)]
+pub struct ArrowData<'a> {
+    pub arrow_file_path: &'a str,
+    pub field_types: HashMap<&'a str, DataTypeDescriptor<'a>>,
+    pub table_options: TableOptions<'a>,
+}
+
+/// the result set of a query, read from a database through its ODBC driver
+#[derive(PartialEq, Debug)]
+pub struct ODBCData<'a> {
+    pub connection_string: &'a str,
+    pub query: &'a str,
+    /// the maximum number of rows that are fetched at once
+    pub batch_size: usize,
+    /// the maximum length of text columns in bytes, long values are truncated.
+    /// Columns without a length, like TEXT in SQLite, need it, None takes the length the driver reports
+    pub max_text_size: Option<usize>,
+    pub table_options: TableOptions<'a>,
+}
+
+/// the rows of a sqlite table, a whole table or the result set of a query
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SQLiteSource<'a> {
+    Table(&'a str),
+    Query(&'a str),
+}
+
+/// a table of a sqlite database file
+#[derive(PartialEq, Debug)]
+pub struct SQLiteData<'a> {
+    pub sqlite_file_path: &'a str,
+    pub source: SQLiteSource<'a>,
+    pub field_types: HashMap<&'a str, DataTypeDescriptor<'a>>,
+    pub table_options: TableOptions<'a>,
+}
+
+/// a rectangle of cells of a worksheet, rows and columns are counted from zero
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct CellRange {
+    /// row and column of the upper left cell
+    pub start: (u32, u32),
+    /// row and column of the lower right cell
+    pub end: (u32, u32),
+}
+
+/// a worksheet of an excel workbook
+#[derive(PartialEq, Debug)]
+pub struct XLSXData<'a> {
+    pub xlsx_file_path: &'a str,
+    /// the name of the worksheet, the first worksheet is read if None
+    pub sheet: Option<&'a str>,
+    /// the cells to read, all used cells of the worksheet are read if None
+    pub range: Option<CellRange>,
+    pub has_header: bool,
+    pub field_types: HashMap<&'a str, DataTypeDescriptor<'a>>,
+    pub table_options: TableOptions<'a>,
 }
 
 #[derive(PartialEq, Debug)]
 pub enum FileDescriptorData<'a> {
     CSV(CSVData<'a>),
+    Parquet(ParquetData<'a>),
+    JSON(JSONData<'a>),
+    Avro(AvroData<'a>),
+    Arrow(ArrowData<'a>),
+    ODBC(ODBCData<'a>),
+    SQLite(SQLiteData<'a>),
+    XLSX(XLSXData<'a>),
+}
+
+impl<'a> FileDescriptorData<'a> {
+    /// the path of the files of the table, or the query of a database table
+    pub fn file_path(&self) -> &'a str {
+        match self {
+            FileDescriptorData::CSV(data) => data.csv_file_path,
+            FileDescriptorData::Parquet(data) => data.parquet_file_path,
+            FileDescriptorData::JSON(data) => data.json_file_path,
+            FileDescriptorData::Avro(data) => data.avro_file_path,
+            FileDescriptorData::Arrow(data) => data.arrow_file_path,
+            FileDescriptorData::ODBC(data) => data.query,
+            FileDescriptorData::SQLite(data) => data.sqlite_file_path,
+            FileDescriptorData::XLSX(data) => data.xlsx_file_path,
+        }
+    }
+
+    pub fn table_options(&self) -> &TableOptions<'a> {
+        match self {
+            FileDescriptorData::CSV(data) => &data.table_options,
+            FileDescriptorData::Parquet(data) => &data.table_options,
+            FileDescriptorData::JSON(data) => &data.table_options,
+            FileDescriptorData::Avro(data) => &data.table_options,
+            FileDescriptorData::Arrow(data) => &data.table_options,
+            FileDescriptorData::ODBC(data) => &data.table_options,
+            FileDescriptorData::SQLite(data) => &data.table_options,
+            FileDescriptorData::XLSX(data) => &data.table_options,
+        }
+    }
+}
+
+/// a column of a loaded table, written as Table.Column in the dsl
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ColumnReference<'a> {
+    pub table: &'a str,
+    pub column: &'a str,
+}
+
+/// written as "table"."column", both names are quoted
+impl<'a> fmt::Display for ColumnReference<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\".\"{}\"", self.table, self.column)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Cardinality {
+    ManyToOne,
+    OneToOne,
+    ManyToMany,
+}
+
+/// the direction in which filters flow along a relationship.
+///
+/// Filters on the referenced table always flow to the referencing table, with `Both` they
+/// also flow from the referencing table to the referenced table.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum FilterDirection {
+    Single,
+    Both,
+}
+
+/// `from` references the key column `to`
+#[derive(PartialEq, Debug)]
+pub struct RelationshipData<'a> {
+    pub from: ColumnReference<'a>,
+    pub to: ColumnReference<'a>,
+    pub cardinality: Cardinality,
+    pub filter_direction: FilterDirection,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum AggregateFunction {
+    Sum,
+    Min,
+    Max,
+    Avg,
+    Count,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BinaryOperator {
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    /// string concatenation, written as || in the dsl
+    Concat,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum LiteralValue<'a> {
+    Int64(i64),
+    Float64(f64),
+    /// the string without its quotes, a doubled quote inside of it is a single quote
+    Utf8(Cow<'a, str>),
+}
+
+/// an expression over the columns of the loaded tables
+#[derive(PartialEq, Debug, Clone)]
+pub enum Expression<'a> {
+    Column(ColumnReference<'a>),
+    /// a column without its table, refers to the table of a calculated column
+    UnqualifiedColumn(&'a str),
+    Literal(LiteralValue<'a>),
+    /// reference to a measure by its name, written as [name] in the dsl
+    Measure(&'a str),
+    /// a parameter of a named query, written as $name in the dsl
+    Parameter(&'a str),
+    Aggregate(AggregateFunction, Box<Expression<'a>>),
+    Binary(Box<Expression<'a>>, BinaryOperator, Box<Expression<'a>>),
+}
+
+/// the expression in the syntax of the dsl, binary expressions are put in parentheses
+impl<'a> fmt::Display for Expression<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Column(c) => write!(f, "{}", c),
+            Expression::UnqualifiedColumn(c) => write!(f, "{}", c),
+            Expression::Literal(LiteralValue::Int64(i)) => write!(f, "{}", i),
+            // Debug keeps the fraction of whole numbers, 1.0 would be written as 1 otherwise
+            Expression::Literal(LiteralValue::Float64(v)) => write!(f, "{:?}", v),
+            Expression::Literal(LiteralValue::Utf8(s)) => write!(f, "'{}'", s.replace('\'', "''")),
+            Expression::Measure(name) => write!(f, "[{}]", name),
+            Expression::Parameter(name) => write!(f, "${}", name),
+            Expression::Aggregate(function, e) => {
+                let function = match function {
+                    AggregateFunction::Sum => "sum",
+                    AggregateFunction::Min => "min",
+                    AggregateFunction::Max => "max",
+                    AggregateFunction::Avg => "avg",
+                    AggregateFunction::Count => "count",
+                };
+                write!(f, "{}({})", function, e)
+            }
+            Expression::Binary(left, op, right) => {
+                let op = match op {
+                    BinaryOperator::Plus => "+",
+                    BinaryOperator::Minus => "-",
+                    BinaryOperator::Multiply => "*",
+                    BinaryOperator::Divide => "/",
+                    BinaryOperator::Concat => "||",
+                    BinaryOperator::Eq => "=",
+                    BinaryOperator::NotEq => "<>",
+                    BinaryOperator::Lt => "<",
+                    BinaryOperator::LtEq => "<=",
+                    BinaryOperator::Gt => ">",
+                    BinaryOperator::GtEq => ">=",
+                };
+                write!(f, "({} {} {})", left, op, right)
+            }
+        }
+    }
 }
 
+/// a named aggregate expression
 #[derive(PartialEq, Debug)]
+pub struct MeasureData<'a> {
+    pub name: &'a str,
+    pub expression: Expression<'a>,
+}
+
+/// a column that is added to a loaded table, computed row by row from `expression`
+#[derive(PartialEq, Debug)]
+pub struct CalculatedColumnData<'a> {
+    pub column: ColumnReference<'a>,
+    pub expression: Expression<'a>,
+}
+
+/// a query: the expressions to be shown and the filters that restrict the rows
+#[derive(PartialEq, Debug)]
+pub struct QueryData<'a> {
+    pub select: Vec<Expression<'a>>,
+    pub filters: Vec<Expression<'a>>,
+}
+
+/// the query in the syntax of the dsl, binary expressions are put in parentheses
+impl<'a> fmt::Display for QueryData<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |expressions: &[Expression], separator| {
+            expressions
+                .iter()
+                .map(Expression::to_string)
+                .collect::<Vec<_>>()
+                .join(separator)
+        };
+        write!(f, "{}", join(&self.select, ", "))?;
+        if !self.filters.is_empty() {
+            write!(f, " where {}", join(&self.filters, " and "))?;
+        }
+        Ok(())
+    }
+}
+
+/// a statement of the query language: a query of the model or raw sql for DataFusion
+#[derive(PartialEq, Debug)]
+pub enum StatementData<'a> {
+    Query(QueryData<'a>),
+    /// the sql with its escapes resolved
+    Sql(String),
+}
+
+/// a typed parameter of a named query
+#[derive(PartialEq, Debug)]
+pub struct QueryParameterData<'a> {
+    pub name: &'a str,
+    pub data_type: DataTypeDescriptor<'a>,
+}
+
+/// a query that is stored with the model under its name and run with values for its parameters
+#[derive(PartialEq, Debug)]
+pub struct NamedQueryData<'a> {
+    pub name: &'a str,
+    pub parameters: Vec<QueryParameterData<'a>>,
+    pub query: QueryData<'a>,
+}
+
+/// the named query in the syntax of the dsl
+impl<'a> fmt::Display for NamedQueryData<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self.name)?;
+        if !self.parameters.is_empty() {
+            let parameters = self
+                .parameters
+                .iter()
+                .map(|p| format!("{}: {}", p.name, p.data_type))
+                .collect::<Vec<_>>();
+            write!(f, "({})", parameters.join(", "))?;
+        }
+        write!(f, ": {}", self.query)
+    }
+}
+
+#[derive(PartialEq, Debug, Default)]
 pub struct Ast<'a> {
     pub file_descriptors: Vec<FileDescriptorData<'a>>,
+    pub relationships: Vec<RelationshipData<'a>>,
+    pub calculated_columns: Vec<CalculatedColumnData<'a>>,
+    pub measures: Vec<MeasureData<'a>>,
+    pub queries: Vec<NamedQueryData<'a>>,
 }
 
 impl<'a> CSVData<'a> {
@@ -84,6 +547,10 @@ impl<'a> CSVData<'a> {
             delimiter: b';',
             max_read_records: Some(100),
             has_header: true,
+            dialect: CSVDialect::default(),
+            compression: None,
+            materialize: true,
+            table_options: TableOptions::default(),
         }
     }
 }
@@ -1,66 +1,398 @@
-/// This module is a parser for the data modelling language of tldr.
-///
-/// Every tldr script has to read in somedata first
-///
-/// tldr_input_language     = "load_files" file_descriptor ["," file_descriptor]* ","?
-///
-/// file_descriptor         = "(" name_parameter_block ["," name_parameter_block]* ","? ")"
-///
-/// name_parameter_block    = "csv_file_name" ":" \"file_path\"
-///                         | "delimiter" ":" \"char\"
-///                         | "has_header" ":" (true|false)
-///                         | "max_read_records" ":" number
-///                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
-///
-///     >>> a csv_file_name block must be given
-///     >>> a delimiter block gives the separator of values in the file
-///     >>> a has_header block indicates whether the file contains headers or not
-///     >>> a max_read_records block gives the number of rows to be read to determine the schema
-///     >>> a field_types block can overwrite some or all entries on the schema
-///
-/// field_type_descriptor   = "name" ":" \"name\"
-///                         | "type" ":" type
-///
-/// type                    = "int8"  ["(" "is_nullable" ":" (true|false) ")"]?
-///                         | "int16" (true|false)
-///                         | "int32" (true|false)
-///                         | "int64" (true|false)
-///                         | "uint8" (true|false)
-///                         | "uint16" (true|flase)
-///                         | "uint32" (true|false)
-///                         | "uint64" (true|false)
-///                         | "float32" (true|false)
-///                         | "float64" (true|false)
-///                         | "null"
-///                         | "boolean" (true|false)
-///                         | "binary" (true|false)
-///                         | "string" (true|false)
-///                         | "duration" (true|false) time_unit
-///                         | "time" (true|false) \"format_string\"
-///                         | "date" (true|false) \"format_string\"
-///                         | "datetime" (true|false) \"format_string\"
-///
-///     >>> the first parameter indicates whether the field is nullable or not
-///
+//! This module is a parser for the data modelling language of tldr.
+//!
+//! ```text
+//! Every tldr script has to read in somedata first
+//!
+//! tldr_input_language     = "load_files" "(" file_descriptor ["," file_descriptor]* ","? ")"
+//!                           relationships?
+//!                           calculated_columns?
+//!                           measures?
+//!                           queries?
+//!
+//! file_descriptor         = "(" name_parameter_block ["," name_parameter_block]* ","? ")"
+//!                         | "(" parquet_parameter_block ["," parquet_parameter_block]* ","? ")"
+//!                         | "(" json_parameter_block ["," json_parameter_block]* ","? ")"
+//!                         | "(" avro_parameter_block ["," avro_parameter_block]* ","? ")"
+//!                         | "(" arrow_parameter_block ["," arrow_parameter_block]* ","? ")"
+//!                         | "odbc"? "(" odbc_parameter_block ["," odbc_parameter_block]* ","? ")"
+//!                         | "sqlite" "(" sqlite_parameter_block ["," sqlite_parameter_block]* ","? ")"
+//!                         | "xlsx" "(" xlsx_parameter_block ["," xlsx_parameter_block]* ","? ")"
+//!
+//! name_parameter_block    = "csv_file_name" ":" \"file_path\"
+//!                         | "delimiter" ":" \"char\"
+//!                         | "has_header" ":" (true|false)
+//!                         | "max_read_records" ":" number
+//!                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+//!                         | "materialize" ":" (true|false)
+//!                         | "compression" ":" compression
+//!                         | dialect_parameter_block
+//!                         | table_parameter_block
+//!
+//!     >>> a csv_file_name block must be given
+//!     >>> a delimiter block gives the separator of values in the file
+//!     >>> a has_header block indicates whether the file contains headers or not
+//!     >>> a max_read_records block gives the number of rows to be read to determine the schema
+//!     >>> a field_types block can overwrite some or all entries on the schema
+//!     >>> with materialize: false the file is not loaded into memory but read whenever a query scans it
+//!     >>> a compression block gives the compression of the file, see compression
+//!
+//! dialect_parameter_block = "quote" ":" \"char\"
+//!                         | "escape" ":" \"char\"
+//!                         | "comment" ":" \"char\"
+//!                         | "null_values" ":" "(" \"value\" ["," \"value\"]* ","? ")"
+//!                         | "skip_rows" ":" number
+//!                         | "truncated_rows" ":" (true|false)
+//!                         | "encoding" ":" \"label\"
+//!
+//!     >>> a quote block gives the character that quotes values, a double quote by default
+//!     >>> an escape block gives the character that escapes a quote in a quoted value,
+//!     >>> by default a quote is escaped by doubling it
+//!     >>> a comment block gives the character that starts a comment line
+//!     >>> a null_values block gives the values that are read as null, like (\"\", \"NULL\", \"n/a\"),
+//!     >>> by default only the empty value is null
+//!     >>> a skip_rows block gives the number of lines before the header
+//!     >>> with truncated_rows: true rows with fewer values than the header are filled up with nulls
+//!     >>> an encoding block gives the encoding of the file like \"latin1\" or \"cp1252\", utf-8 by default
+//!     >>> a file with a comment, skip_rows, truncated_rows or encoding block is always loaded into memory,
+//!     >>> as is a file with a null_values block
+//!
+//! parquet_parameter_block = "parquet_file_name" ":" \"file_path\"
+//!                         | "columns" ":" "(" \"name\" ["," \"name\"]* ","? ")"
+//!                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+//!                         | "materialize" ":" (true|false)
+//!                         | table_parameter_block
+//!
+//!     >>> a parquet_file_name block must be given
+//!     >>> a columns block restricts the table to the given columns
+//!     >>> a field_types block can overwrite some or all entries on the schema
+//!     >>> with materialize: false the file is not loaded into memory but read whenever a query scans it
+//!
+//! json_parameter_block    = "json_file_name" ":" \"file_path\"
+//!                         | "max_read_records" ":" number
+//!                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+//!                         | "materialize" ":" (true|false)
+//!                         | "compression" ":" compression
+//!                         | table_parameter_block
+//!
+//!     >>> a json_file_name block must be given
+//!     >>> the file may contain newline-delimited json objects or a single json array of objects
+//!     >>> a max_read_records block gives the number of records to be read to determine the schema
+//!     >>> a field_types block can overwrite some or all entries on the schema
+//!     >>> with materialize: false newline-delimited json is read whenever a query scans it,
+//!     >>> a json array is always loaded into memory
+//!     >>> a compression block gives the compression of the file, see compression
+//!
+//! compression             = "gzip" | "zstd" | "bzip2" | "xz"
+//!
+//!     >>> csv and json files may be compressed. Unless a compression block gives it, the
+//!     >>> compression is taken from the extension .gz, .gzip, .zst, .bz2 or .xz of a file like
+//!     >>> \"FactOnlineSales.csv.gz\", the table of that file is named FactOnlineSales. A csv
+//!     >>> file without a csv extension is only loaded with a compression block
+//!
+//! avro_parameter_block    = "avro_file_name" ":" \"file_path\"
+//!                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+//!                         | "materialize" ":" (true|false)
+//!                         | table_parameter_block
+//!
+//!     >>> an avro_file_name block must be given
+//!     >>> the columns and their types are taken from the avro schema of the file
+//!     >>> a field_types block can overwrite some or all entries on the schema
+//!     >>> with materialize: false the file is not loaded into memory but read whenever a query scans it
+//!
+//! arrow_parameter_block   = "arrow_file_name" ":" \"file_path\"
+//!                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+//!                         | table_parameter_block
+//!
+//!     >>> an arrow_file_name block must be given
+//!     >>> the file may be an arrow ipc file, also known as feather file, or an arrow ipc stream
+//!     >>> a field_types block can overwrite some or all entries on the schema
+//!     >>> the file is always loaded into memory
+//!
+//! odbc_parameter_block    = "connection_string" ":" \"connection_string\"
+//!                         | "query" ":" \"query\"
+//!                         | "batch_size" ":" number
+//!                         | "max_text_size" ":" number
+//!                         | table_parameter_block
+//!
+//!     >>> the result set of the query is loaded from a database through its ODBC driver,
+//!     >>> e.g. odbc(connection_string: \"Driver={SQLite3};Database=contoso.db\", query: \"SELECT * FROM DimProduct\",
+//!     >>> table_name: \"DimProduct\")
+//!     >>> connection_string, query and table_name blocks must be given
+//!     >>> the query cannot contain double quotes, use single quotes for strings
+//!     >>> a batch_size block gives the number of rows that are fetched at once, 1024 by default
+//!     >>> a max_text_size block limits the length of text columns in bytes, longer values are
+//!     >>> truncated. Drivers that report no length for a text column need it
+//!
+//! sqlite_parameter_block  = "path" ":" \"file_path\"
+//!                         | "table" ":" \"name\"
+//!                         | "query" ":" \"query\"
+//!                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+//!                         | table_parameter_block
+//!
+//!     >>> a path block and either a table or a query block must be given
+//!     >>> the table is named like the sqlite table by default, a query needs a table_name block
+//!     >>> the query cannot contain double quotes, use single quotes for strings
+//!     >>> the column types are taken from the declared types of the columns, or from their
+//!     >>> values if a column has no declared type. Loading fails on a value that does not
+//!     >>> convert to the type, like text in an INTEGER column, unless a field_types block
+//!     >>> declares the column nullable
+//!     >>> a field_types block can overwrite some or all entries on the schema, dates and times
+//!     >>> stored as text are parsed with the format string of the field type
+//!
+//! xlsx_parameter_block    = "path" ":" \"file_path\"
+//!                         | "sheet" ":" \"name\"
+//!                         | "range" ":" \"cell:cell\"
+//!                         | "has_header" ":" (true|false)
+//!                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+//!                         | table_parameter_block
+//!
+//!     >>> a path block must be given, the file is an excel workbook (.xlsx or .xlsm)
+//!     >>> a sheet block gives the worksheet to read, by default the first worksheet is read
+//!     >>> the table is named like the worksheet if a sheet block is given, else like the file
+//!     >>> a range block restricts the table to a rectangle of cells like \"B3:F120\"
+//!     >>> a has_header block indicates whether the first row holds the column names
+//!     >>> the column types are inferred from the cells like for csv files, only empty cells are
+//!     >>> null, loading fails on a cell that does not fit the type of its column
+//!     >>> a field_types block can overwrite some or all entries on the schema, dates and times
+//!     >>> stored as text are parsed with the format string of the field type
+//!
+//! table_parameter_block   = ("table_name" | "alias") ":" \"name\"
+//!                         | "schema" ":" \"name\"
+//!                         | "file_name_column" ":" \"name\"
+//!                         | "partition_columns" ":" "(" \"name\" ["," \"name\"]* ","? ")"
+//!
+//!     >>> the file path may also be a directory or a glob pattern like \"data/sales_*.csv\",
+//!     >>> all files of a directory or a pattern are loaded into one table
+//!     >>> a table_name block names the table, by default it is the file name without extension
+//!     >>> a glob pattern needs a table_name block
+//!     >>> a schema block registers the table in that schema, the table is then referred to
+//!     >>> as \"schema.table\" in relationships, calculated columns, measures and queries
+//!     >>> no two tables may have the same name in the same schema
+//!     >>> a file_name_column block adds a column with the path of the file of every row
+//!     >>> a partition_columns block adds a column for every hive-style directory name=value
+//!
+//! field_type_descriptor   = "name" ":" \"name\"
+//!                         | "type" ":" type
+//!
+//! type                    = "int8"  ["(" "is_nullable" ":" (true|false) ")"]?
+//!                         | "int16" (true|false)
+//!                         | "int32" (true|false)
+//!                         | "int64" (true|false)
+//!                         | "uint8" (true|false)
+//!                         | "uint16" (true|flase)
+//!                         | "uint32" (true|false)
+//!                         | "uint64" (true|false)
+//!                         | "float32" (true|false)
+//!                         | "float64" (true|false)
+//!                         | "null"
+//!                         | "boolean" (true|false)
+//!                         | "binary" (true|false)
+//!                         | "string" (true|false)
+//!                         | "duration" (true|false) time_unit
+//!                         | "time" (true|false) \"format_string\"
+//!                         | "date" (true|false) \"format_string\"
+//!                         | "datetime" (true|false) \"format_string\"
+//!
+//!     >>> the first parameter indicates whether the field is nullable or not
+//!
+//! The loaded tables may be linked by relationships
+//!
+//! relationships           = "relationships" "(" relationship ["," relationship]* ","? ")"
+//!
+//! relationship            = column_reference "->" column_reference
+//!                           ["(" relationship_parameter ["," relationship_parameter]* ","? ")"]?
+//!
+//! relationship_parameter  = "cardinality" ":" cardinality
+//!                         | "filter_direction" ":" filter_direction
+//!
+//! column_reference        = name "." name
+//!
+//! name                    = identifier | \"name\"
+//!
+//! cardinality             = "many_to_one" | "one_to_one" | "many_to_many"
+//!
+//! filter_direction        = "single" | "both"
+//!
+//!     >>> the left hand side of a relationship is the referencing column, usually in a fact table
+//!     >>> the right hand side is the referenced key column, usually in a dimension table
+//!     >>> the cardinality defaults to many_to_one
+//!     >>> filters flow from the right hand side to the left hand side, with both also the other way
+//!     >>> the filter_direction defaults to single
+//!
+//! Calculated columns are added to the loaded tables and computed row by row
+//!
+//! calculated_columns      = "calculated_columns" "(" calculated_column ["," calculated_column]* ","? ")"
+//!
+//! calculated_column       = column_reference "=" expression
+//!
+//!     >>> the expression may only use columns of the table the column is added to
+//!     >>> these columns may be given without their table
+//!     >>> a calculated column may use the calculated columns defined before it
+//!
+//! Statements are run against a loaded model, either as a query or as raw sql
+//!
+//! statement               = "sql" sql_string
+//!                         | query
+//!
+//! sql_string              = \"sql\"
+//!
+//!     >>> raw sql is run by DataFusion against the loaded tables, see Context::sql
+//!     >>> inside of the sql a double quote or a backslash is escaped with a backslash
+//!
+//! Queries against the loaded tables name the columns they want to see
+//!
+//! query                   = select_list ["where" expression ["and" expression]*]?
+//!
+//! select_list             = expression ["," expression]* ","?
+//!
+//! expression              = concatenation [comparison_operator concatenation]?
+//!
+//! comparison_operator     = "=" | "<>" | "!=" | "<" | "<=" | ">" | ">="
+//!
+//! concatenation           = arithmetic ["||" arithmetic]*
+//!
+//! arithmetic              = term [("+" | "-") term]*
+//!
+//! term                    = factor [("*" | "/") factor]*
+//!
+//! factor                  = aggregate_function "(" expression ")"
+//!                         | "[" measure_name "]"
+//!                         | "$" identifier
+//!                         | number
+//!                         | \'string\'
+//!                         | column_reference
+//!                         | identifier
+//!                         | "(" expression ")"
+//!
+//! aggregate_function      = "sum" | "min" | "max" | "avg" | "count"
+//!
+//!     >>> columns have to be given with their table
+//!     >>> the tables of the referenced columns are joined along the declared relationships
+//!     >>> columns outside of aggregate functions are grouped by
+//!     >>> a single quote inside of a string is doubled, like \'O\'\'Brien\'
+//!     >>> the filters of the where clause flow along the relationships to the queried tables
+//!
+//! Measures are named aggregate expressions that can be used in queries and other measures
+//!
+//! measures                = "measures" "(" measure ["," measure]* ","? ")"
+//!
+//! measure                 = \"measure_name\" ":" expression
+//!
+//!     >>> every column of a measure has to be inside of an aggregate function
+//!
+//! Named queries are stored with the model and run with values for their parameters
+//!
+//! queries                 = "queries" "(" named_query [";" named_query]* ";"? ")"
+//!
+//! named_query             = \"query_name\" ["(" [query_parameter ["," query_parameter]* ","?]? ")"]?
+//!                           ":" query
+//!
+//! query_parameter         = identifier ":" type
+//!
+//!     >>> the expressions of a named query may use its parameters as $name
+//!     >>> the queries are separated by semicolons since their select lists contain commas
+//!     >>> a value of a parameter is parsed as its type, dates and times with the format string
+//! ```
+
 use arrow::datatypes::TimeUnit;
-use std::collections::HashMap;
+use encoding_rs::Encoding;
+use std::{borrow::Cow, collections::HashMap};
 
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_until},
+    bytes::complete::{is_not, tag, take_until},
     character::{
-        complete::{char, multispace0, u32},
+        complete::{alpha1, alphanumeric1, char, digit1, multispace0, none_of, u32},
         streaming::anychar,
     },
-    combinator::{map, opt},
-    error::ParseError,
-    multi::{many0, separated_list1},
-    sequence::{delimited, separated_pair, tuple},
-    IResult, Parser,
+    combinator::{cut, map, map_opt, map_res, opt, recognize},
+    error::{context, ParseError, VerboseError, VerboseErrorKind},
+    multi::{fold_many0, many0, separated_list0, separated_list1},
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
+    IResult, InputTake, Parser,
+};
+
+use crate::error::TldrParseError;
+
+use super::ast::{
+    AggregateFunction, ArrowData, Ast, AvroData, BinaryOperator, CSVData, CSVDialect,
+    CalculatedColumnData, Cardinality, CellRange, ColumnReference, Compression, DataTypeDescriptor,
+    Expression, FileDescriptorData, FilterDirection, JSONData, LiteralValue, MeasureData,
+    NamedQueryData, ODBCData, ParquetData, QueryData, QueryParameterData, RelationshipData,
+    SQLiteData, SQLiteSource, Span, StatementData, TableOptions, XLSXData,
 };
-use nom_locate::position;
 
-use super::ast::{Ast, CSVData, DataTypeDescriptor, FileDescriptorData, Span};
+/// the result of the parsers of this module.
+///
+/// The error keeps the position of a failure and what was expected there, see `parse_script`.
+pub type ParseResult<'a, O> = IResult<Span<'a>, O, VerboseError<Span<'a>>>;
+
+// what the parsers expect, as shown in parse errors
+const FILE_DESCRIPTOR: &str = "a file descriptor";
+const DATA_TYPE: &str = "a data type";
+const SECTION: &str = "a section";
+const RELATIONSHIP: &str = "a relationship";
+const RELATIONSHIP_PARAMETER: &str = "a relationship parameter";
+const CARDINALITY: &str = "a cardinality";
+const COMPRESSION: &str = "a compression";
+const FILTER_DIRECTION: &str = "a filter direction";
+const CALCULATED_COLUMN: &str = "a calculated column";
+const MEASURE: &str = "a measure";
+const NAMED_QUERY: &str = "a named query";
+
+/// the keywords that may appear where something is expected, used for the hints of parse errors
+fn keywords(expected: &str) -> &'static [&'static str] {
+    match expected {
+        FILE_DESCRIPTOR => &[
+            "csv_file_name",
+            "parquet_file_name",
+            "json_file_name",
+            "avro_file_name",
+            "arrow_file_name",
+            "odbc",
+            "connection_string",
+            "query",
+            "batch_size",
+            "max_text_size",
+            "sqlite",
+            "path",
+            "table",
+            "xlsx",
+            "sheet",
+            "range",
+            "delimiter",
+            "has_header",
+            "max_read_records",
+            "columns",
+            "field_types",
+            "materialize",
+            "compression",
+            "quote",
+            "escape",
+            "comment",
+            "null_values",
+            "skip_rows",
+            "truncated_rows",
+            "encoding",
+            "table_name",
+            "alias",
+            "schema",
+            "file_name_column",
+            "partition_columns",
+        ],
+        DATA_TYPE => &[
+            "int8", "int16", "int32", "int64", "uint8", "uint16", "uint32", "uint64", "float32",
+            "float64", "null", "boolean", "binary", "string", "duration", "time", "date",
+            "datetime",
+        ],
+        SECTION => &["relationships", "calculated_columns", "measures", "queries"],
+        RELATIONSHIP_PARAMETER => &["cardinality", "filter_direction"],
+        CARDINALITY => &["many_to_one", "one_to_one", "many_to_many"],
+        COMPRESSION => &["gzip", "zstd", "bzip2", "xz"],
+        FILTER_DIRECTION => &["single", "both"],
+        _ => &[],
+    }
+}
 
 /// A combinator that takes a parser `inner` and produces a parser that also consumes both leading and
 /// trailing whitespace, returning the output of `inner`.
@@ -75,123 +407,1565 @@ where
 ///
 /// Here we encode the rule for tldr_input_language:
 ///
-/// tldr_input_language     = "load_files" file_descriptor ["," file_descriptor]* ","?
+/// tldr_input_language     = "load_files" "(" file_descriptor ["," file_descriptor]* ","? ")"
+///                           relationships?
+///                           calculated_columns?
+///                           measures?
+///                           queries?
+///
+/// A single file descriptor may be given without its parentheses.
 ///
-pub fn ast_parser<'a>(input: Span<'a>) -> IResult<Span<'a>, Ast> {
-    let start = tuple((ws(tag("load_files")), ws(tag("("))));
-    let mid = ws(separated_list1(ws(tag(",")), ws(file_descriptor_parser)));
+pub fn ast_parser<'a>(input: Span<'a>) -> ParseResult<'a, Ast<'a>> {
+    let start = context("load_files", tuple((ws(tag("load_files")), ws(tag("(")))));
+    let mid = ws(separated_list1(
+        ws(tag(",")),
+        alt((
+            ws(tagged_descriptor_parser),
+            preceded(
+                ws(tag("(")),
+                cut(terminated(file_descriptor_parser, ws(tag(")")))),
+            ),
+            ws(file_descriptor_parser),
+        )),
+    ));
     let end = tuple((ws(opt(tag(","))), ws(tag(")"))));
 
-    let parser = delimited(start, mid, end);
+    let load_files = preceded(start, cut(context(FILE_DESCRIPTOR, terminated(mid, end))));
+
+    let parser = tuple((
+        load_files,
+        opt(relationships_parser),
+        opt(calculated_columns_parser),
+        opt(measures_parser),
+        opt(queries_parser),
+    ));
+
+    map(
+        parser,
+        |(file_descriptors, relationships, calculated_columns, measures, queries)| Ast {
+            file_descriptors,
+            relationships: relationships.unwrap_or_default(),
+            calculated_columns: calculated_columns.unwrap_or_default(),
+            measures: measures.unwrap_or_default(),
+            queries: queries.unwrap_or_default(),
+        },
+    )(input)
+}
+
+/// parses a whole tldr script.
+///
+/// Unlike `ast_parser`, the script has to be parsed completely. If it cannot be parsed, the
+/// error points at the line and column where parsing failed and tells what was expected there.
+pub fn parse_script(script: &str) -> Result<Ast<'_>, TldrParseError> {
+    let (at, expected) = match ast_parser(Span::new(script)) {
+        Ok((rest, ast)) if rest.fragment().trim().is_empty() => return Ok(ast),
+        Ok((rest, _)) => (rest, SECTION),
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+            // the innermost context tells best what was expected
+            let expected = e
+                .errors
+                .iter()
+                .find_map(|(_, kind)| match kind {
+                    VerboseErrorKind::Context(c) => Some(*c),
+                    _ => None,
+                })
+                .unwrap_or("valid input");
+            (e.errors[0].0, expected)
+        }
+        Err(nom::Err::Incomplete(_)) => {
+            let end = Span::new(script).take_split(script.len()).0;
+            (end, "more input")
+        }
+    };
+
+    let word = match identifier_parser(at) {
+        Ok((_, word)) => word.fragment().to_string(),
+        Err(_) => at
+            .fragment()
+            .chars()
+            .next()
+            .map(String::from)
+            .unwrap_or_default(),
+    };
+
+    Err(TldrParseError {
+        script: script.to_string(),
+        span: (at.location_offset(), word.len()).into(),
+        line: at.location_line(),
+        column: at.get_utf8_column(),
+        expected: expected.to_string(),
+        help: hint(&word, expected),
+    })
+}
+
+/// suggests the keywords that are similar to `word`, or lists all keywords if none is
+fn hint(word: &str, expected: &str) -> Option<String> {
+    let keywords = keywords(expected);
+    if keywords.is_empty() {
+        return None;
+    }
+
+    let similar = keywords
+        .iter()
+        .filter(|k| {
+            (word.len() > 2 && (k.contains(word) || word.contains(*k)))
+                || edit_distance(word, k) <= 2
+        })
+        .map(|k| format!("`{}`", k))
+        .collect::<Vec<_>>();
+
+    Some(match similar.split_last() {
+        None => format!("{} is one of {}", expected, keywords.join(", ")),
+        Some((last, [])) => format!("did you mean {}?", last),
+        Some((last, others)) => format!("did you mean {} or {}?", others.join(", "), last),
+    })
+}
+
+/// the levenshtein distance of `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Here, we parse a file-descriptor block of any of the supported file types
+fn file_descriptor_parser(input: Span) -> ParseResult<FileDescriptorData> {
+    alt((
+        parquet_descriptor_parser,
+        json_descriptor_parser,
+        avro_descriptor_parser,
+        arrow_descriptor_parser,
+        odbc_descriptor_parser,
+        csv_descriptor_parser,
+    ))(input)
+}
+
+/// Here, we parse a file-descriptor block that is introduced by the kind of its source
+///
+/// tagged_descriptor       = "odbc" "(" odbc_parameter_block ["," odbc_parameter_block]* ","? ")"
+///                         | "sqlite" "(" sqlite_parameter_block ["," sqlite_parameter_block]* ","? ")"
+///                         | "xlsx" "(" xlsx_parameter_block ["," xlsx_parameter_block]* ","? ")"
+fn tagged_descriptor_parser(input: Span) -> ParseResult<FileDescriptorData> {
+    let odbc = preceded(
+        ws(tag("odbc")),
+        cut(delimited(
+            ws(tag("(")),
+            odbc_descriptor_parser,
+            ws(tag(")")),
+        )),
+    );
+
+    let sqlite = preceded(
+        ws(tag("sqlite")),
+        cut(delimited(
+            ws(tag("(")),
+            sqlite_descriptor_parser,
+            ws(tag(")")),
+        )),
+    );
+
+    let xlsx = preceded(
+        ws(tag("xlsx")),
+        cut(delimited(
+            ws(tag("(")),
+            xlsx_descriptor_parser,
+            ws(tag(")")),
+        )),
+    );
 
-    map(parser, |file_descriptors| Ast { file_descriptors })(input)
+    alt((odbc, sqlite, xlsx))(input)
 }
 
-/// Here, we parse the entries of the file-descriptor block
+/// Here, we parse the entries of a csv file-descriptor block
 ///
 /// name_parameter_block    = "csv_file_name" ":" \"file_path\"
 ///                         | "delimiter" ":" \"char\"
 ///                         | "has_header" ":" (true|false)
 ///                         | "max_read_records" ":" number
 ///                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+///                         | "materialize" ":" (true|false)
+///                         | "compression" ":" compression
+///                         | dialect_parameter_block
+///                         | table_parameter_block
 ///
-fn file_descriptor_parser(input: Span) -> IResult<Span, FileDescriptorData> {
+/// The parser fails if no csv_file_name block is given.
+fn csv_descriptor_parser(input: Span) -> ParseResult<FileDescriptorData> {
     enum IntermediateResult<'a> {
         CSVFileName(&'a str),
         Delimiter(u8),
         HasHeader(bool),
         MaxReadRecords(Option<usize>),
         FieldTypes(HashMap<&'a str, DataTypeDescriptor<'a>>),
+        Materialize(bool),
+        Compression(Compression),
+        Dialect(DialectParameter<'a>),
+        Table(TableParameter<'a>),
     }
 
     let csv_file_name_block = map(
         tuple((
             ws(tag("csv_file_name")),
             ws(tag(":")),
-            ws(string_parser),
+            cut(ws(string_parser)),
             opt(ws(tag(","))),
         )),
         |(_, _, s, _)| IntermediateResult::CSVFileName(s.fragment()),
     );
 
-    let delimiter_block = map(
-        tuple((
-            ws(tag("delimiter")),
-            ws(tag(":")),
-            ws(char_parser),
-            opt(ws(tag(","))),
-        )),
-        |(_, _, c, _)| IntermediateResult::Delimiter(c),
-    );
+    let delimiter_block = map(
+        tuple((
+            ws(tag("delimiter")),
+            ws(tag(":")),
+            cut(ws(char_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, c, _)| IntermediateResult::Delimiter(c),
+    );
+
+    let has_header_block = map(
+        tuple((
+            ws(tag("has_header")),
+            ws(tag(":")),
+            cut(ws(bool_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, b, _)| IntermediateResult::HasHeader(b),
+    );
+
+    let max_read_records_block = map(max_read_records_block, IntermediateResult::MaxReadRecords);
+
+    let field_types_block = map(field_types_block, IntermediateResult::FieldTypes);
+
+    let materialize_block = map(materialize_block, IntermediateResult::Materialize);
+
+    let compression_block = map(compression_block, IntermediateResult::Compression);
+
+    let dialect_parameter_block = map(dialect_parameter_block, IntermediateResult::Dialect);
+
+    let table_parameter_block = map(table_parameter_block, IntermediateResult::Table);
+
+    let any_of_that = many0(alt((
+        csv_file_name_block,
+        field_types_block,
+        delimiter_block,
+        has_header_block,
+        max_read_records_block,
+        materialize_block,
+        compression_block,
+        dialect_parameter_block,
+        table_parameter_block,
+    )));
+
+    map_opt(any_of_that, |ds| {
+        let mut csv_file_path = None;
+        let mut field_types = HashMap::new();
+        let mut delimiter: u8 = b',';
+        let mut has_header = true;
+        let mut max_read_records = Some(100);
+        let mut materialize = true;
+        let mut compression = None;
+        let mut dialect = CSVDialect::default();
+        let mut table_options = TableOptions::default();
+
+        for d in ds {
+            match d {
+                IntermediateResult::CSVFileName(s) => csv_file_path = Some(s),
+                IntermediateResult::Delimiter(c) => delimiter = c,
+                IntermediateResult::FieldTypes(s) => field_types = s,
+                IntermediateResult::HasHeader(b) => has_header = b,
+                IntermediateResult::MaxReadRecords(n) => max_read_records = n,
+                IntermediateResult::Materialize(b) => materialize = b,
+                IntermediateResult::Compression(c) => compression = Some(c),
+                IntermediateResult::Dialect(p) => p.apply(&mut dialect),
+                IntermediateResult::Table(p) => p.apply(&mut table_options),
+            }
+        }
+
+        Some(FileDescriptorData::CSV(CSVData {
+            csv_file_path: csv_file_path?,
+            field_types,
+            delimiter,
+            max_read_records,
+            has_header,
+            dialect,
+            compression,
+            materialize,
+            table_options,
+        }))
+    })(input)
+}
+
+/// Here, we parse the entries of a parquet file-descriptor block
+///
+/// parquet_parameter_block = "parquet_file_name" ":" \"file_path\"
+///                         | "columns" ":" "(" \"name\" ["," \"name\"]* ","? ")"
+///                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+///                         | "materialize" ":" (true|false)
+///                         | table_parameter_block
+///
+/// The parser fails if no parquet_file_name block is given.
+fn parquet_descriptor_parser(input: Span) -> ParseResult<FileDescriptorData> {
+    enum IntermediateResult<'a> {
+        ParquetFileName(&'a str),
+        Columns(Vec<&'a str>),
+        FieldTypes(HashMap<&'a str, DataTypeDescriptor<'a>>),
+        Materialize(bool),
+        Table(TableParameter<'a>),
+    }
+
+    let parquet_file_name_block = map(
+        tuple((
+            ws(tag("parquet_file_name")),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| IntermediateResult::ParquetFileName(s.fragment()),
+    );
+
+    let columns_block = map(
+        tuple((
+            ws(tag("columns")),
+            ws(tag(":")),
+            cut(ws(string_list_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, c, _)| IntermediateResult::Columns(c),
+    );
+
+    let field_types_block = map(field_types_block, IntermediateResult::FieldTypes);
+
+    let materialize_block = map(materialize_block, IntermediateResult::Materialize);
+
+    let table_parameter_block = map(table_parameter_block, IntermediateResult::Table);
+
+    let any_of_that = many0(alt((
+        parquet_file_name_block,
+        columns_block,
+        field_types_block,
+        materialize_block,
+        table_parameter_block,
+    )));
+
+    map_opt(any_of_that, |ds| {
+        let mut parquet_file_path = None;
+        let mut columns = None;
+        let mut field_types = HashMap::new();
+        let mut materialize = true;
+        let mut table_options = TableOptions::default();
+
+        for d in ds {
+            match d {
+                IntermediateResult::ParquetFileName(s) => parquet_file_path = Some(s),
+                IntermediateResult::Columns(c) => columns = Some(c),
+                IntermediateResult::FieldTypes(s) => field_types = s,
+                IntermediateResult::Materialize(b) => materialize = b,
+                IntermediateResult::Table(p) => p.apply(&mut table_options),
+            }
+        }
+
+        Some(FileDescriptorData::Parquet(ParquetData {
+            parquet_file_path: parquet_file_path?,
+            columns,
+            field_types,
+            materialize,
+            table_options,
+        }))
+    })(input)
+}
+
+/// Here, we parse the entries of a json file-descriptor block
+///
+/// json_parameter_block    = "json_file_name" ":" \"file_path\"
+///                         | "max_read_records" ":" number
+///                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+///                         | "materialize" ":" (true|false)
+///                         | "compression" ":" compression
+///                         | table_parameter_block
+///
+/// The parser fails if no json_file_name block is given.
+fn json_descriptor_parser(input: Span) -> ParseResult<FileDescriptorData> {
+    enum IntermediateResult<'a> {
+        JSONFileName(&'a str),
+        MaxReadRecords(Option<usize>),
+        FieldTypes(HashMap<&'a str, DataTypeDescriptor<'a>>),
+        Materialize(bool),
+        Compression(Compression),
+        Table(TableParameter<'a>),
+    }
+
+    let json_file_name_block = map(
+        tuple((
+            ws(tag("json_file_name")),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| IntermediateResult::JSONFileName(s.fragment()),
+    );
+
+    let max_read_records_block = map(max_read_records_block, IntermediateResult::MaxReadRecords);
+
+    let field_types_block = map(field_types_block, IntermediateResult::FieldTypes);
+
+    let materialize_block = map(materialize_block, IntermediateResult::Materialize);
+
+    let compression_block = map(compression_block, IntermediateResult::Compression);
+
+    let table_parameter_block = map(table_parameter_block, IntermediateResult::Table);
+
+    let any_of_that = many0(alt((
+        json_file_name_block,
+        max_read_records_block,
+        field_types_block,
+        materialize_block,
+        compression_block,
+        table_parameter_block,
+    )));
+
+    map_opt(any_of_that, |ds| {
+        let mut json_file_path = None;
+        let mut max_read_records = Some(100);
+        let mut field_types = HashMap::new();
+        let mut materialize = true;
+        let mut compression = None;
+        let mut table_options = TableOptions::default();
+
+        for d in ds {
+            match d {
+                IntermediateResult::JSONFileName(s) => json_file_path = Some(s),
+                IntermediateResult::MaxReadRecords(n) => max_read_records = n,
+                IntermediateResult::FieldTypes(s) => field_types = s,
+                IntermediateResult::Materialize(b) => materialize = b,
+                IntermediateResult::Compression(c) => compression = Some(c),
+                IntermediateResult::Table(p) => p.apply(&mut table_options),
+            }
+        }
+
+        Some(FileDescriptorData::JSON(JSONData {
+            json_file_path: json_file_path?,
+            field_types,
+            max_read_records,
+            compression,
+            materialize,
+            table_options,
+        }))
+    })(input)
+}
+
+/// Here, we parse the entries of an avro file-descriptor block
+///
+/// avro_parameter_block    = "avro_file_name" ":" \"file_path\"
+///                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+///                         | "materialize" ":" (true|false)
+///                         | table_parameter_block
+///
+/// The parser fails if no avro_file_name block is given.
+fn avro_descriptor_parser(input: Span) -> ParseResult<FileDescriptorData> {
+    enum IntermediateResult<'a> {
+        AvroFileName(&'a str),
+        FieldTypes(HashMap<&'a str, DataTypeDescriptor<'a>>),
+        Materialize(bool),
+        Table(TableParameter<'a>),
+    }
+
+    let avro_file_name_block = map(
+        tuple((
+            ws(tag("avro_file_name")),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| IntermediateResult::AvroFileName(s.fragment()),
+    );
+
+    let field_types_block = map(field_types_block, IntermediateResult::FieldTypes);
+
+    let materialize_block = map(materialize_block, IntermediateResult::Materialize);
+
+    let table_parameter_block = map(table_parameter_block, IntermediateResult::Table);
+
+    let any_of_that = many0(alt((
+        avro_file_name_block,
+        field_types_block,
+        materialize_block,
+        table_parameter_block,
+    )));
+
+    map_opt(any_of_that, |ds| {
+        let mut avro_file_path = None;
+        let mut field_types = HashMap::new();
+        let mut materialize = true;
+        let mut table_options = TableOptions::default();
+
+        for d in ds {
+            match d {
+                IntermediateResult::AvroFileName(s) => avro_file_path = Some(s),
+                IntermediateResult::FieldTypes(s) => field_types = s,
+                IntermediateResult::Materialize(b) => materialize = b,
+                IntermediateResult::Table(p) => p.apply(&mut table_options),
+            }
+        }
+
+        Some(FileDescriptorData::Avro(AvroData {
+            avro_file_path: avro_file_path?,
+            field_types,
+            materialize,
+            table_options,
+        }))
+    })(input)
+}
+
+/// Here, we parse the entries of an arrow file-descriptor block
+///
+/// arrow_parameter_block   = "arrow_file_name" ":" \"file_path\"
+///                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+///                         | table_parameter_block
+///
+/// The parser fails if no arrow_file_name block is given.
+fn arrow_descriptor_parser(input: Span) -> ParseResult<FileDescriptorData> {
+    enum IntermediateResult<'a> {
+        ArrowFileName(&'a str),
+        FieldTypes(HashMap<&'a str, DataTypeDescriptor<'a>>),
+        Table(TableParameter<'a>),
+    }
+
+    let arrow_file_name_block = map(
+        tuple((
+            ws(tag("arrow_file_name")),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| IntermediateResult::ArrowFileName(s.fragment()),
+    );
+
+    let field_types_block = map(field_types_block, IntermediateResult::FieldTypes);
+
+    let table_parameter_block = map(table_parameter_block, IntermediateResult::Table);
+
+    let any_of_that = many0(alt((
+        arrow_file_name_block,
+        field_types_block,
+        table_parameter_block,
+    )));
+
+    map_opt(any_of_that, |ds| {
+        let mut arrow_file_path = None;
+        let mut field_types = HashMap::new();
+        let mut table_options = TableOptions::default();
+
+        for d in ds {
+            match d {
+                IntermediateResult::ArrowFileName(s) => arrow_file_path = Some(s),
+                IntermediateResult::FieldTypes(s) => field_types = s,
+                IntermediateResult::Table(p) => p.apply(&mut table_options),
+            }
+        }
+
+        Some(FileDescriptorData::Arrow(ArrowData {
+            arrow_file_path: arrow_file_path?,
+            field_types,
+            table_options,
+        }))
+    })(input)
+}
+
+/// Here, we parse the entries of an odbc descriptor block
+///
+/// odbc_parameter_block    = "connection_string" ":" \"connection_string\"
+///                         | "query" ":" \"query\"
+///                         | "batch_size" ":" number
+///                         | "max_text_size" ":" number
+///                         | table_parameter_block
+///
+/// The parser fails if no connection_string or no query block is given.
+fn odbc_descriptor_parser(input: Span) -> ParseResult<FileDescriptorData> {
+    enum IntermediateResult<'a> {
+        ConnectionString(&'a str),
+        Query(&'a str),
+        BatchSize(usize),
+        MaxTextSize(usize),
+        Table(TableParameter<'a>),
+    }
+
+    let connection_string_block = map(
+        tuple((
+            ws(tag("connection_string")),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| IntermediateResult::ConnectionString(s.fragment()),
+    );
+
+    let query_block = map(
+        tuple((
+            ws(tag("query")),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| IntermediateResult::Query(s.fragment()),
+    );
+
+    let batch_size_block = map(
+        tuple((
+            ws(tag("batch_size")),
+            ws(tag(":")),
+            cut(ws(usize_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, n, _)| IntermediateResult::BatchSize(n),
+    );
+
+    let max_text_size_block = map(
+        tuple((
+            ws(tag("max_text_size")),
+            ws(tag(":")),
+            cut(ws(usize_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, n, _)| IntermediateResult::MaxTextSize(n),
+    );
+
+    let table_parameter_block = map(table_parameter_block, IntermediateResult::Table);
+
+    let any_of_that = many0(alt((
+        connection_string_block,
+        query_block,
+        batch_size_block,
+        max_text_size_block,
+        table_parameter_block,
+    )));
+
+    map_opt(any_of_that, |ds| {
+        let mut connection_string = None;
+        let mut query = None;
+        let mut batch_size = 1024;
+        let mut max_text_size = None;
+        let mut table_options = TableOptions::default();
+
+        for d in ds {
+            match d {
+                IntermediateResult::ConnectionString(s) => connection_string = Some(s),
+                IntermediateResult::Query(s) => query = Some(s),
+                IntermediateResult::BatchSize(n) => batch_size = n,
+                IntermediateResult::MaxTextSize(n) => max_text_size = Some(n),
+                IntermediateResult::Table(p) => p.apply(&mut table_options),
+            }
+        }
+
+        Some(FileDescriptorData::ODBC(ODBCData {
+            connection_string: connection_string?,
+            query: query?,
+            batch_size,
+            max_text_size,
+            table_options,
+        }))
+    })(input)
+}
+
+/// Here, we parse the entries of a sqlite descriptor block
+///
+/// sqlite_parameter_block  = "path" ":" \"file_path\"
+///                         | "table" ":" \"name\"
+///                         | "query" ":" \"query\"
+///                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+///                         | table_parameter_block
+///
+/// The parser fails if no path block or not exactly one of a table and a query block is given.
+fn sqlite_descriptor_parser(input: Span) -> ParseResult<FileDescriptorData> {
+    enum IntermediateResult<'a> {
+        Path(&'a str),
+        Table(&'a str),
+        Query(&'a str),
+        FieldTypes(HashMap<&'a str, DataTypeDescriptor<'a>>),
+        TableParameter(TableParameter<'a>),
+    }
+
+    let path_block = map(
+        tuple((
+            ws(tag("path")),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| IntermediateResult::Path(s.fragment()),
+    );
+
+    let table_block = map(
+        tuple((
+            ws(tag("table")),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| IntermediateResult::Table(s.fragment()),
+    );
+
+    let query_block = map(
+        tuple((
+            ws(tag("query")),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| IntermediateResult::Query(s.fragment()),
+    );
+
+    let field_types_block = map(field_types_block, IntermediateResult::FieldTypes);
+
+    let table_parameter_block = map(table_parameter_block, IntermediateResult::TableParameter);
+
+    // table_name has to be tried before table
+    let any_of_that = many0(alt((
+        path_block,
+        table_parameter_block,
+        table_block,
+        query_block,
+        field_types_block,
+    )));
+
+    map_opt(any_of_that, |ds| {
+        let mut sqlite_file_path = None;
+        let mut table = None;
+        let mut query = None;
+        let mut field_types = HashMap::new();
+        let mut table_options = TableOptions::default();
+
+        for d in ds {
+            match d {
+                IntermediateResult::Path(s) => sqlite_file_path = Some(s),
+                IntermediateResult::Table(s) => table = Some(s),
+                IntermediateResult::Query(s) => query = Some(s),
+                IntermediateResult::FieldTypes(s) => field_types = s,
+                IntermediateResult::TableParameter(p) => p.apply(&mut table_options),
+            }
+        }
+
+        let source = match (table, query) {
+            (Some(table), None) => SQLiteSource::Table(table),
+            (None, Some(query)) => SQLiteSource::Query(query),
+            _ => return None,
+        };
+
+        Some(FileDescriptorData::SQLite(SQLiteData {
+            sqlite_file_path: sqlite_file_path?,
+            source,
+            field_types,
+            table_options,
+        }))
+    })(input)
+}
+
+/// Here, we parse the entries of an xlsx descriptor block
+///
+/// xlsx_parameter_block    = "path" ":" \"file_path\"
+///                         | "sheet" ":" \"name\"
+///                         | "range" ":" \"cell:cell\"
+///                         | "has_header" ":" (true|false)
+///                         | "field_types" ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+///                         | table_parameter_block
+///
+/// The parser fails if no path block is given.
+fn xlsx_descriptor_parser(input: Span) -> ParseResult<FileDescriptorData> {
+    enum IntermediateResult<'a> {
+        Path(&'a str),
+        Sheet(&'a str),
+        Range(CellRange),
+        HasHeader(bool),
+        FieldTypes(HashMap<&'a str, DataTypeDescriptor<'a>>),
+        TableParameter(TableParameter<'a>),
+    }
+
+    let path_block = map(
+        tuple((
+            ws(tag("path")),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| IntermediateResult::Path(s.fragment()),
+    );
+
+    let sheet_block = map(
+        tuple((
+            ws(tag("sheet")),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| IntermediateResult::Sheet(s.fragment()),
+    );
+
+    let range_block = map(
+        tuple((
+            ws(tag("range")),
+            ws(tag(":")),
+            cut(ws(map_opt(string_parser, |s| cell_range(s.fragment())))),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, r, _)| IntermediateResult::Range(r),
+    );
+
+    let has_header_block = map(
+        tuple((
+            ws(tag("has_header")),
+            ws(tag(":")),
+            cut(ws(bool_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, b, _)| IntermediateResult::HasHeader(b),
+    );
+
+    let field_types_block = map(field_types_block, IntermediateResult::FieldTypes);
+
+    let table_parameter_block = map(table_parameter_block, IntermediateResult::TableParameter);
+
+    let any_of_that = many0(alt((
+        path_block,
+        sheet_block,
+        table_parameter_block,
+        range_block,
+        has_header_block,
+        field_types_block,
+    )));
+
+    map_opt(any_of_that, |ds| {
+        let mut xlsx_file_path = None;
+        let mut sheet = None;
+        let mut range = None;
+        let mut has_header = true;
+        let mut field_types = HashMap::new();
+        let mut table_options = TableOptions::default();
+
+        for d in ds {
+            match d {
+                IntermediateResult::Path(s) => xlsx_file_path = Some(s),
+                IntermediateResult::Sheet(s) => sheet = Some(s),
+                IntermediateResult::Range(r) => range = Some(r),
+                IntermediateResult::HasHeader(b) => has_header = b,
+                IntermediateResult::FieldTypes(s) => field_types = s,
+                IntermediateResult::TableParameter(p) => p.apply(&mut table_options),
+            }
+        }
+
+        Some(FileDescriptorData::XLSX(XLSXData {
+            xlsx_file_path: xlsx_file_path?,
+            sheet,
+            range,
+            has_header,
+            field_types,
+            table_options,
+        }))
+    })(input)
+}
+
+/// a range of cells in the A1 notation of excel like "B3:F120", absolute references like
+/// "$B$3" are accepted as well. None if the range is not valid or its end is above or left
+/// of its start.
+fn cell_range(s: &str) -> Option<CellRange> {
+    let (start, end) = s.split_once(':')?;
+    let (start, end) = (cell_reference(start)?, cell_reference(end)?);
+    if end.0 < start.0 || end.1 < start.1 {
+        return None;
+    }
+    Some(CellRange { start, end })
+}
+
+/// the zero based row and column of a cell like "AB12"
+fn cell_reference(s: &str) -> Option<(u32, u32)> {
+    let s = s.trim().replace('$', "");
+    let digits = s.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = s.split_at(digits);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let column = letters.chars().try_fold(0u32, |column, c| {
+        column
+            .checked_mul(26)?
+            .checked_add(c.to_ascii_uppercase() as u32 - 'A' as u32 + 1)
+    })?;
+    let row = digits.parse::<u32>().ok()?;
+    if row == 0 {
+        return None;
+    }
+    Some((row - 1, column - 1))
+}
+
+/// "max_read_records" ":" number
+fn max_read_records_block(input: Span) -> ParseResult<Option<usize>> {
+    map(
+        tuple((
+            ws(tag("max_read_records")),
+            ws(tag(":")),
+            ws(opt(usize_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, n, _)| n,
+    )(input)
+}
+
+/// a parameter of table_parameter_block
+enum TableParameter<'a> {
+    TableName(&'a str),
+    Schema(&'a str),
+    FileNameColumn(&'a str),
+    PartitionColumns(Vec<&'a str>),
+}
+
+impl<'a> TableParameter<'a> {
+    fn apply(self, options: &mut TableOptions<'a>) {
+        match self {
+            TableParameter::TableName(s) => options.table_name = Some(s),
+            TableParameter::Schema(s) => options.schema = Some(s),
+            TableParameter::FileNameColumn(s) => options.file_name_column = Some(s),
+            TableParameter::PartitionColumns(c) => options.partition_columns = c,
+        }
+    }
+}
+
+/// Here, we parse the entries of a file-descriptor block that all file types have in common
+///
+/// table_parameter_block   = ("table_name" | "alias") ":" \"name\"
+///                         | "schema" ":" \"name\"
+///                         | "file_name_column" ":" \"name\"
+///                         | "partition_columns" ":" "(" \"name\" ["," \"name\"]* ","? ")"
+fn table_parameter_block(input: Span) -> ParseResult<TableParameter> {
+    let table_name_block = map(
+        tuple((
+            ws(alt((tag("table_name"), tag("alias")))),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| TableParameter::TableName(s.fragment()),
+    );
+
+    let schema_block = map(
+        tuple((
+            ws(tag("schema")),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| TableParameter::Schema(s.fragment()),
+    );
+
+    let file_name_column_block = map(
+        tuple((
+            ws(tag("file_name_column")),
+            ws(tag(":")),
+            cut(ws(string_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| TableParameter::FileNameColumn(s.fragment()),
+    );
+
+    let partition_columns_block = map(
+        tuple((
+            ws(tag("partition_columns")),
+            ws(tag(":")),
+            cut(ws(string_list_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, c, _)| TableParameter::PartitionColumns(c),
+    );
+
+    alt((
+        table_name_block,
+        schema_block,
+        file_name_column_block,
+        partition_columns_block,
+    ))(input)
+}
+
+/// an entry of a csv descriptor block that describes how the values of the file are written
+enum DialectParameter<'a> {
+    Quote(u8),
+    Escape(u8),
+    Comment(u8),
+    NullValues(Vec<&'a str>),
+    SkipRows(usize),
+    TruncatedRows(bool),
+    Encoding(&'a str),
+}
+
+impl<'a> DialectParameter<'a> {
+    fn apply(self, dialect: &mut CSVDialect<'a>) {
+        match self {
+            DialectParameter::Quote(c) => dialect.quote = c,
+            DialectParameter::Escape(c) => dialect.escape = Some(c),
+            DialectParameter::Comment(c) => dialect.comment = Some(c),
+            DialectParameter::NullValues(v) => dialect.null_values = v,
+            DialectParameter::SkipRows(n) => dialect.skip_rows = n,
+            DialectParameter::TruncatedRows(b) => dialect.truncated_rows = b,
+            DialectParameter::Encoding(s) => dialect.encoding = Some(s),
+        }
+    }
+}
+
+/// Here, we parse the entries of a csv descriptor block that describe the dialect of the file
+///
+/// dialect_parameter_block = "quote" ":" \"char\"
+///                         | "escape" ":" \"char\"
+///                         | "comment" ":" \"char\"
+///                         | "null_values" ":" "(" \"value\" ["," \"value\"]* ","? ")"
+///                         | "skip_rows" ":" number
+///                         | "truncated_rows" ":" (true|false)
+///                         | "encoding" ":" \"label\"
+///
+/// The parser fails on an encoding label that is unknown.
+fn dialect_parameter_block(input: Span) -> ParseResult<DialectParameter> {
+    let char_block = |name: &'static str| {
+        map(
+            tuple((
+                ws(tag(name)),
+                ws(tag(":")),
+                cut(ws(char_parser)),
+                opt(ws(tag(","))),
+            )),
+            |(_, _, c, _)| c,
+        )
+    };
+
+    let quote_block = map(char_block("quote"), DialectParameter::Quote);
+
+    let escape_block = map(char_block("escape"), DialectParameter::Escape);
+
+    let comment_block = map(char_block("comment"), DialectParameter::Comment);
+
+    let null_values_block = map(
+        tuple((
+            ws(tag("null_values")),
+            ws(tag(":")),
+            cut(ws(string_list_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, v, _)| DialectParameter::NullValues(v),
+    );
+
+    let skip_rows_block = map(
+        tuple((
+            ws(tag("skip_rows")),
+            ws(tag(":")),
+            cut(ws(usize_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, n, _)| DialectParameter::SkipRows(n),
+    );
+
+    let truncated_rows_block = map(
+        tuple((
+            ws(tag("truncated_rows")),
+            ws(tag(":")),
+            cut(ws(bool_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, b, _)| DialectParameter::TruncatedRows(b),
+    );
+
+    let encoding_block = map(
+        tuple((
+            ws(tag("encoding")),
+            ws(tag(":")),
+            cut(ws(map_opt(string_parser, |s| {
+                Encoding::for_label(s.fragment().as_bytes()).map(|_| *s.fragment())
+            }))),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| DialectParameter::Encoding(s),
+    );
+
+    alt((
+        quote_block,
+        escape_block,
+        comment_block,
+        null_values_block,
+        skip_rows_block,
+        truncated_rows_block,
+        encoding_block,
+    ))(input)
+}
+
+/// "materialize" ":" (true|false)
+fn materialize_block(input: Span) -> ParseResult<bool> {
+    map(
+        tuple((
+            ws(tag("materialize")),
+            ws(tag(":")),
+            cut(ws(bool_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, b, _)| b,
+    )(input)
+}
+
+/// "compression" ":" compression
+fn compression_block(input: Span) -> ParseResult<Compression> {
+    map(
+        tuple((
+            ws(tag("compression")),
+            ws(tag(":")),
+            cut(context(COMPRESSION, ws(compression_parser))),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, c, _)| c,
+    )(input)
+}
+
+/// compression             = "gzip" | "zstd" | "bzip2" | "xz"
+fn compression_parser(input: Span) -> ParseResult<Compression> {
+    alt((
+        map(tag("gzip"), |_| Compression::Gzip),
+        map(tag("zstd"), |_| Compression::Zstd),
+        map(tag("bzip2"), |_| Compression::Bzip2),
+        map(tag("xz"), |_| Compression::Xz),
+    ))(input)
+}
+
+/// field_types ":" "(" field_type_descriptor ["," field_type_descriptor]* ","? ")"
+fn field_types_block(input: Span<'_>) -> ParseResult<'_, HashMap<&str, DataTypeDescriptor<'_>>> {
+    map(
+        tuple((
+            ws(tag("field_types")),
+            ws(tag(":")),
+            cut(ws(schema_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, s, _)| s,
+    )(input)
+}
+
+/// "(" \"string\" ["," \"string\"]* ","? ")"
+fn string_list_parser(input: Span<'_>) -> ParseResult<'_, Vec<&str>> {
+    let head = ws(tag("("));
+    let tail = tuple((opt(ws(tag(","))), ws(tag(")"))));
+    let parser = separated_list1(ws(tag(",")), ws(string_parser));
+
+    delimited(
+        head,
+        map(parser, |v| v.into_iter().map(|s| *s.fragment()).collect()),
+        tail,
+    )(input)
+}
+
+/// relationships           = "relationships" "(" relationship ["," relationship]* ","? ")"
+fn relationships_parser(input: Span) -> ParseResult<Vec<RelationshipData>> {
+    let start = tuple((ws(tag("relationships")), ws(tag("("))));
+    let mid = ws(separated_list1(ws(tag(",")), ws(relationship_parser)));
+    let end = tuple((ws(opt(tag(","))), ws(tag(")"))));
+
+    preceded(start, cut(context(RELATIONSHIP, terminated(mid, end))))(input)
+}
+
+/// relationship            = column_reference "->" column_reference
+///                           ["(" relationship_parameter ["," relationship_parameter]* ","? ")"]?
+fn relationship_parser(input: Span) -> ParseResult<RelationshipData> {
+    enum IntermediateResult {
+        Cardinality(Cardinality),
+        FilterDirection(FilterDirection),
+    }
+
+    let cardinality_block = map(
+        tuple((
+            ws(tag("cardinality")),
+            ws(tag(":")),
+            cut(context(CARDINALITY, ws(cardinality_parser))),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, c, _)| IntermediateResult::Cardinality(c),
+    );
+
+    let filter_direction_block = map(
+        tuple((
+            ws(tag("filter_direction")),
+            ws(tag(":")),
+            cut(context(FILTER_DIRECTION, ws(filter_direction_parser))),
+            opt(ws(tag(","))),
+        )),
+        |(_, _, d, _)| IntermediateResult::FilterDirection(d),
+    );
+
+    let parameters = preceded(
+        ws(tag("(")),
+        cut(context(
+            RELATIONSHIP_PARAMETER,
+            terminated(
+                many0(alt((cardinality_block, filter_direction_block))),
+                ws(tag(")")),
+            ),
+        )),
+    );
+
+    map(
+        tuple((
+            ws(column_reference_parser),
+            ws(tag("->")),
+            ws(column_reference_parser),
+            opt(parameters),
+        )),
+        |(from, _, to, parameters)| {
+            let mut cardinality = Cardinality::ManyToOne;
+            let mut filter_direction = FilterDirection::Single;
+
+            for p in parameters.unwrap_or_default() {
+                match p {
+                    IntermediateResult::Cardinality(c) => cardinality = c,
+                    IntermediateResult::FilterDirection(d) => filter_direction = d,
+                }
+            }
+
+            RelationshipData {
+                from,
+                to,
+                cardinality,
+                filter_direction,
+            }
+        },
+    )(input)
+}
+
+/// statement               = "sql" sql_string
+///                         | query
+pub fn statement_parser(input: Span) -> ParseResult<StatementData> {
+    alt((
+        map(
+            preceded(ws(tag("sql")), ws(sql_string_parser)),
+            StatementData::Sql,
+        ),
+        map(query_parser, StatementData::Query),
+    ))(input)
+}
+
+/// sql_string              = \"sql\"
+fn sql_string_parser(input: Span) -> ParseResult<String> {
+    let escaped = preceded(char('\\'), alt((char('\\'), char('"'))));
+    let sql = fold_many0(alt((escaped, none_of("\\\""))), String::new, |mut s, c| {
+        s.push(c);
+        s
+    });
+
+    delimited(char('"'), sql, char('"'))(input)
+}
+
+/// query                   = select_list ["where" expression ["and" expression]*]?
+pub fn query_parser(input: Span) -> ParseResult<QueryData> {
+    let filters = preceded(
+        ws(tag("where")),
+        separated_list1(ws(tag("and")), ws(expression_parser)),
+    );
+
+    map(
+        pair(select_list_parser, opt(filters)),
+        |(select, filters)| QueryData {
+            select,
+            filters: filters.unwrap_or_default(),
+        },
+    )(input)
+}
+
+/// select_list             = expression ["," expression]* ","?
+pub fn select_list_parser(input: Span) -> ParseResult<Vec<Expression>> {
+    map(
+        tuple((
+            separated_list1(ws(tag(",")), ws(expression_parser)),
+            opt(ws(tag(","))),
+        )),
+        |(e, _)| e,
+    )(input)
+}
+
+/// expression              = concatenation [comparison_operator concatenation]?
+pub fn expression_parser(input: Span) -> ParseResult<Expression> {
+    // the two character operators have to be tried first
+    let operator = alt((
+        map(tag("<>"), |_| BinaryOperator::NotEq),
+        map(tag("!="), |_| BinaryOperator::NotEq),
+        map(tag("<="), |_| BinaryOperator::LtEq),
+        map(tag(">="), |_| BinaryOperator::GtEq),
+        map(tag("="), |_| BinaryOperator::Eq),
+        map(tag("<"), |_| BinaryOperator::Lt),
+        map(tag(">"), |_| BinaryOperator::Gt),
+    ));
+
+    map(
+        pair(
+            concatenation_parser,
+            opt(pair(ws(operator), concatenation_parser)),
+        ),
+        |(left, right)| match right {
+            Some((op, right)) => Expression::Binary(Box::new(left), op, Box::new(right)),
+            None => left,
+        },
+    )(input)
+}
+
+/// concatenation           = arithmetic ["||" arithmetic]*
+fn concatenation_parser(input: Span) -> ParseResult<Expression> {
+    let (input, first) = arithmetic_parser(input)?;
+
+    fold_many0(
+        pair(ws(tag("||")), arithmetic_parser),
+        move || first.clone(),
+        |left, (_, right)| {
+            Expression::Binary(Box::new(left), BinaryOperator::Concat, Box::new(right))
+        },
+    )(input)
+}
+
+/// arithmetic              = term [("+" | "-") term]*
+fn arithmetic_parser(input: Span) -> ParseResult<Expression> {
+    let (input, first) = term_parser(input)?;
+
+    let operator = alt((
+        map(tag("+"), |_| BinaryOperator::Plus),
+        map(tag("-"), |_| BinaryOperator::Minus),
+    ));
+
+    fold_many0(
+        pair(ws(operator), term_parser),
+        move || first.clone(),
+        |left, (op, right)| Expression::Binary(Box::new(left), op, Box::new(right)),
+    )(input)
+}
+
+/// term                    = factor [("*" | "/") factor]*
+fn term_parser(input: Span) -> ParseResult<Expression> {
+    let (input, first) = factor_parser(input)?;
+
+    let operator = alt((
+        map(tag("*"), |_| BinaryOperator::Multiply),
+        map(tag("/"), |_| BinaryOperator::Divide),
+    ));
+
+    fold_many0(
+        pair(ws(operator), factor_parser),
+        move || first.clone(),
+        |left, (op, right)| Expression::Binary(Box::new(left), op, Box::new(right)),
+    )(input)
+}
+
+/// factor                  = aggregate_function "(" expression ")"
+///                         | "[" measure_name "]"
+///                         | "$" identifier
+///                         | number
+///                         | \'string\'
+///                         | column_reference
+///                         | identifier
+///                         | "(" expression ")"
+fn factor_parser(input: Span) -> ParseResult<Expression> {
+    let aggregate = map(
+        tuple((
+            ws(aggregate_function_parser),
+            delimited(ws(tag("(")), expression_parser, ws(tag(")"))),
+        )),
+        |(f, e)| Expression::Aggregate(f, Box::new(e)),
+    );
+
+    let measure = map(measure_reference_parser, |s| {
+        Expression::Measure(s.fragment())
+    });
+
+    let parameter = map(preceded(char('$'), identifier_parser), |s| {
+        Expression::Parameter(s.fragment())
+    });
+
+    let literal = map(
+        alt((
+            number_parser,
+            map(literal_string_parser, LiteralValue::Utf8),
+        )),
+        Expression::Literal,
+    );
+
+    let column = map(column_reference_parser, Expression::Column);
+
+    let unqualified_column = map(identifier_parser, |s| {
+        Expression::UnqualifiedColumn(s.fragment())
+    });
+
+    let parenthesized = delimited(ws(tag("(")), expression_parser, ws(tag(")")));
+
+    ws(alt((
+        aggregate,
+        measure,
+        parameter,
+        literal,
+        column,
+        unqualified_column,
+        parenthesized,
+    )))
+    .parse(input)
+}
+
+/// "[" measure_name "]"
+fn measure_reference_parser(input: Span) -> ParseResult<Span> {
+    delimited(char('['), take_until("]"), char(']'))(input)
+}
+
+/// integers become Int64, numbers with a fraction Float64
+fn number_parser(input: Span) -> ParseResult<LiteralValue> {
+    let integer = recognize(pair(opt(char('-')), digit1));
+    let float = recognize(tuple((opt(char('-')), digit1, char('.'), digit1)));
+
+    alt((
+        map_res(float, |s: Span| {
+            s.fragment().parse().map(LiteralValue::Float64)
+        }),
+        map_res(integer, |s: Span| {
+            s.fragment().parse().map(LiteralValue::Int64)
+        }),
+    ))(input)
+}
+
+/// string literals in expressions are enclosed in single quotes, a quote inside of a string is
+/// doubled like in 'O''Brien'
+fn literal_string_parser(input: Span<'_>) -> ParseResult<'_, Cow<'_, str>> {
+    let text = recognize(many0(alt((tag("''"), is_not("'")))));
+
+    map(delimited(char('\''), text, char('\'')), |s: Span| {
+        let s = *s.fragment();
+        if s.contains("''") {
+            Cow::Owned(s.replace("''", "'"))
+        } else {
+            Cow::Borrowed(s)
+        }
+    })(input)
+}
+
+/// calculated_columns      = "calculated_columns" "(" calculated_column ["," calculated_column]* ","? ")"
+fn calculated_columns_parser(input: Span) -> ParseResult<Vec<CalculatedColumnData>> {
+    let start = tuple((ws(tag("calculated_columns")), ws(tag("("))));
+    let mid = ws(separated_list1(ws(tag(",")), ws(calculated_column_parser)));
+    let end = tuple((ws(opt(tag(","))), ws(tag(")"))));
+
+    preceded(start, cut(context(CALCULATED_COLUMN, terminated(mid, end))))(input)
+}
+
+/// calculated_column       = column_reference "=" expression
+fn calculated_column_parser(input: Span) -> ParseResult<CalculatedColumnData> {
+    map(
+        separated_pair(column_reference_parser, ws(tag("=")), expression_parser),
+        |(column, expression)| CalculatedColumnData { column, expression },
+    )(input)
+}
+
+/// measures                = "measures" "(" measure ["," measure]* ","? ")"
+fn measures_parser(input: Span) -> ParseResult<Vec<MeasureData>> {
+    let start = tuple((ws(tag("measures")), ws(tag("("))));
+    let mid = ws(separated_list1(ws(tag(",")), ws(measure_parser)));
+    let end = tuple((ws(opt(tag(","))), ws(tag(")"))));
+
+    preceded(start, cut(context(MEASURE, terminated(mid, end))))(input)
+}
+
+/// measure                 = \"measure_name\" ":" expression
+fn measure_parser(input: Span) -> ParseResult<MeasureData> {
+    map(
+        separated_pair(string_parser, ws(tag(":")), expression_parser),
+        |(name, expression)| MeasureData {
+            name: name.fragment(),
+            expression,
+        },
+    )(input)
+}
+
+/// queries                 = "queries" "(" named_query [";" named_query]* ";"? ")"
+fn queries_parser(input: Span) -> ParseResult<Vec<NamedQueryData>> {
+    let start = tuple((ws(tag("queries")), ws(tag("("))));
+    let mid = ws(separated_list1(ws(tag(";")), ws(named_query_parser)));
+    let end = tuple((ws(opt(tag(";"))), ws(tag(")"))));
+
+    preceded(start, cut(context(NAMED_QUERY, terminated(mid, end))))(input)
+}
 
-    let has_header_block = map(
-        tuple((
-            ws(tag("has_header")),
-            ws(tag(":")),
-            ws(bool_parser),
-            opt(ws(tag(","))),
-        )),
-        |(_, _, b, _)| IntermediateResult::HasHeader(b),
+/// named_query             = \"query_name\" ["(" [query_parameter ["," query_parameter]* ","?]? ")"]?
+///                           ":" query
+fn named_query_parser(input: Span) -> ParseResult<NamedQueryData> {
+    let parameter = map(
+        separated_pair(ws(identifier_parser), ws(tag(":")), ws(data_type_parser)),
+        |(name, data_type)| QueryParameterData {
+            name: name.fragment(),
+            data_type,
+        },
     );
-
-    let max_read_records_block = map(
-        tuple((
-            ws(tag("max_read_records")),
-            ws(tag(":")),
-            ws(opt(usize_parser)),
-            opt(ws(tag(","))),
-        )),
-        |(_, _, n, _)| IntermediateResult::MaxReadRecords(n),
+    let parameters = delimited(
+        ws(tag("(")),
+        terminated(separated_list0(ws(tag(",")), parameter), opt(ws(tag(",")))),
+        ws(tag(")")),
     );
 
-    let field_types_block = map(
-        tuple((
-            ws(tag("field_types")),
-            ws(tag(":")),
-            ws(schema_parser),
-            opt(ws(tag(","))),
-        )),
-        |(_, _, s, _)| IntermediateResult::FieldTypes(s),
-    );
+    map(
+        tuple((string_parser, opt(parameters), ws(tag(":")), query_parser)),
+        |(name, parameters, _, query)| NamedQueryData {
+            name: name.fragment(),
+            parameters: parameters.unwrap_or_default(),
+            query,
+        },
+    )(input)
+}
 
-    let any_of_that = many0(alt((
-        csv_file_name_block,
-        field_types_block,
-        delimiter_block,
-        has_header_block,
-        max_read_records_block,
-    )));
+fn aggregate_function_parser(input: Span) -> ParseResult<AggregateFunction> {
+    alt((
+        map(tag("sum"), |_| AggregateFunction::Sum),
+        map(tag("min"), |_| AggregateFunction::Min),
+        map(tag("max"), |_| AggregateFunction::Max),
+        map(tag("avg"), |_| AggregateFunction::Avg),
+        map(tag("count"), |_| AggregateFunction::Count),
+    ))(input)
+}
 
-    map(opt(any_of_that), |ds| {
-        let mut csv_file_path = "";
-        let mut field_types = HashMap::new();
-        let mut delimiter: u8 = b',';
-        let mut has_header = true;
-        let mut max_read_records = Some(100);
+fn cardinality_parser(input: Span) -> ParseResult<Cardinality> {
+    alt((
+        map(tag("many_to_one"), |_| Cardinality::ManyToOne),
+        map(tag("one_to_one"), |_| Cardinality::OneToOne),
+        map(tag("many_to_many"), |_| Cardinality::ManyToMany),
+    ))(input)
+}
 
-        if let Some(ds) = ds {
-            for d in ds {
-                match d {
-                    IntermediateResult::CSVFileName(s) => csv_file_path = s,
-                    IntermediateResult::Delimiter(c) => delimiter = c,
-                    IntermediateResult::FieldTypes(s) => field_types = s,
-                    IntermediateResult::HasHeader(b) => has_header = b,
-                    IntermediateResult::MaxReadRecords(n) => max_read_records = n,
-                }
-            }
-        }
+fn filter_direction_parser(input: Span) -> ParseResult<FilterDirection> {
+    alt((
+        map(tag("single"), |_| FilterDirection::Single),
+        map(tag("both"), |_| FilterDirection::Both),
+    ))(input)
+}
 
-        FileDescriptorData::CSV(CSVData {
-            csv_file_path,
-            field_types,
-            delimiter,
-            max_read_records,
-            has_header,
-        })
-    })(input)
+/// column_reference        = name "." name
+fn column_reference_parser(input: Span) -> ParseResult<ColumnReference> {
+    map(
+        separated_pair(name_parser, char('.'), name_parser),
+        |(table, column)| ColumnReference { table, column },
+    )(input)
+}
+
+/// name                    = identifier | \"name\"
+fn name_parser(input: Span<'_>) -> ParseResult<'_, &str> {
+    map(alt((string_parser, identifier_parser)), |s| *s.fragment())(input)
+}
+
+/// identifiers start with a letter or an underscore, followed by letters, digits or underscores
+fn identifier_parser(input: Span) -> ParseResult<Span> {
+    recognize(pair(
+        alt((alpha1, tag("_"))),
+        many0(alt((alphanumeric1, tag("_")))),
+    ))(input)
 }
 
-fn schema_parser(input: Span) -> IResult<Span, HashMap<&str, DataTypeDescriptor>> {
+fn schema_parser(input: Span<'_>) -> ParseResult<'_, HashMap<&str, DataTypeDescriptor<'_>>> {
     let head = ws(tag("("));
     let tail = tuple((opt(ws(tag(","))), ws(tag(")"))));
     let parser = separated_list1(ws(tag(",")), schema_entry_parser);
@@ -207,31 +1981,39 @@ fn schema_parser(input: Span) -> IResult<Span, HashMap<&str, DataTypeDescriptor>
     delimited(head, parser_map, tail)(input)
 }
 
-fn schema_entry_parser(input: Span) -> IResult<Span, (Span, DataTypeDescriptor)> {
-    separated_pair(string_parser, ws(tag(":")), data_type_parser)(input)
+fn schema_entry_parser(input: Span) -> ParseResult<(Span, DataTypeDescriptor)> {
+    separated_pair(
+        string_parser,
+        ws(tag(":")),
+        cut(context(DATA_TYPE, data_type_parser)),
+    )(input)
 }
 
 /// strings have the shape (double quotes) string (w/o double quotes) (double quotes)
-fn string_parser(input: Span) -> IResult<Span, Span> {
+fn string_parser(input: Span) -> ParseResult<Span> {
     delimited(char('"'), take_until("\""), char('"'))(input)
 }
 
 /// parse a single char in double quotes
-fn char_parser(input: Span) -> IResult<Span, u8> {
+fn char_parser(input: Span) -> ParseResult<u8> {
     delimited(char('"'), anychar, char('"'))(input).map(|(s, c)| (s, c as u8))
 }
 
 #[test]
 fn char_parser_test() {
-    assert_eq!(char_parser(Span::new("\";\"")), Ok((Span::new(""), b';')));
-    assert_eq!(char_parser(Span::new("\",\"")), Ok((Span::new(""), b',')));
+    let (rest, c) = char_parser(Span::new("\";\"")).unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(c, b';');
+    let (rest, c) = char_parser(Span::new("\",\"")).unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(c, b',');
 }
 
-fn usize_parser(input: Span) -> IResult<Span, usize> {
+fn usize_parser(input: Span) -> ParseResult<usize> {
     u32(input).map(|(s, d)| (s, d as usize))
 }
 
-fn time_unit_parser(input: Span) -> IResult<Span, TimeUnit> {
+fn time_unit_parser(input: Span) -> ParseResult<TimeUnit> {
     let nanoseconds_parser = map(ws(tag("nanoseconds")), |_| TimeUnit::Nanosecond);
     let microseconds_parser = map(ws(tag("microseconds")), |_| TimeUnit::Microsecond);
     let milliseconds_parser = map(ws(tag("milliseconds")), |_| TimeUnit::Millisecond);
@@ -250,19 +2032,19 @@ fn time_unit_parser(input: Span) -> IResult<Span, TimeUnit> {
     )(input)
 }
 
-fn is_nullable_parser(input: Span) -> IResult<Span, bool> {
+fn is_nullable_parser(input: Span) -> ParseResult<bool> {
     map(
         tuple((
             ws(tag("is_nullable")),
             ws(tag(":")),
-            ws(bool_parser),
+            cut(ws(bool_parser)),
             opt(ws(tag(","))),
         )),
         |(_, _, b, _)| b,
     )(input)
 }
 
-fn is_nullable_parameter_parser(input: Span) -> IResult<Span, bool> {
+fn is_nullable_parameter_parser(input: Span) -> ParseResult<bool> {
     map(
         opt(delimited(
             ws(tag("(")),
@@ -281,19 +2063,19 @@ fn is_nullable_parameter_parser(input: Span) -> IResult<Span, bool> {
     )(input)
 }
 
-fn format_parser(input: Span) -> IResult<Span, Span> {
+fn format_parser(input: Span) -> ParseResult<Span> {
     map(
         tuple((
             ws(tag("format")),
             ws(tag(":")),
-            ws(string_parser),
+            cut(ws(string_parser)),
             opt(ws(tag(","))),
         )),
         |(_, _, s, _)| s,
     )(input)
 }
 
-fn time_unit_parameter_parser(input: Span) -> IResult<Span, (TimeUnit, bool)> {
+fn time_unit_parameter_parser(input: Span) -> ParseResult<(TimeUnit, bool)> {
     enum IntermediateResult {
         Bool(bool),
         TimeUnit(TimeUnit),
@@ -325,7 +2107,7 @@ fn time_unit_parameter_parser(input: Span) -> IResult<Span, (TimeUnit, bool)> {
     })(input)
 }
 
-fn format_parameter_parser(input: Span) -> IResult<Span, (Span, bool)> {
+fn format_parameter_parser(input: Span) -> ParseResult<(Span, bool)> {
     enum IntermediateResult<'a> {
         Bool(bool),
         Str(Span<'a>),
@@ -356,26 +2138,24 @@ fn format_parameter_parser(input: Span) -> IResult<Span, (Span, bool)> {
 
 #[test]
 fn format_parameter_parser_test() {
-    assert_eq!(
-        format_parameter_parser(Span::new("")),
-        Ok((Span::new(""), (Span::new(""), true)))
-    );
-    assert_eq!(
-        format_parameter_parser(Span::new("()")),
-        Ok((Span::new(""), (Span::new(""), true)))
-    );
-    assert_eq!(
-        format_parameter_parser(Span::new("(format : \"%Y\", is_nullable: false)")),
-        Ok((Span::new(""), (Span::new("%Y"), false)))
-    );
-    assert_eq!(
-        format_parameter_parser(Span::new("(is_nullable: false, format : \"%Y\")")),
-        Ok((Span::new(""), (Span::new("%Y"), false)))
-    );
+    let (rest, (format, is_nullable)) = format_parameter_parser(Span::new("")).unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!((*format.fragment(), is_nullable), ("", true));
+    let (rest, (format, is_nullable)) = format_parameter_parser(Span::new("()")).unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!((*format.fragment(), is_nullable), ("", true));
+    let (rest, (format, is_nullable)) =
+        format_parameter_parser(Span::new("(format : \"%Y\", is_nullable: false)")).unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!((*format.fragment(), is_nullable), ("%Y", false));
+    let (rest, (format, is_nullable)) =
+        format_parameter_parser(Span::new("(is_nullable: false, format : \"%Y\")")).unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!((*format.fragment(), is_nullable), ("%Y", false));
 }
 
 /// A parser for data types
-fn data_type_parser(input: Span) -> IResult<Span, DataTypeDescriptor> {
+fn data_type_parser(input: Span) -> ParseResult<DataTypeDescriptor> {
     let boolean_type_parser = map(
         tuple((ws(tag("boolean")), ws(is_nullable_parameter_parser))),
         |(_, b)| DataTypeDescriptor::Boolean(b),
@@ -484,7 +2264,7 @@ fn data_type_parser(input: Span) -> IResult<Span, DataTypeDescriptor> {
     ))(input)
 }
 
-fn bool_parser(i: Span) -> IResult<Span, bool> {
+fn bool_parser(i: Span) -> ParseResult<bool> {
     alt((map(tag("true"), |_| true), map(tag("false"), |_| false))).parse(i)
 }
 
@@ -493,24 +2273,89 @@ fn ast_parser_test() {
     let mut expected_schema = HashMap::new();
     expected_schema.insert("asdf", DataTypeDescriptor::Date(false, "%Y"));
 
-    assert_eq!(
-        ast_parser(Span::new(
-            "load_files (
+    let (rest, ast) = ast_parser(Span::new(
+        "load_files (
                 csv_file_name: \"dir/fn.csv\", 
-                field_types: (\"asdf\": date( format: \"%Y\", is_nullable: false)) )"
-        )),
-        Ok((
-            Span::new(""),
-            Ast {
-                file_descriptors: vec![FileDescriptorData::CSV(CSVData {
-                    csv_file_path: "dir/fn.csv",
-                    field_types: expected_schema,
-                    delimiter: b',',
-                    max_read_records: Some(100),
-                    has_header: true
-                })]
-            }
-        ))
+                field_types: (\"asdf\": date( format: \"%Y\", is_nullable: false)) )",
+    ))
+    .unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(
+        ast,
+        Ast {
+            file_descriptors: vec![FileDescriptorData::CSV(CSVData {
+                csv_file_path: "dir/fn.csv",
+                field_types: expected_schema,
+                delimiter: b',',
+                max_read_records: Some(100),
+                has_header: true,
+                dialect: CSVDialect::default(),
+                compression: None,
+                materialize: true,
+                table_options: TableOptions::default(),
+            })],
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn relationships_parser_test() {
+    let result = ast_parser(Span::new(
+        "load_files (
+            (csv_file_name: \"dir/FactSales.csv\"),
+            (csv_file_name: \"dir/DimProduct.csv\"),
+        )
+        relationships (
+            FactSales.ProductKey -> DimProduct.ProductKey,
+            FactSales.DateKey -> \"Dim Date\".DateKey (filter_direction: both, cardinality: many_to_one),
+            DimProduct.ProductKey -> DimProductExtra.ProductKey (cardinality: one_to_one),
+        )",
+    ));
+
+    let (rest, ast) = result.unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(ast.file_descriptors.len(), 2);
+    assert_eq!(
+        ast.relationships,
+        vec![
+            RelationshipData {
+                from: ColumnReference {
+                    table: "FactSales",
+                    column: "ProductKey"
+                },
+                to: ColumnReference {
+                    table: "DimProduct",
+                    column: "ProductKey"
+                },
+                cardinality: Cardinality::ManyToOne,
+                filter_direction: FilterDirection::Single,
+            },
+            RelationshipData {
+                from: ColumnReference {
+                    table: "FactSales",
+                    column: "DateKey"
+                },
+                to: ColumnReference {
+                    table: "Dim Date",
+                    column: "DateKey"
+                },
+                cardinality: Cardinality::ManyToOne,
+                filter_direction: FilterDirection::Both,
+            },
+            RelationshipData {
+                from: ColumnReference {
+                    table: "DimProduct",
+                    column: "ProductKey"
+                },
+                to: ColumnReference {
+                    table: "DimProductExtra",
+                    column: "ProductKey"
+                },
+                cardinality: Cardinality::OneToOne,
+                filter_direction: FilterDirection::Single,
+            },
+        ]
     );
 }
 
@@ -519,152 +2364,848 @@ fn file_descriptor_parser_test() {
     let mut expected_schema = HashMap::new();
     expected_schema.insert("asdf", DataTypeDescriptor::Date(false, "%Y"));
 
-    assert_eq!(
-        file_descriptor_parser(Span::new(
-            "csv_file_name: \"dir/fn.csv\", 
+    let (rest, descriptor) = file_descriptor_parser(Span::new(
+        "csv_file_name: \"dir/fn.csv\", 
             field_types: ( \"asdf\": date(format: \"%Y\", is_nullable: false)), 
             delimiter: \";\",
             max_read_records: 200,
-            has_header: false"
-        )),
-        Ok((
-            Span::new(""),
-            FileDescriptorData::CSV(CSVData {
-                csv_file_path: "dir/fn.csv",
-                field_types: expected_schema,
-                delimiter: b';',
-                max_read_records: Some(200),
-                has_header: false
-            })
-        ))
+            has_header: false",
+    ))
+    .unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(
+        descriptor,
+        FileDescriptorData::CSV(CSVData {
+            csv_file_path: "dir/fn.csv",
+            field_types: expected_schema,
+            delimiter: b';',
+            max_read_records: Some(200),
+            has_header: false,
+            dialect: CSVDialect::default(),
+            compression: None,
+            materialize: true,
+            table_options: TableOptions::default(),
+        })
+    );
+}
+
+#[test]
+fn dialect_parameter_parser_test() {
+    let (rest, descriptor) = file_descriptor_parser(Span::new(
+        "csv_file_name: \"exports/Umsatz.csv\",
+            delimiter: \";\",
+            quote: \"'\",
+            escape: \"\\\",
+            comment: \"#\",
+            null_values: (\"\", \"NULL\", \"n/a\"),
+            skip_rows: 2,
+            truncated_rows: true,
+            encoding: \"latin1\"",
+    ))
+    .unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(
+        descriptor,
+        FileDescriptorData::CSV(CSVData {
+            dialect: CSVDialect {
+                quote: b'\'',
+                escape: Some(b'\\'),
+                comment: Some(b'#'),
+                null_values: vec!["", "NULL", "n/a"],
+                skip_rows: 2,
+                truncated_rows: true,
+                encoding: Some("latin1"),
+            },
+            delimiter: b';',
+            ..CSVData::new("exports/Umsatz.csv")
+        })
+    );
+
+    assert!(parse_script(
+        "load_files ( (csv_file_name: \"exports/Umsatz.csv\", encoding: \"klingon\") )"
     )
+    .is_err());
 }
 
 #[test]
-fn schema_entry_parser_test() {
+fn table_parameter_parser_test() {
+    let (rest, descriptor) = file_descriptor_parser(Span::new(
+        "csv_file_name: \"data/daily/*/sales_*.csv\",
+            table_name: \"FactSales\",
+            file_name_column: \"SourceFile\",
+            partition_columns: (\"year\", \"month\")",
+    ))
+    .unwrap();
+    assert_eq!(rest.fragment(), &"");
     assert_eq!(
-        schema_entry_parser(Span::new("\"asdf\": boolean")),
-        Ok((
-            Span::new(""),
-            (Span::new("asdf"), DataTypeDescriptor::Boolean(true))
+        descriptor,
+        FileDescriptorData::CSV(CSVData {
+            table_options: TableOptions {
+                table_name: Some("FactSales"),
+                schema: None,
+                file_name_column: Some("SourceFile"),
+                partition_columns: vec!["year", "month"],
+            },
+            delimiter: b',',
+            ..CSVData::new("data/daily/*/sales_*.csv")
+        })
+    );
+
+    let (rest, descriptor) = file_descriptor_parser(Span::new(
+        "parquet_file_name: \"archive/2023/FactSales.parquet\",
+            alias: \"Sales\",
+            schema: \"archive\"",
+    ))
+    .unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(
+        descriptor,
+        FileDescriptorData::Parquet(ParquetData {
+            parquet_file_path: "archive/2023/FactSales.parquet",
+            columns: None,
+            field_types: HashMap::new(),
+            materialize: true,
+            table_options: TableOptions {
+                table_name: Some("Sales"),
+                schema: Some("archive"),
+                ..Default::default()
+            },
+        })
+    );
+}
+
+#[test]
+fn parquet_descriptor_parser_test() {
+    let mut expected_schema = HashMap::new();
+    expected_schema.insert("SalesAmount", DataTypeDescriptor::Float64(false));
+
+    let (rest, descriptor) = file_descriptor_parser(Span::new(
+        "parquet_file_name: \"dir/FactSales.parquet\",
+            columns: (\"ProductKey\", \"SalesAmount\"),
+            field_types: ( \"SalesAmount\": float64(is_nullable: false)),
+            materialize: false",
+    ))
+    .unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(
+        descriptor,
+        FileDescriptorData::Parquet(ParquetData {
+            parquet_file_path: "dir/FactSales.parquet",
+            columns: Some(vec!["ProductKey", "SalesAmount"]),
+            field_types: expected_schema,
+            materialize: false,
+            table_options: TableOptions::default(),
+        })
+    );
+
+    assert!(parquet_descriptor_parser(Span::new("csv_file_name: \"dir/fn.csv\"")).is_err());
+}
+
+#[test]
+fn json_descriptor_parser_test() {
+    let mut expected_schema = HashMap::new();
+    expected_schema.insert(
+        "Timestamp",
+        DataTypeDescriptor::Datetime(true, "%Y-%m-%dT%H:%M:%S"),
+    );
+
+    let (rest, descriptor) = file_descriptor_parser(Span::new(
+        "json_file_name: \"logs/events.ndjson\",
+            max_read_records: 1000,
+            field_types: ( \"Timestamp\": datetime(format: \"%Y-%m-%dT%H:%M:%S\"))",
+    ))
+    .unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(
+        descriptor,
+        FileDescriptorData::JSON(JSONData {
+            json_file_path: "logs/events.ndjson",
+            field_types: expected_schema,
+            max_read_records: Some(1000),
+            compression: None,
+            materialize: true,
+            table_options: TableOptions::default(),
+        })
+    );
+
+    let (rest, descriptor) = file_descriptor_parser(Span::new(
+        "json_file_name: \"logs/events.ndjson.gzip\",
+            compression: gzip",
+    ))
+    .unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(
+        descriptor,
+        FileDescriptorData::JSON(JSONData {
+            json_file_path: "logs/events.ndjson.gzip",
+            field_types: HashMap::new(),
+            max_read_records: Some(100),
+            compression: Some(Compression::Gzip),
+            materialize: true,
+            table_options: TableOptions::default(),
+        })
+    );
+
+    let error = parse_script(
+        "load_files ( (json_file_name: \"logs/events.ndjson.gzip\", compression: zip) )",
+    )
+    .unwrap_err();
+    assert_eq!(error.expected, COMPRESSION);
+}
+
+#[test]
+fn avro_descriptor_parser_test() {
+    let mut expected_schema = HashMap::new();
+    expected_schema.insert(
+        "Timestamp",
+        DataTypeDescriptor::Datetime(false, "%Y-%m-%dT%H:%M:%S"),
+    );
+
+    assert_eq!(
+        file_descriptor_parser(Span::new(
+            "avro_file_name: \"archive/events-*.avro\",
+            field_types: ( \"Timestamp\": datetime(format: \"%Y-%m-%dT%H:%M:%S\", is_nullable: false)),
+            materialize: false,
+            table_name: \"Events\""
         ))
+        .map(|(_, descriptor)| descriptor),
+        Ok(FileDescriptorData::Avro(AvroData {
+            avro_file_path: "archive/events-*.avro",
+            field_types: expected_schema,
+            materialize: false,
+            table_options: TableOptions {
+                table_name: Some("Events"),
+                ..Default::default()
+            },
+        }))
     );
 }
 
 #[test]
-fn schema_parser_test() {
-    let mut expected_result = HashMap::new();
-    expected_result.insert("asdf", DataTypeDescriptor::String(false));
+fn arrow_descriptor_parser_test() {
+    let ast = parse_script(
+        "load_files (
+            (arrow_file_name: \"snapshot/FactSales.arrow\", table_name: \"FactSales\"),
+        )",
+    )
+    .unwrap();
 
     assert_eq!(
-        schema_parser(Span::new("( \"asdf\": string(is_nullable: false) )")),
-        Ok((Span::new(""), expected_result))
+        ast.file_descriptors,
+        vec![FileDescriptorData::Arrow(ArrowData {
+            arrow_file_path: "snapshot/FactSales.arrow",
+            field_types: HashMap::new(),
+            table_options: TableOptions {
+                table_name: Some("FactSales"),
+                ..Default::default()
+            },
+        })]
     );
 }
 
 #[test]
-fn string_parser_test() {
+fn expression_display_test() {
+    let measure = "((sum(\"FactSales\".\"SalesAmount\") - [Total Cost]) / 2.0)";
+    let (rest, expression) = expression_parser(Span::new(measure)).unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(expression.to_string(), measure);
+
+    let filter = "(\"DimProduct\".\"Color\" <> 'Red')";
+    let (_, expression) = expression_parser(Span::new(filter)).unwrap();
+    assert_eq!(expression.to_string(), filter);
+}
+
+#[test]
+fn odbc_descriptor_parser_test() {
+    let ast = parse_script(
+        "load_files (
+            odbc(
+                connection_string: \"Driver={SQLite3};Database=fixtures/contoso.db\",
+                query: \"SELECT * FROM DimProduct WHERE Color <> 'Red'\",
+                table_name: \"DimProduct\",
+            ),
+            (
+                connection_string: \"DSN=contoso\",
+                query: \"SELECT StoreKey, StoreName FROM DimStore\",
+                batch_size: 100,
+                max_text_size: 255,
+                alias: \"DimStore\",
+                schema: \"reference\",
+            ),
+        )",
+    )
+    .unwrap();
+
     assert_eq!(
-        string_parser(Span::new("\"Hello, world!\"")),
-        Ok((Span::new(""), Span::new("Hello, world!")))
+        ast.file_descriptors,
+        vec![
+            FileDescriptorData::ODBC(ODBCData {
+                connection_string: "Driver={SQLite3};Database=fixtures/contoso.db",
+                query: "SELECT * FROM DimProduct WHERE Color <> 'Red'",
+                batch_size: 1024,
+                max_text_size: None,
+                table_options: TableOptions {
+                    table_name: Some("DimProduct"),
+                    ..Default::default()
+                },
+            }),
+            FileDescriptorData::ODBC(ODBCData {
+                connection_string: "DSN=contoso",
+                query: "SELECT StoreKey, StoreName FROM DimStore",
+                batch_size: 100,
+                max_text_size: Some(255),
+                table_options: TableOptions {
+                    table_name: Some("DimStore"),
+                    schema: Some("reference"),
+                    ..Default::default()
+                },
+            }),
+        ]
     );
+
+    // the query is required
+    assert!(parse_script(
+        "load_files ( odbc( connection_string: \"DSN=contoso\", table_name: \"DimStore\" ) )"
+    )
+    .is_err());
 }
 
 #[test]
-fn data_type_parser_test() {
+fn sqlite_descriptor_parser_test() {
+    let mut expected_schema = HashMap::new();
+    expected_schema.insert("StartDate", DataTypeDescriptor::Date(true, "%Y-%m-%d"));
+
+    let ast = parse_script(
+        "load_files (
+            sqlite(
+                path: \"fixtures/contoso.db\",
+                table: \"DimPromotion\",
+                field_types: ( \"StartDate\": date(format: \"%Y-%m-%d\") ),
+            ),
+            sqlite(
+                path: \"fixtures/contoso.db\",
+                query: \"SELECT * FROM DimProduct WHERE Color = 'Black'\",
+                table_name: \"BlackProducts\",
+            ),
+        )",
+    )
+    .unwrap();
+
     assert_eq!(
-        data_type_parser("boolean"),
-        Ok(("", DataTypeDescriptor::Boolean(true)))
+        ast.file_descriptors,
+        vec![
+            FileDescriptorData::SQLite(SQLiteData {
+                sqlite_file_path: "fixtures/contoso.db",
+                source: SQLiteSource::Table("DimPromotion"),
+                field_types: expected_schema,
+                table_options: TableOptions::default(),
+            }),
+            FileDescriptorData::SQLite(SQLiteData {
+                sqlite_file_path: "fixtures/contoso.db",
+                source: SQLiteSource::Query("SELECT * FROM DimProduct WHERE Color = 'Black'"),
+                field_types: HashMap::new(),
+                table_options: TableOptions {
+                    table_name: Some("BlackProducts"),
+                    ..Default::default()
+                },
+            }),
+        ]
+    );
+
+    // either a table or a query
+    assert!(parse_script(
+        "load_files ( sqlite( path: \"fixtures/contoso.db\", table: \"DimStore\", query: \"SELECT 1\" ) )"
+    )
+    .is_err());
+    assert!(parse_script("load_files ( sqlite( path: \"fixtures/contoso.db\" ) )").is_err());
+}
+
+#[test]
+fn xlsx_descriptor_parser_test() {
+    let mut expected_schema = HashMap::new();
+    expected_schema.insert(
+        "Closed",
+        DataTypeDescriptor::Datetime(true, "%Y-%m-%d %H:%M"),
     );
 
+    let ast = parse_script(
+        "load_files (
+            xlsx(
+                path: \"fixtures/Budget.xlsx\",
+                field_types: ( \"Closed\": datetime(format: \"%Y-%m-%d %H:%M\") ),
+            ),
+            xlsx(
+                path: \"fixtures/Budget.xlsx\",
+                sheet: \"Notes\",
+                range: \"$B$3:AA5\",
+                has_header: false,
+                schema: \"excel\",
+            ),
+        )",
+    )
+    .unwrap();
+
     assert_eq!(
-        data_type_parser("boolean(is_nullable: false)"),
-        Ok(("", DataTypeDescriptor::Boolean(false)))
+        ast.file_descriptors,
+        vec![
+            FileDescriptorData::XLSX(XLSXData {
+                xlsx_file_path: "fixtures/Budget.xlsx",
+                sheet: None,
+                range: None,
+                has_header: true,
+                field_types: expected_schema,
+                table_options: TableOptions::default(),
+            }),
+            FileDescriptorData::XLSX(XLSXData {
+                xlsx_file_path: "fixtures/Budget.xlsx",
+                sheet: Some("Notes"),
+                range: Some(CellRange {
+                    start: (2, 1),
+                    end: (4, 26),
+                }),
+                has_header: false,
+                field_types: HashMap::new(),
+                table_options: TableOptions {
+                    schema: Some("excel"),
+                    ..Default::default()
+                },
+            }),
+        ]
+    );
+
+    // the end of a range cannot be above or left of its start
+    assert!(parse_script(
+        "load_files ( xlsx( path: \"fixtures/Budget.xlsx\", range: \"C3:B5\" ) )"
+    )
+    .is_err());
+    assert!(parse_script(
+        "load_files ( xlsx( path: \"fixtures/Budget.xlsx\", range: \"B0:C5\" ) )"
+    )
+    .is_err());
+    assert!(parse_script("load_files ( xlsx( sheet: \"Notes\" ) )").is_err());
+}
+
+#[test]
+fn select_list_parser_test() {
+    let (rest, expressions) =
+        select_list_parser(Span::new("DimProduct.Color, sum(FactSales.SalesAmount)")).unwrap();
+
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(
+        expressions,
+        vec![
+            Expression::Column(ColumnReference {
+                table: "DimProduct",
+                column: "Color"
+            }),
+            Expression::Aggregate(
+                AggregateFunction::Sum,
+                Box::new(Expression::Column(ColumnReference {
+                    table: "FactSales",
+                    column: "SalesAmount"
+                }))
+            ),
+        ]
     );
 
+    // a column of a table whose name starts like an aggregate function
     assert_eq!(
-        data_type_parser("uint8"),
-        Ok(("", DataTypeDescriptor::UInt8(true)))
+        expression_parser(Span::new("counters.Value")).map(|(_, e)| e),
+        Ok(Expression::Column(ColumnReference {
+            table: "counters",
+            column: "Value"
+        }))
     );
+}
 
+#[test]
+fn query_parser_test() {
+    let (rest, query) = query_parser(Span::new(
+        "DimProduct.Color, sum(FactSales.SalesAmount)
+         where DimDate.CalendarYear >= 2009 and DimProduct.Color <> 'Red'",
+    ))
+    .unwrap();
+
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(query.select.len(), 2);
     assert_eq!(
-        data_type_parser("uint16"),
-        Ok(("", DataTypeDescriptor::UInt16(true)))
+        query.filters,
+        vec![
+            Expression::Binary(
+                Box::new(Expression::Column(ColumnReference {
+                    table: "DimDate",
+                    column: "CalendarYear"
+                })),
+                BinaryOperator::GtEq,
+                Box::new(Expression::Literal(LiteralValue::Int64(2009))),
+            ),
+            Expression::Binary(
+                Box::new(Expression::Column(ColumnReference {
+                    table: "DimProduct",
+                    column: "Color"
+                })),
+                BinaryOperator::NotEq,
+                Box::new(Expression::Literal(LiteralValue::Utf8("Red".into()))),
+            ),
+        ]
     );
+
+    // without a where clause
+    let (rest, query) = query_parser(Span::new("[Total Sales]")).unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(query.filters, vec![]);
+}
+
+#[test]
+fn calculated_columns_parser_test() {
+    let (rest, columns) = calculated_columns_parser(Span::new(
+        "calculated_columns (
+            DimCustomer.FullName = FirstName || ' ' || DimCustomer.LastName,
+        )",
+    ))
+    .unwrap();
+
+    assert_eq!(rest.fragment(), &"");
     assert_eq!(
-        data_type_parser("uint32"),
-        Ok(("", DataTypeDescriptor::UInt32(true)))
+        columns,
+        vec![CalculatedColumnData {
+            column: ColumnReference {
+                table: "DimCustomer",
+                column: "FullName"
+            },
+            expression: Expression::Binary(
+                Box::new(Expression::Binary(
+                    Box::new(Expression::UnqualifiedColumn("FirstName")),
+                    BinaryOperator::Concat,
+                    Box::new(Expression::Literal(LiteralValue::Utf8(" ".into()))),
+                )),
+                BinaryOperator::Concat,
+                Box::new(Expression::Column(ColumnReference {
+                    table: "DimCustomer",
+                    column: "LastName"
+                })),
+            ),
+        }]
     );
+}
+
+#[test]
+fn measures_parser_test() {
+    let (rest, measures) = measures_parser(Span::new(
+        "measures (
+            \"Total Sales\": sum(FactSales.SalesAmount),
+            \"Margin %\": ([Total Sales] - [Total Cost]) / [Total Sales] * 100,
+        )",
+    ))
+    .unwrap();
+
+    assert_eq!(rest.fragment(), &"");
     assert_eq!(
-        data_type_parser("uint64"),
-        Ok(("", DataTypeDescriptor::UInt64(true)))
+        measures,
+        vec![
+            MeasureData {
+                name: "Total Sales",
+                expression: Expression::Aggregate(
+                    AggregateFunction::Sum,
+                    Box::new(Expression::Column(ColumnReference {
+                        table: "FactSales",
+                        column: "SalesAmount"
+                    }))
+                ),
+            },
+            MeasureData {
+                name: "Margin %",
+                expression: Expression::Binary(
+                    Box::new(Expression::Binary(
+                        Box::new(Expression::Binary(
+                            Box::new(Expression::Measure("Total Sales")),
+                            BinaryOperator::Minus,
+                            Box::new(Expression::Measure("Total Cost")),
+                        )),
+                        BinaryOperator::Divide,
+                        Box::new(Expression::Measure("Total Sales")),
+                    )),
+                    BinaryOperator::Multiply,
+                    Box::new(Expression::Literal(LiteralValue::Int64(100))),
+                ),
+            },
+        ]
     );
+}
+
+#[test]
+fn queries_parser_test() {
+    let (rest, queries) = queries_parser(Span::new(
+        "queries (
+            \"top_products\"(year: int32, since: date(format: \"%d.%m.%Y\"),):
+                DimProduct.Color, [Total Sales]
+                where DimDate.CalendarYear = $year and DimDate.Date >= $since;
+            \"colors\": DimProduct.Color;
+        )",
+    ))
+    .unwrap();
+
+    assert_eq!(rest.fragment(), &"");
     assert_eq!(
-        data_type_parser("int8"),
-        Ok(("", DataTypeDescriptor::Int8(true)))
+        queries,
+        vec![
+            NamedQueryData {
+                name: "top_products",
+                parameters: vec![
+                    QueryParameterData {
+                        name: "year",
+                        data_type: DataTypeDescriptor::Int32(true),
+                    },
+                    QueryParameterData {
+                        name: "since",
+                        data_type: DataTypeDescriptor::Date(true, "%d.%m.%Y"),
+                    },
+                ],
+                query: QueryData {
+                    select: vec![
+                        Expression::Column(ColumnReference {
+                            table: "DimProduct",
+                            column: "Color"
+                        }),
+                        Expression::Measure("Total Sales"),
+                    ],
+                    filters: vec![
+                        Expression::Binary(
+                            Box::new(Expression::Column(ColumnReference {
+                                table: "DimDate",
+                                column: "CalendarYear"
+                            })),
+                            BinaryOperator::Eq,
+                            Box::new(Expression::Parameter("year")),
+                        ),
+                        Expression::Binary(
+                            Box::new(Expression::Column(ColumnReference {
+                                table: "DimDate",
+                                column: "Date"
+                            })),
+                            BinaryOperator::GtEq,
+                            Box::new(Expression::Parameter("since")),
+                        ),
+                    ],
+                },
+            },
+            NamedQueryData {
+                name: "colors",
+                parameters: vec![],
+                query: QueryData {
+                    select: vec![Expression::Column(ColumnReference {
+                        table: "DimProduct",
+                        column: "Color"
+                    })],
+                    filters: vec![],
+                },
+            },
+        ]
     );
+
+    // the definition is written back in the syntax of the dsl
     assert_eq!(
-        data_type_parser("int16"),
-        Ok(("", DataTypeDescriptor::Int16(true)))
+        queries[0].to_string(),
+        "\"top_products\"(year: int32, since: date(format: \"%d.%m.%Y\")): \
+         \"DimProduct\".\"Color\", [Total Sales] \
+         where (\"DimDate\".\"CalendarYear\" = $year) and (\"DimDate\".\"Date\" >= $since)"
     );
+
+    let error = parse_script(
+        "load_files ( (csv_file_name: \"a.csv\") )
+        queries ( \"a\": A.b, \"b\": A.c )",
+    )
+    .unwrap_err();
+    assert_eq!(error.expected, NAMED_QUERY);
+}
+
+#[test]
+fn statement_parser_test() {
+    let (rest, statement) = statement_parser(Span::new(
+        r#"sql "SELECT \"Color\", 'a\\b' FROM \"DimProduct\"""#,
+    ))
+    .unwrap();
+    assert!(rest.fragment().is_empty());
     assert_eq!(
-        data_type_parser("int32"),
-        Ok(("", DataTypeDescriptor::Int32(true)))
+        statement,
+        StatementData::Sql(r#"SELECT "Color", 'a\b' FROM "DimProduct""#.to_string())
     );
+
+    let (_, statement) = statement_parser(Span::new("sql_names.Color")).unwrap();
     assert_eq!(
-        data_type_parser("int64"),
-        Ok(("", DataTypeDescriptor::Int64(true)))
+        statement,
+        StatementData::Query(QueryData {
+            select: vec![Expression::Column(ColumnReference {
+                table: "sql_names",
+                column: "Color"
+            })],
+            filters: vec![],
+        })
     );
+
+    // only double quotes and backslashes are escaped
+    assert!(!matches!(
+        statement_parser(Span::new(r#"sql "SELECT '\d'""#)),
+        Ok((_, StatementData::Sql(_)))
+    ));
+}
+
+#[test]
+fn schema_entry_parser_test() {
+    let (rest, (name, data_type)) = schema_entry_parser(Span::new("\"asdf\": boolean")).unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(name.fragment(), &"asdf");
+    assert_eq!(data_type, DataTypeDescriptor::Boolean(true));
+}
+
+#[test]
+fn schema_parser_test() {
+    let mut expected_result = HashMap::new();
+    expected_result.insert("asdf", DataTypeDescriptor::String(false));
+
+    let (rest, schema) =
+        schema_parser(Span::new("( \"asdf\": string(is_nullable: false) )")).unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(schema, expected_result);
+}
+
+#[test]
+fn string_parser_test() {
+    let (rest, s) = string_parser(Span::new("\"Hello, world!\"")).unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(s.fragment(), &"Hello, world!");
+}
+
+#[test]
+fn literal_string_parser_test() {
+    let (rest, s) = literal_string_parser(Span::new("'O''Brien' || ''")).unwrap();
+    assert_eq!(rest.fragment(), &" || ''");
+    assert_eq!(s, "O'Brien");
+
+    // a string is written back with its quotes doubled and parses to the same expression
+    let input = "DimCustomer.LastName || '''' = 'O''Brien'''";
+    let (_, expression) = expression_parser(Span::new(input)).unwrap();
+    let written = expression.to_string();
+    assert!(written.contains("'O''Brien'''"), "{}", written);
+    let (rest, reparsed) = expression_parser(Span::new(&written)).unwrap();
+    assert_eq!(rest.fragment(), &"");
+    assert_eq!(reparsed, expression);
+}
+
+#[test]
+fn data_type_parser_test() {
+    // the rest of the input is compared by its fragment, a span also has a position
+    fn parse(input: &str) -> Result<(&str, DataTypeDescriptor<'_>), String> {
+        data_type_parser(Span::new(input))
+            .map(|(rest, data_type)| (*rest.fragment(), data_type))
+            .map_err(|e| e.to_string())
+    }
+
     assert_eq!(
-        data_type_parser("float32"),
-        Ok(("", DataTypeDescriptor::Float32(true)))
+        parse("boolean"),
+        Ok(("", DataTypeDescriptor::Boolean(true)))
     );
+
     assert_eq!(
-        data_type_parser("float64"),
-        Ok(("", DataTypeDescriptor::Float64(true)))
+        parse("boolean(is_nullable: false)"),
+        Ok(("", DataTypeDescriptor::Boolean(false)))
     );
+
+    assert_eq!(parse("uint8"), Ok(("", DataTypeDescriptor::UInt8(true))));
+
+    assert_eq!(parse("uint16"), Ok(("", DataTypeDescriptor::UInt16(true))));
+    assert_eq!(parse("uint32"), Ok(("", DataTypeDescriptor::UInt32(true))));
+    assert_eq!(parse("uint64"), Ok(("", DataTypeDescriptor::UInt64(true))));
+    assert_eq!(parse("int8"), Ok(("", DataTypeDescriptor::Int8(true))));
+    assert_eq!(parse("int16"), Ok(("", DataTypeDescriptor::Int16(true))));
+    assert_eq!(parse("int32"), Ok(("", DataTypeDescriptor::Int32(true))));
+    assert_eq!(parse("int64"), Ok(("", DataTypeDescriptor::Int64(true))));
     assert_eq!(
-        data_type_parser("string"),
-        Ok(("", DataTypeDescriptor::String(true)))
+        parse("float32"),
+        Ok(("", DataTypeDescriptor::Float32(true)))
     );
     assert_eq!(
-        data_type_parser("binary"),
-        Ok(("", DataTypeDescriptor::Binary(true)))
+        parse("float64"),
+        Ok(("", DataTypeDescriptor::Float64(true)))
     );
+    assert_eq!(parse("string"), Ok(("", DataTypeDescriptor::String(true))));
+    assert_eq!(parse("binary"), Ok(("", DataTypeDescriptor::Binary(true))));
     assert_eq!(
-        data_type_parser("date(format: \"%Y\", is_nullable: false)"),
+        parse("date(format: \"%Y\", is_nullable: false)"),
         Ok(("", DataTypeDescriptor::Date(false, "%Y")))
     );
 
     assert_eq!(
-        data_type_parser("datetime(format: \"%Y\" )"),
+        parse("datetime(format: \"%Y\" )"),
         Ok(("", DataTypeDescriptor::Datetime(true, "%Y")))
     );
 
     assert_eq!(
-        data_type_parser("duration(is_nullable: false, time_unit: nanoseconds)"),
+        parse("duration(is_nullable: false, time_unit: nanoseconds)"),
         Ok((
             "",
             DataTypeDescriptor::Duration(false, TimeUnit::Nanosecond)
         ))
     );
     assert_eq!(
-        data_type_parser("duration(time_unit: microseconds)"),
+        parse("duration(time_unit: microseconds)"),
         Ok((
             "",
             DataTypeDescriptor::Duration(true, TimeUnit::Microsecond)
         ))
     );
     assert_eq!(
-        data_type_parser("duration(time_unit: milliseconds)"),
+        parse("duration(time_unit: milliseconds)"),
         Ok((
             "",
             DataTypeDescriptor::Duration(true, TimeUnit::Millisecond)
         ))
     );
     assert_eq!(
-        data_type_parser("time(format: \"%Y\", is_nullable: false)"),
+        parse("time(format: \"%Y\", is_nullable: false)"),
         Ok(("", DataTypeDescriptor::Time(false, "%Y")))
     );
-    assert_eq!(data_type_parser("null"), Ok(("", DataTypeDescriptor::Null)));
+    assert_eq!(parse("null"), Ok(("", DataTypeDescriptor::Null)));
+}
+
+#[test]
+fn parse_error_test() {
+    let error = parse_script("load_files (\n    (file_name: \"a.csv\"),\n)").unwrap_err();
+    assert_eq!((error.line, error.column), (2, 6));
+    assert_eq!(error.expected, FILE_DESCRIPTOR);
+    assert_eq!(
+        error.help.as_deref(),
+        Some(
+            "did you mean `csv_file_name`, `parquet_file_name`, `json_file_name`, \
+             `avro_file_name`, `arrow_file_name` or `file_name_column`?"
+        )
+    );
+
+    let error = parse_script(
+        "load_files (\n    (csv_file_name: \"a.csv\", field_types: (\"x\": integer)),\n)",
+    )
+    .unwrap_err();
+    assert_eq!((error.line, error.column), (2, 49));
+    assert_eq!(error.expected, DATA_TYPE);
+    assert!(error
+        .help
+        .unwrap()
+        .starts_with("a data type is one of int8"));
+
+    let error = parse_script(
+        "load_files ( (csv_file_name: \"a.csv\") )
+        relationships ( A.b -> C.d (cardinality: many_to_on) )",
+    )
+    .unwrap_err();
+    assert_eq!(error.expected, CARDINALITY);
+    assert_eq!(error.help.as_deref(), Some("did you mean `many_to_one`?"));
+
+    let error =
+        parse_script("load_files ( (csv_file_name: \"a.csv\") )\nrelationship ( A.b -> C.d )")
+            .unwrap_err();
+    assert_eq!((error.line, error.column), (2, 1));
+    assert_eq!(error.expected, SECTION);
+    assert_eq!(error.help.as_deref(), Some("did you mean `relationships`?"));
+
+    assert!(parse_script("load_files ( (csv_file_name: \"a.csv\") )").is_ok());
 }
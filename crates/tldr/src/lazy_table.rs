@@ -0,0 +1,201 @@
+use crate::{context::convert_columns, grammar::ast::DataTypeDescriptor};
+
+use arrow::datatypes::SchemaRef;
+use async_trait::async_trait;
+use datafusion::{
+    datasource::TableProvider,
+    error::DataFusionError,
+    execution::{context::SessionState, TaskContext},
+    logical_expr::{Expr, TableType},
+    physical_expr::PhysicalSortExpr,
+    physical_plan::{
+        stream::RecordBatchStreamAdapter, DisplayAs, DisplayFormatType, ExecutionPlan,
+        Partitioning, SendableRecordBatchStream,
+    },
+};
+use futures::StreamExt;
+use std::{any::Any, collections::HashMap, fmt, sync::Arc};
+
+#[derive(Debug, Clone, Copy)]
+enum TemporalKind {
+    Date,
+    Time,
+    Datetime,
+}
+
+/// a Date, Time or Datetime column that is read as a string and parsed while it is scanned
+#[derive(Debug, Clone)]
+struct TemporalField {
+    name: String,
+    kind: TemporalKind,
+    nullable: bool,
+    format: String,
+}
+
+impl TemporalField {
+    fn descriptor(&self) -> DataTypeDescriptor<'_> {
+        match self.kind {
+            TemporalKind::Date => DataTypeDescriptor::Date(self.nullable, &self.format),
+            TemporalKind::Time => DataTypeDescriptor::Time(self.nullable, &self.format),
+            TemporalKind::Datetime => DataTypeDescriptor::Datetime(self.nullable, &self.format),
+        }
+    }
+}
+
+/// a table that is read from its file whenever a query scans it.
+///
+/// `source` reads the temporal columns of `field_types` as strings, they are parsed batch by
+/// batch into the types of `schema`. A value that cannot be parsed is reported with the file
+/// but without its line, `source` may scan the file in several partitions.
+pub(crate) struct LazyTable {
+    file_path: String,
+    source: Arc<dyn TableProvider>,
+    schema: SchemaRef,
+    temporal_fields: Arc<Vec<TemporalField>>,
+}
+
+impl LazyTable {
+    pub(crate) fn new(
+        file_path: &str,
+        source: Arc<dyn TableProvider>,
+        schema: SchemaRef,
+        field_types: &HashMap<&str, DataTypeDescriptor>,
+    ) -> LazyTable {
+        let temporal_fields = field_types
+            .iter()
+            .filter_map(|(name, descriptor)| {
+                let (kind, nullable, format) = match descriptor {
+                    DataTypeDescriptor::Date(n, f) => (TemporalKind::Date, *n, *f),
+                    DataTypeDescriptor::Time(n, f) => (TemporalKind::Time, *n, *f),
+                    DataTypeDescriptor::Datetime(n, f) => (TemporalKind::Datetime, *n, *f),
+                    _ => return None,
+                };
+                Some(TemporalField {
+                    name: name.to_string(),
+                    kind,
+                    nullable,
+                    format: format.to_string(),
+                })
+            })
+            .collect();
+
+        LazyTable {
+            file_path: file_path.to_string(),
+            source,
+            schema,
+            temporal_fields: Arc::new(temporal_fields),
+        }
+    }
+}
+
+#[async_trait]
+impl TableProvider for LazyTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    async fn scan(
+        &self,
+        state: &SessionState,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        // filters are not pushed down, the temporal columns are still strings in the source
+        let input = self.source.scan(state, projection, &[], limit).await?;
+        let schema = match projection {
+            Some(projection) => Arc::new(self.schema.project(projection)?),
+            None => self.schema.clone(),
+        };
+
+        Ok(Arc::new(ParseTemporalExec {
+            input,
+            schema,
+            file_path: self.file_path.clone(),
+            temporal_fields: self.temporal_fields.clone(),
+        }))
+    }
+}
+
+/// parses the temporal columns of the batches of `input`.
+///
+/// A partition does not know where in the file its batches start, so a value that cannot be
+/// parsed is reported without its line.
+#[derive(Debug, Clone)]
+struct ParseTemporalExec {
+    input: Arc<dyn ExecutionPlan>,
+    schema: SchemaRef,
+    file_path: String,
+    temporal_fields: Arc<Vec<TemporalField>>,
+}
+
+impl DisplayAs for ParseTemporalExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ParseTemporalExec: file={}", self.file_path)
+    }
+}
+
+impl ExecutionPlan for ParseTemporalExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        self.input.output_ordering()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        Ok(Arc::new(ParseTemporalExec {
+            input: children[0].clone(),
+            ..ParseTemporalExec::clone(&self)
+        }))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream, DataFusionError> {
+        let input = self.input.execute(partition, context)?;
+        let schema = self.schema.clone();
+        let file_path = self.file_path.clone();
+        let temporal_fields = self.temporal_fields.clone();
+
+        let batches = input.map(move |batch| {
+            let field_types = temporal_fields
+                .iter()
+                .map(|f| (f.name.as_str(), f.descriptor()))
+                .collect::<HashMap<_, _>>();
+            convert_columns(&file_path, &field_types, schema.clone(), batch?, None)
+                .map_err(|e| DataFusionError::External(Box::new(e)))
+        });
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema.clone(),
+            batches,
+        )))
+    }
+}
@@ -1,7 +1,12 @@
+pub mod cli;
 pub mod context;
 pub mod cxxqt_object;
 pub mod error;
 pub mod grammar;
+mod lazy_table;
+pub mod query;
+pub mod repl;
+pub mod snapshot;
 
 use cxx_qt_lib::{QGuiApplication, QQmlApplicationEngine, QUrl};
 pub fn main2() {
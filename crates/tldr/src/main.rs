@@ -1,5 +1,15 @@
-use tldr::main2;
+use clap::Parser;
+use tldr::{
+    cli::{self, load_options, Cli, Command},
+    main2,
+    repl::Repl,
+};
 
-fn main() {
-    main2();
+fn main() -> miette::Result<()> {
+    match Cli::parse().command {
+        Some(Command::Run(args)) => cli::run(&args)?,
+        Some(Command::Repl(args)) => Repl::new(&args.script, load_options(args.jobs))?.run()?,
+        None => main2(),
+    }
+    Ok(())
 }
@@ -0,0 +1,1839 @@
+use crate::{
+    context::{
+        data_type_of, nanoseconds_since_midnight, register_schema, Context, Measure, NamedQuery,
+        QueryParameter, TableReferences, Tables,
+    },
+    error::TldrError,
+    grammar::{
+        ast::{
+            AggregateFunction, BinaryOperator, CalculatedColumnData, Cardinality, ColumnReference,
+            DataTypeDescriptor, Expression, FilterDirection, LiteralValue, MeasureData,
+            NamedQueryData, Span, StatementData,
+        },
+        parser::{query_parser, statement_parser},
+    },
+};
+
+use arrow::{
+    datatypes::{DataType, Date32Type, Schema, TimeUnit},
+    record_batch::RecordBatch,
+};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use datafusion::{
+    common::{
+        tree_node::{Transformed, TreeNode},
+        Column, DFSchema, ScalarValue,
+    },
+    dataframe::DataFrame,
+    datasource::{provider_as_source, view::ViewTable, TableProvider},
+    error::DataFusionError,
+    execution::context::{SQLOptions, SessionContext},
+    logical_expr::{
+        avg, binary_expr, count, expr::Placeholder, lit, max, min, sum, Expr, ExprSchemable,
+        JoinType, LogicalPlan, LogicalPlanBuilder, Operator,
+    },
+    sql::TableReference,
+};
+use std::{collections::HashMap, fmt, iter, panic, sync::Arc, thread};
+use tokio::runtime::Builder;
+
+/// the schema of the views of the measures, see `Context::sql`
+pub const MEASURES_SCHEMA: &str = "measures";
+
+/// a relationship that is followed from `from_table` to `to_table` when joining tables
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct JoinStep<'a> {
+    from_table: &'a str,
+    from_column: &'a str,
+    to_table: &'a str,
+    to_column: &'a str,
+}
+
+impl<'a> JoinStep<'a> {
+    fn reversed(&self) -> JoinStep<'a> {
+        JoinStep {
+            from_table: self.to_table,
+            from_column: self.to_column,
+            to_table: self.from_table,
+            to_column: self.from_column,
+        }
+    }
+}
+
+impl<'a> fmt::Display for JoinStep<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{} -> {}.{}",
+            self.from_table, self.from_column, self.to_table, self.to_column
+        )
+    }
+}
+
+/// the tables of a query are joined to `root` along `steps`, in that order
+struct JoinTree<'a> {
+    root: &'a str,
+    steps: Vec<JoinStep<'a>>,
+}
+
+impl<'a> JoinTree<'a> {
+    /// the root and all tables joined to it
+    fn tables(&self) -> Vec<&'a str> {
+        iter::once(self.root)
+            .chain(self.steps.iter().map(|s| s.to_table))
+            .collect()
+    }
+}
+
+impl<'a> fmt::Display for JoinTree<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let steps = self
+            .steps
+            .iter()
+            .map(JoinStep::to_string)
+            .collect::<Vec<_>>();
+        write!(f, "{}: {}", self.root, steps.join(", "))
+    }
+}
+
+impl Context {
+    /// plans a query given as a select list with optional filters, e.g.
+    /// `DimProduct.Color, sum(FactSales.SalesAmount) where DimDate.CalendarYear = 2009`.
+    ///
+    /// The tables of all referenced columns are joined along the declared relationships.
+    /// Columns outside of aggregate functions are grouped by.
+    pub fn plan_query(&self, query: &str) -> Result<LogicalPlan, TldrError> {
+        let query = match query_parser(Span::new(query)) {
+            Ok((rest, query)) if rest.fragment().trim().is_empty() => query,
+            _ => {
+                return Err(TldrError::TldrCouldNotParseQuery {
+                    query: query.to_string(),
+                })
+            }
+        };
+
+        self.plan_expressions(&query.select, &query.filters)
+    }
+
+    /// plans and runs a statement, either a query as in `plan_query` or raw sql as in `sql`,
+    /// e.g. `sql "SELECT count(*) FROM \"FactSales\""`.
+    ///
+    /// A query without rows returns a single empty batch, so that the schema of its result is
+    /// known.
+    pub async fn execute_query(&self, query: &str) -> Result<Vec<RecordBatch>, TldrError> {
+        let statement = match statement_parser(Span::new(query)) {
+            Ok((rest, statement)) if rest.fragment().trim().is_empty() => statement,
+            _ => {
+                return Err(TldrError::TldrCouldNotParseQuery {
+                    query: query.to_string(),
+                })
+            }
+        };
+
+        match statement {
+            StatementData::Sql(sql) => self.execute_sql(&sql).await,
+            StatementData::Query(q) => {
+                let plan = self.plan_expressions(&q.select, &q.filters)?;
+                self.execute_plan(query, plan).await
+            }
+        }
+    }
+
+    /// runs raw DataFusion sql against the tables registered by `load_base_tables` and returns
+    /// its result.
+    ///
+    /// Calculated columns are columns of their tables. Every measure is a view of its total in
+    /// the schema `measures`, e.g. `SELECT * FROM measures."Total Sales"`. Unquoted names are
+    /// lower case in sql, so most names of a model have to be quoted. The sql may only read
+    /// the model, DDL, DML and statements like SET are rejected.
+    ///
+    /// The sql runs on a runtime of its own, so the caller needs neither a runtime nor to be
+    /// async. Async code can use `execute_sql` instead.
+    pub fn sql(&self, sql: &str) -> Result<Vec<RecordBatch>, TldrError> {
+        // a runtime cannot be started on a thread that already drives one
+        thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let runtime = Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .map_err(|e| TldrError::TldrCouldNotStartRuntime { source: e })?;
+                    runtime.block_on(self.execute_sql(sql))
+                })
+                .join()
+                .unwrap_or_else(|e| panic::resume_unwind(e))
+        })
+    }
+
+    /// runs raw sql on the runtime of the caller, see `sql`
+    pub async fn execute_sql(&self, sql: &str) -> Result<Vec<RecordBatch>, TldrError> {
+        let options = SQLOptions::new()
+            .with_allow_ddl(false)
+            .with_allow_dml(false)
+            .with_allow_statements(false);
+        let df = match self.ctx.sql_with_options(sql, options).await {
+            Ok(df) => df,
+            Err(e) => return Err(self.sql_error(sql, e)),
+        };
+        collect(sql, df).await
+    }
+
+    /// the error of sql that failed to plan, a missing view of a measure is reported with the
+    /// reason it is missing
+    fn sql_error(&self, sql: &str, e: DataFusionError) -> TldrError {
+        // the tables the sql refers to, sql that cannot be parsed refers to none
+        let state = self.ctx.state();
+        let dialect = state.config_options().sql_parser.dialect.clone();
+        let references = state
+            .sql_to_statement(sql, &dialect)
+            .and_then(|statement| state.resolve_table_references(&statement))
+            .unwrap_or_default();
+        let missing = references.iter().find_map(|r| match r.schema() {
+            Some(MEASURES_SCHEMA) => self.missing_measure_views.get_key_value(r.table()),
+            _ => None,
+        });
+
+        match missing {
+            Some((measure, reason)) => TldrError::TldrMissingMeasureView {
+                measure: measure.clone(),
+                reason: reason.clone(),
+                source: e,
+            },
+            None => run_error(sql)(e),
+        }
+    }
+
+    /// runs the named query `name` with `values` for its parameters, see `plan_named_query`
+    pub async fn run_query(
+        &self,
+        name: &str,
+        values: &[(&str, &str)],
+    ) -> Result<Vec<RecordBatch>, TldrError> {
+        let plan = self.plan_named_query(name, values)?;
+        self.execute_plan(name, plan).await
+    }
+
+    /// plans the named query `name` with `values` for its parameters.
+    ///
+    /// Every parameter needs a value. A value is parsed as the type of its parameter and bound
+    /// into the plan as a literal, it never becomes part of the text of a query.
+    pub fn plan_named_query(
+        &self,
+        name: &str,
+        values: &[(&str, &str)],
+    ) -> Result<LogicalPlan, TldrError> {
+        let query = self
+            .queries
+            .iter()
+            .find(|q| q.name == name)
+            .ok_or_else(|| TldrError::TldrUnknownQuery {
+                query: name.to_string(),
+            })?;
+
+        if let Some((parameter, _)) = values
+            .iter()
+            .find(|(n, _)| query.parameters.iter().all(|p| p.name != *n))
+        {
+            return Err(TldrError::TldrUnknownParameter {
+                query: name.to_string(),
+                parameter: parameter.to_string(),
+            });
+        }
+
+        let mut bound = HashMap::new();
+        for parameter in &query.parameters {
+            let value = values
+                .iter()
+                .find(|(n, _)| *n == parameter.name)
+                .map(|(_, v)| *v)
+                .ok_or_else(|| TldrError::TldrMissingParameter {
+                    query: name.to_string(),
+                    parameter: parameter.name.clone(),
+                })?;
+            let scalar = parameter_value(parameter, value).ok_or_else(|| {
+                TldrError::TldrInvalidParameterValue {
+                    query: name.to_string(),
+                    parameter: parameter.name.clone(),
+                    value: value.to_string(),
+                    data_type: parameter.data_type.clone(),
+                }
+            })?;
+            bound.insert(parameter.name.as_str(), scalar);
+        }
+
+        self.plan_bound(query, &bound)
+    }
+
+    /// compiles the named queries of a model.
+    ///
+    /// Every query is planned once with nulls for its parameters, so that a query that cannot
+    /// be planned is found when the model is loaded.
+    pub(crate) fn compile_queries(
+        &self,
+        definitions: &[NamedQueryData],
+    ) -> Result<Vec<NamedQuery>, TldrError> {
+        let mut queries: Vec<NamedQuery> = Vec::new();
+
+        for definition in definitions {
+            let invalid = |reason: String| TldrError::TldrInvalidQuery {
+                query: definition.name.to_string(),
+                reason,
+            };
+            if queries.iter().any(|q| q.name == definition.name) {
+                return Err(invalid("the query is defined more than once".to_string()));
+            }
+
+            let mut parameters: Vec<QueryParameter> = Vec::new();
+            for p in &definition.parameters {
+                if parameters.iter().any(|q| q.name == p.name) {
+                    return Err(invalid(format!(
+                        "the parameter ${} is declared more than once",
+                        p.name
+                    )));
+                }
+                let format = match &p.data_type {
+                    DataTypeDescriptor::Date(_, f)
+                    | DataTypeDescriptor::Time(_, f)
+                    | DataTypeDescriptor::Datetime(_, f)
+                        if !f.is_empty() =>
+                    {
+                        Some(f.to_string())
+                    }
+                    _ => None,
+                };
+                parameters.push(QueryParameter {
+                    name: p.name.to_string(),
+                    data_type: data_type_of(&p.data_type),
+                    format,
+                });
+            }
+
+            let parameter = |name: &str| {
+                parameters
+                    .iter()
+                    .find(|p| p.name == name)
+                    .map(|p| {
+                        Expr::Placeholder(Placeholder::new(
+                            format!("${}", p.name),
+                            Some(p.data_type.clone()),
+                        ))
+                    })
+                    .ok_or_else(|| invalid(format!("the parameter ${} is not declared", name)))
+            };
+            let (exprs, filters) = self.query_exprs(
+                &definition.query.select,
+                &definition.query.filters,
+                &parameter,
+            )?;
+            let query = NamedQuery {
+                name: definition.name.to_string(),
+                parameters,
+                exprs,
+                filters,
+                definition: definition.to_string(),
+            };
+
+            let mut nulls = HashMap::new();
+            for p in &query.parameters {
+                let null =
+                    ScalarValue::try_from(&p.data_type).map_err(|e| invalid(e.to_string()))?;
+                nulls.insert(p.name.as_str(), null);
+            }
+            self.plan_bound(&query, &nulls)?;
+            queries.push(query);
+        }
+
+        Ok(queries)
+    }
+
+    /// plans a named query with its parameters replaced by the literals of `values`
+    fn plan_bound(
+        &self,
+        query: &NamedQuery,
+        values: &HashMap<&str, ScalarValue>,
+    ) -> Result<LogicalPlan, TldrError> {
+        let bind = |expr: &Expr| {
+            expr.clone()
+                .transform(&|e| {
+                    Ok(match e {
+                        Expr::Placeholder(p) => match values.get(&p.id[1..]) {
+                            Some(value) => Transformed::Yes(Expr::Literal(value.clone())),
+                            None => Transformed::No(Expr::Placeholder(p)),
+                        },
+                        e => Transformed::No(e),
+                    })
+                })
+                .map_err(plan_error)
+        };
+        let exprs = query
+            .exprs
+            .iter()
+            .map(bind)
+            .collect::<Result<Vec<_>, _>>()?;
+        let filters = query
+            .filters
+            .iter()
+            .map(bind)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.plan_exprs(exprs, filters)
+    }
+
+    /// runs a planned query, `query` names it in errors
+    async fn execute_plan(
+        &self,
+        query: &str,
+        plan: LogicalPlan,
+    ) -> Result<Vec<RecordBatch>, TldrError> {
+        let df = self
+            .ctx
+            .execute_logical_plan(plan)
+            .await
+            .map_err(run_error(query))?;
+        collect(query, df).await
+    }
+
+    /// plans a query given as lists of already parsed expressions and filters, see `plan_query`
+    pub fn plan_expressions(
+        &self,
+        expressions: &[Expression],
+        filters: &[Expression],
+    ) -> Result<LogicalPlan, TldrError> {
+        let (exprs, filters) = self.query_exprs(expressions, filters, &unexpected_parameter)?;
+        self.plan_exprs(exprs, filters)
+    }
+
+    /// the datafusion expressions and filters of a query, parameters are resolved by
+    /// `parameter`
+    fn query_exprs(
+        &self,
+        expressions: &[Expression],
+        filters: &[Expression],
+        parameter: &dyn Fn(&str) -> Result<Expr, TldrError>,
+    ) -> Result<(Vec<Expr>, Vec<Expr>), TldrError> {
+        let exprs = expressions
+            .iter()
+            .map(|e| {
+                let expr = to_expr(e, &mut |name| self.measure(name), parameter)?;
+                // measures are shown under their name
+                Ok(match e {
+                    Expression::Measure(name) => expr.alias(*name),
+                    _ => expr,
+                })
+            })
+            .collect::<Result<Vec<_>, TldrError>>()?;
+        let filters = filters
+            .iter()
+            .map(|f| to_expr(f, &mut |name| self.measure(name), parameter))
+            .collect::<Result<Vec<_>, TldrError>>()?;
+
+        Ok((exprs, filters))
+    }
+
+    /// plans a query given as lists of datafusion expressions and filters over the registered
+    /// tables
+    pub(crate) fn plan_exprs(
+        &self,
+        exprs: Vec<Expr>,
+        filters: Vec<Expr>,
+    ) -> Result<LogicalPlan, TldrError> {
+        let mut columns = Vec::new();
+        for e in &exprs {
+            collect_columns(e, &mut columns);
+        }
+
+        let mut tables = Vec::new();
+        for c in &columns {
+            let table = check_column(&self.tables, c)?;
+            if !tables.contains(&table) {
+                tables.push(table);
+            }
+        }
+
+        let tree = self.resolve_join_tree(&tables)?;
+        let builder = self.join_plan(&tree)?;
+        let builder = self.filter_plan(builder, &tree, filters)?;
+
+        let mut aggregates = Vec::new();
+        let projection = exprs
+            .iter()
+            .map(|e| {
+                if contains_aggregate(e) {
+                    rebase(e, &mut aggregates)
+                } else {
+                    grouped(e)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let builder = if aggregates.is_empty() {
+            builder.project(exprs)
+        } else {
+            let group_exprs = exprs
+                .iter()
+                .filter(|e| !contains_aggregate(e))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            builder
+                .aggregate(group_exprs, aggregates)
+                .and_then(|b| b.project(projection))
+        };
+
+        builder.and_then(|b| b.build()).map_err(plan_error)
+    }
+
+    /// registers a view of the total of every measure in the schema `measures`, see `sql`.
+    ///
+    /// A measure over tables that cannot be joined has no view, the reason is kept in
+    /// `missing_measure_views` and reported by `execute_sql`. Neither has a measure whose name
+    /// is taken by a loaded table of that schema, the table is queried instead.
+    pub(crate) fn register_measure_views(&mut self) -> Result<(), TldrError> {
+        if self.measures.is_empty() {
+            return Ok(());
+        }
+        // the schema exists even without views, so that a missing view is an unknown table
+        register_schema(&self.ctx, MEASURES_SCHEMA).map_err(|e| {
+            TldrError::TldrCouldNotRegisterTable {
+                table: MEASURES_SCHEMA.to_string(),
+                source: e,
+            }
+        })?;
+
+        for measure in &self.measures {
+            let table = format!("{}.{}", MEASURES_SCHEMA, measure.name);
+            if self.tables.contains_key(&table) {
+                continue;
+            }
+            let exprs = vec![measure.expr.clone().alias(&measure.name)];
+            let plan = match self.plan_exprs(exprs, Vec::new()) {
+                Ok(plan) => plan,
+                Err(e) => {
+                    self.missing_measure_views
+                        .insert(measure.name.clone(), e.to_string());
+                    continue;
+                }
+            };
+
+            let reference = TableReference::partial(MEASURES_SCHEMA, measure.name.as_str());
+            ViewTable::try_new(plan, None)
+                .and_then(|view| self.ctx.register_table(reference, Arc::new(view)))
+                .map_err(|e| TldrError::TldrCouldNotRegisterTable { table, source: e })?;
+        }
+
+        Ok(())
+    }
+
+    /// the compiled expression of the measure `name`
+    pub fn measure(&self, name: &str) -> Result<Expr, TldrError> {
+        self.measures
+            .iter()
+            .find(|m| m.name == name)
+            .map(|m| m.expr.clone())
+            .ok_or_else(|| TldrError::TldrUnknownMeasure {
+                measure: name.to_string(),
+            })
+    }
+
+    /// the relationships as steps that can be followed when joining.
+    ///
+    /// Many-to-one relationships are followed from the many side to the one side only,
+    /// all others in both directions.
+    fn join_steps(&self) -> Vec<JoinStep> {
+        let mut steps = Vec::new();
+        for r in &self.relationships {
+            let step = JoinStep {
+                from_table: &r.from_table,
+                from_column: &r.from_column,
+                to_table: &r.to_table,
+                to_column: &r.to_column,
+            };
+            steps.push(step);
+            if r.cardinality != Cardinality::ManyToOne {
+                steps.push(step.reversed());
+            }
+        }
+        steps
+    }
+
+    /// the relationships as steps along which filters flow.
+    ///
+    /// Filters flow from the referenced table to the referencing table. They flow the other
+    /// way too for one-to-one relationships and relationships with filter direction both.
+    fn filter_steps(&self) -> Vec<JoinStep> {
+        let mut steps = Vec::new();
+        for r in &self.relationships {
+            let step = JoinStep {
+                from_table: &r.to_table,
+                from_column: &r.to_column,
+                to_table: &r.from_table,
+                to_column: &r.from_column,
+            };
+            steps.push(step);
+            if r.filter_direction == FilterDirection::Both || r.cardinality == Cardinality::OneToOne
+            {
+                steps.push(step.reversed());
+            }
+        }
+        steps
+    }
+
+    /// finds the table all `tables` can be joined to, and the way to join them.
+    ///
+    /// The tables of the query are tried first, in the order of the query. If none of them
+    /// works, e.g. because only dimensions are queried, all other tables are tried and
+    /// exactly one of them must work.
+    fn resolve_join_tree(&self, tables: &[&str]) -> Result<JoinTree, TldrError> {
+        let steps = self.join_steps();
+
+        let queried = tables
+            .iter()
+            .filter_map(|t| self.tables.get_key_value(*t))
+            .map(|(k, _)| k.as_str())
+            .collect::<Vec<_>>();
+        let mut others = self
+            .tables
+            .keys()
+            .map(String::as_str)
+            .filter(|t| !tables.contains(t))
+            .collect::<Vec<_>>();
+        others.sort();
+
+        for (candidates, first_wins) in [(queried, true), (others, false)] {
+            let mut resolved = Vec::new();
+            let mut ambiguous = Vec::new();
+            for root in candidates {
+                match join_tree(&steps, root, tables) {
+                    Ok(Some(tree)) => resolved.push(tree),
+                    Ok(None) => {}
+                    Err(paths) => ambiguous.extend(paths),
+                }
+            }
+
+            if resolved.len() == 1 || (first_wins && !resolved.is_empty()) {
+                return Ok(resolved.swap_remove(0));
+            }
+            if resolved.len() > 1 {
+                return Err(TldrError::TldrAmbiguousJoinPath {
+                    paths: resolved.iter().map(JoinTree::to_string).collect(),
+                });
+            }
+            if !ambiguous.is_empty() {
+                return Err(TldrError::TldrAmbiguousJoinPath { paths: ambiguous });
+            }
+        }
+
+        Err(TldrError::TldrNoJoinPath {
+            tables: tables.iter().map(|t| t.to_string()).collect(),
+        })
+    }
+
+    fn join_plan(&self, tree: &JoinTree) -> Result<LogicalPlanBuilder, TldrError> {
+        let mut builder = self.scan(tree.root)?;
+        for step in &tree.steps {
+            let right = self.scan(step.to_table)?.build().map_err(plan_error)?;
+            builder = builder
+                .join(
+                    right,
+                    JoinType::Left,
+                    (
+                        vec![column(step.from_table, step.from_column)],
+                        vec![column(step.to_table, step.to_column)],
+                    ),
+                    None,
+                )
+                .map_err(plan_error)?;
+        }
+        Ok(builder)
+    }
+
+    /// restricts the rows of the tables joined in `tree` by `filters`.
+    ///
+    /// Filters on joined tables are applied as they are. Filters on any other table flow along
+    /// the relationships to the first joined table they reach, which keeps the rows that are
+    /// related to a row passing the filter. A filter that reaches no joined table because of the
+    /// filter direction of its relationships has no effect, a filter on a table that has no
+    /// relationships to the joined tables is an error.
+    fn filter_plan(
+        &self,
+        mut builder: LogicalPlanBuilder,
+        tree: &JoinTree,
+        filters: Vec<Expr>,
+    ) -> Result<LogicalPlanBuilder, TldrError> {
+        let joined = tree.tables();
+
+        // the combined filters of every table that is not joined
+        let mut flowing: Vec<(String, Expr)> = Vec::new();
+        for filter in filters {
+            if contains_aggregate(&filter) {
+                return Err(TldrError::TldrInvalidFilter {
+                    filter: filter.to_string(),
+                });
+            }
+
+            let mut columns = Vec::new();
+            collect_columns(&filter, &mut columns);
+            let mut tables = Vec::new();
+            for c in &columns {
+                let table = check_column(&self.tables, c)?.to_string();
+                if !tables.contains(&table) {
+                    tables.push(table);
+                }
+            }
+
+            if tables.iter().all(|t| joined.contains(&t.as_str())) {
+                builder = builder.filter(filter).map_err(plan_error)?;
+            } else if tables.len() == 1 {
+                let table = tables.swap_remove(0);
+                match flowing.iter_mut().find(|(t, _)| *t == table) {
+                    Some((_, predicate)) => *predicate = predicate.clone().and(filter),
+                    None => flowing.push((table, filter)),
+                }
+            } else {
+                return Err(TldrError::TldrInvalidFilter {
+                    filter: filter.to_string(),
+                });
+            }
+        }
+
+        let steps = self.filter_steps();
+        for (table, predicate) in flowing {
+            let mut paths = Vec::new();
+            for target in &joined {
+                follow(&steps, &table, target, &mut Vec::new(), &mut paths);
+            }
+            // a filter does not flow through joined tables
+            paths.retain(|p| {
+                p[..p.len() - 1]
+                    .iter()
+                    .all(|s| !joined.contains(&s.to_table))
+            });
+
+            let path = match paths.len() {
+                0 if !self.is_related(&table, &joined) => {
+                    return Err(TldrError::TldrUnrelatedFilter {
+                        filter: predicate.to_string(),
+                        table,
+                    })
+                }
+                0 => continue,
+                1 => paths.swap_remove(0),
+                _ => {
+                    return Err(TldrError::TldrAmbiguousJoinPath {
+                        paths: paths.iter().map(|p| path_to_string(p)).collect(),
+                    })
+                }
+            };
+
+            let mut filtered = self.scan(&table)?.filter(predicate).map_err(plan_error)?;
+            if let Some((last, via)) = path.split_last() {
+                for step in via {
+                    filtered = semi_join(self.scan(step.to_table)?, filtered, step)?;
+                }
+                builder = semi_join(builder, filtered, last)?;
+            }
+        }
+
+        Ok(builder)
+    }
+
+    /// whether `table` is connected to one of `tables` by relationships, in any direction
+    fn is_related(&self, table: &str, tables: &[&str]) -> bool {
+        let mut reached = vec![table];
+        let mut i = 0;
+        while let Some(&at) = reached.get(i) {
+            if tables.contains(&at) {
+                return true;
+            }
+            for r in &self.relationships {
+                for (from, to) in [(&r.from_table, &r.to_table), (&r.to_table, &r.from_table)] {
+                    if from == at && !reached.contains(&to.as_str()) {
+                        reached.push(to);
+                    }
+                }
+            }
+            i += 1;
+        }
+        false
+    }
+
+    fn scan(&self, table: &str) -> Result<LogicalPlanBuilder, TldrError> {
+        let provider = self
+            .tables
+            .get(table)
+            .ok_or_else(|| TldrError::TldrUnknownTable {
+                table: table.to_string(),
+            })?;
+        LogicalPlanBuilder::scan(
+            TableReference::bare(table.to_string()),
+            provider_as_source(provider.clone()),
+            None,
+        )
+        .map_err(plan_error)
+    }
+}
+
+/// joins every table of `tables` to `root`.
+///
+/// Returns None if a table cannot be reached from `root`, and the candidate paths if a
+/// table can be reached in more than one way.
+fn join_tree<'a>(
+    steps: &[JoinStep<'a>],
+    root: &'a str,
+    tables: &[&str],
+) -> Result<Option<JoinTree<'a>>, Vec<String>> {
+    let mut tree = JoinTree {
+        root,
+        steps: Vec::new(),
+    };
+
+    for table in tables.iter().filter(|t| **t != root) {
+        let mut paths = Vec::new();
+        follow(steps, root, table, &mut Vec::new(), &mut paths);
+
+        match paths.len() {
+            0 => return Ok(None),
+            1 => {
+                for step in paths.swap_remove(0) {
+                    if !tree.steps.contains(&step) {
+                        tree.steps.push(step);
+                    }
+                }
+            }
+            _ => return Err(paths.iter().map(|p| path_to_string(p)).collect()),
+        }
+    }
+
+    Ok(Some(tree))
+}
+
+/// collects every path along `steps` from `at` to `to` that visits no table twice
+fn follow<'a>(
+    steps: &[JoinStep<'a>],
+    at: &str,
+    to: &str,
+    path: &mut Vec<JoinStep<'a>>,
+    paths: &mut Vec<Vec<JoinStep<'a>>>,
+) {
+    if at == to {
+        paths.push(path.clone());
+        return;
+    }
+
+    for step in steps.iter().filter(|s| s.from_table == at) {
+        if step.to_table == at || path.iter().any(|p| p.from_table == step.to_table) {
+            continue;
+        }
+        path.push(*step);
+        follow(steps, step.to_table, to, path, paths);
+        path.pop();
+    }
+}
+
+fn path_to_string(path: &[JoinStep]) -> String {
+    path.iter()
+        .map(JoinStep::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// keeps the rows of `left` whose `step.to_column` matches `step.from_column` of a row in `right`
+fn semi_join(
+    left: LogicalPlanBuilder,
+    right: LogicalPlanBuilder,
+    step: &JoinStep,
+) -> Result<LogicalPlanBuilder, TldrError> {
+    left.join(
+        right.build().map_err(plan_error)?,
+        JoinType::LeftSemi,
+        (
+            vec![column(step.to_table, step.to_column)],
+            vec![column(step.from_table, step.from_column)],
+        ),
+        None,
+    )
+    .map_err(plan_error)
+}
+
+fn column(table: &str, column: &str) -> Column {
+    Column::new(Some(TableReference::bare(table.to_string())), column)
+}
+
+/// the datafusion expression of `expression`, measures are resolved by `measure` and
+/// parameters by `parameter`
+fn to_expr(
+    expression: &Expression,
+    measure: &mut dyn FnMut(&str) -> Result<Expr, TldrError>,
+    parameter: &dyn Fn(&str) -> Result<Expr, TldrError>,
+) -> Result<Expr, TldrError> {
+    Ok(match expression {
+        Expression::Column(c) => Expr::Column(column(c.table, c.column)),
+        Expression::UnqualifiedColumn(c) => {
+            return Err(TldrError::TldrUnqualifiedColumn {
+                column: c.to_string(),
+            })
+        }
+        Expression::Literal(LiteralValue::Int64(i)) => lit(*i),
+        Expression::Literal(LiteralValue::Float64(f)) => lit(*f),
+        Expression::Literal(LiteralValue::Utf8(s)) => lit(s.to_string()),
+        Expression::Measure(name) => measure(name)?,
+        Expression::Parameter(name) => parameter(name)?,
+        Expression::Aggregate(f, e) => {
+            let e = to_expr(e, measure, parameter)?;
+            match f {
+                AggregateFunction::Sum => sum(e),
+                AggregateFunction::Min => min(e),
+                AggregateFunction::Max => max(e),
+                AggregateFunction::Avg => avg(e),
+                AggregateFunction::Count => count(e),
+            }
+        }
+        Expression::Binary(left, op, right) => {
+            let op = match op {
+                BinaryOperator::Plus => Operator::Plus,
+                BinaryOperator::Minus => Operator::Minus,
+                BinaryOperator::Multiply => Operator::Multiply,
+                BinaryOperator::Divide => Operator::Divide,
+                BinaryOperator::Concat => Operator::StringConcat,
+                BinaryOperator::Eq => Operator::Eq,
+                BinaryOperator::NotEq => Operator::NotEq,
+                BinaryOperator::Lt => Operator::Lt,
+                BinaryOperator::LtEq => Operator::LtEq,
+                BinaryOperator::Gt => Operator::Gt,
+                BinaryOperator::GtEq => Operator::GtEq,
+            };
+            binary_expr(
+                to_expr(left, measure, parameter)?,
+                op,
+                to_expr(right, measure, parameter)?,
+            )
+        }
+    })
+}
+
+/// parameters can only be used in named queries
+fn unexpected_parameter(name: &str) -> Result<Expr, TldrError> {
+    Err(TldrError::TldrUnexpectedParameter {
+        parameter: name.to_string(),
+    })
+}
+
+/// the value of a parameter of a named query given as a string, None if it is not valid
+fn parameter_value(parameter: &QueryParameter, value: &str) -> Option<ScalarValue> {
+    let value = match (&parameter.data_type, &parameter.format) {
+        (DataType::Date32, Some(format)) => {
+            let date = NaiveDate::parse_from_str(value, format).ok()?;
+            ScalarValue::Date32(Some(Date32Type::from_naive_date(date)))
+        }
+        (DataType::Time64(TimeUnit::Nanosecond), Some(format)) => {
+            let time = NaiveTime::parse_from_str(value, format).ok()?;
+            ScalarValue::Time64Nanosecond(Some(nanoseconds_since_midnight(time)))
+        }
+        (DataType::Timestamp(TimeUnit::Nanosecond, None), Some(format)) => {
+            let datetime = NaiveDateTime::parse_from_str(value, format).ok()?;
+            ScalarValue::TimestampNanosecond(Some(datetime.and_utc().timestamp_nanos_opt()?), None)
+        }
+        (data_type, _) => ScalarValue::try_from_string(value.to_string(), data_type).ok()?,
+    };
+
+    // a value that cannot be cast may become null
+    (!value.is_null()).then_some(value)
+}
+
+/// `expression` with its unqualified columns qualified by `table`
+fn qualify<'a>(expression: &Expression<'a>, table: &'a str) -> Expression<'a> {
+    match expression {
+        Expression::UnqualifiedColumn(c) => {
+            Expression::Column(ColumnReference { table, column: *c })
+        }
+        Expression::Aggregate(f, e) => Expression::Aggregate(*f, Box::new(qualify(e, table))),
+        Expression::Binary(left, op, right) => Expression::Binary(
+            Box::new(qualify(left, table)),
+            *op,
+            Box::new(qualify(right, table)),
+        ),
+        _ => expression.clone(),
+    }
+}
+
+/// adds the calculated columns of a model to their tables.
+///
+/// The table of every calculated column is replaced by a view that adds the column, in the
+/// tables as well as in the session, so that later calculated columns can use it.
+pub(crate) fn add_calculated_columns(
+    ctx: &SessionContext,
+    tables: &mut Tables,
+    references: &TableReferences,
+    definitions: &[CalculatedColumnData],
+) -> Result<(), TldrError> {
+    for definition in definitions {
+        let table = definition.column.table;
+        let name = definition.column.column;
+        let invalid = |reason: String| TldrError::TldrInvalidCalculatedColumn {
+            column: format!("{}.{}", table, name),
+            reason,
+        };
+
+        let provider = tables
+            .get(table)
+            .ok_or_else(|| TldrError::TldrUnknownTable {
+                table: table.to_string(),
+            })?
+            .clone();
+        if provider.schema().field_with_name(name).is_ok() {
+            return Err(invalid("the table already has this column".to_string()));
+        }
+
+        let expr = to_expr(
+            &qualify(&definition.expression, table),
+            &mut |m| Err(invalid(format!("the measure [{}] cannot be used", m))),
+            &unexpected_parameter,
+        )?;
+        if contains_aggregate(&expr) {
+            return Err(invalid("aggregates cannot be used".to_string()));
+        }
+        let mut columns = Vec::new();
+        collect_columns(&expr, &mut columns);
+        for c in &columns {
+            if check_column(tables, c)? != table {
+                return Err(invalid(format!("{} is a column of another table", c)));
+            }
+        }
+
+        let mut projection = provider
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| Expr::Column(column(table, f.name())))
+            .collect::<Vec<_>>();
+        projection.push(expr.alias(name));
+
+        let view = LogicalPlanBuilder::scan(
+            TableReference::bare(table.to_string()),
+            provider_as_source(provider),
+            None,
+        )
+        .and_then(|b| b.project(projection))
+        .and_then(|b| b.build())
+        .and_then(|plan| ViewTable::try_new(plan, None))
+        .map_err(|e| invalid(e.to_string()))?;
+        let view: Arc<dyn TableProvider> = Arc::new(view);
+
+        let reference = references
+            .get(table)
+            .cloned()
+            .unwrap_or_else(|| TableReference::bare(table.to_string()));
+        ctx.deregister_table(reference.clone())
+            .and_then(|_| ctx.register_table(reference, view.clone()))
+            .map_err(|e| TldrError::TldrCouldNotRegisterTable {
+                table: table.to_string(),
+                source: e,
+            })?;
+        tables.insert(table.to_string(), view);
+    }
+
+    Ok(())
+}
+
+/// compiles the measure definitions of a model into datafusion expressions.
+///
+/// Measures may reference each other in any order, but not in a cycle. Every measure is
+/// type checked against the schemas of the tables it references.
+pub(crate) fn compile_measures(
+    tables: &Tables,
+    definitions: &[MeasureData],
+) -> Result<Vec<Measure>, TldrError> {
+    let mut measures: Vec<Measure> = Vec::new();
+
+    for definition in definitions {
+        if measures.iter().any(|m| m.name == definition.name) {
+            return Err(TldrError::TldrDuplicateMeasure {
+                measure: definition.name.to_string(),
+            });
+        }
+
+        let expr = measure_expr(definition.name, definitions, &mut Vec::new())?;
+        check_measure(tables, definition.name, &expr)?;
+
+        measures.push(Measure {
+            name: definition.name.to_string(),
+            expr,
+            definition: definition.expression.to_string(),
+        });
+    }
+
+    Ok(measures)
+}
+
+/// the expression of the measure `name` with all referenced measures inlined.
+///
+/// `stack` holds the measures that are being inlined at the moment.
+fn measure_expr(
+    name: &str,
+    definitions: &[MeasureData],
+    stack: &mut Vec<String>,
+) -> Result<Expr, TldrError> {
+    if stack.iter().any(|n| n == name) {
+        return Err(TldrError::TldrCyclicMeasure {
+            measure: name.to_string(),
+        });
+    }
+    let definition = definitions.iter().find(|d| d.name == name).ok_or_else(|| {
+        TldrError::TldrUnknownMeasure {
+            measure: name.to_string(),
+        }
+    })?;
+
+    stack.push(name.to_string());
+    let expr = to_expr(
+        &definition.expression,
+        &mut |n| measure_expr(n, definitions, stack),
+        &unexpected_parameter,
+    )?;
+    stack.pop();
+
+    Ok(expr)
+}
+
+/// a measure must only use columns inside of aggregates and must have a valid type
+fn check_measure(tables: &Tables, name: &str, expr: &Expr) -> Result<(), TldrError> {
+    if contains_bare_column(expr) {
+        return Err(TldrError::TldrMeasureNotAggregated {
+            measure: name.to_string(),
+        });
+    }
+
+    let type_error = |e| TldrError::TldrMeasureTypeError {
+        measure: name.to_string(),
+        source: e,
+    };
+
+    let mut columns = Vec::new();
+    collect_columns(expr, &mut columns);
+
+    let mut schema = DFSchema::empty();
+    let mut merged = Vec::new();
+    for c in &columns {
+        let table = check_column(tables, c)?;
+        if merged.contains(&table) {
+            continue;
+        }
+        let table_schema = DFSchema::try_from_qualified_schema(
+            TableReference::bare(table.to_string()),
+            &tables[table].schema(),
+        )
+        .map_err(type_error)?;
+        schema.merge(&table_schema);
+        merged.push(table);
+    }
+
+    expr.get_type(&schema).map(|_| ()).map_err(type_error)
+}
+
+/// checks that the column exists and returns the name of its table
+fn check_column<'a>(tables: &Tables, c: &'a Column) -> Result<&'a str, TldrError> {
+    let table = c.relation.as_ref().map(|r| r.table()).unwrap_or_default();
+    tables
+        .get(table)
+        .ok_or_else(|| TldrError::TldrUnknownTable {
+            table: table.to_string(),
+        })?
+        .schema()
+        .field_with_name(&c.name)
+        .map(|_| table)
+        .map_err(|_| TldrError::TldrUnknownColumn {
+            table: table.to_string(),
+            column: c.name.clone(),
+        })
+}
+
+/// collects the distinct columns of `expr`
+fn collect_columns(expr: &Expr, columns: &mut Vec<Column>) {
+    match expr {
+        Expr::Column(c) => {
+            if !columns.contains(c) {
+                columns.push(c.clone());
+            }
+        }
+        Expr::Alias(alias) => collect_columns(&alias.expr, columns),
+        Expr::BinaryExpr(binary) => {
+            collect_columns(&binary.left, columns);
+            collect_columns(&binary.right, columns);
+        }
+        Expr::AggregateFunction(aggregate) => {
+            for arg in &aggregate.args {
+                collect_columns(arg, columns);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn contains_aggregate(expr: &Expr) -> bool {
+    match expr {
+        Expr::AggregateFunction(_) => true,
+        Expr::Alias(alias) => contains_aggregate(&alias.expr),
+        Expr::BinaryExpr(binary) => {
+            contains_aggregate(&binary.left) || contains_aggregate(&binary.right)
+        }
+        _ => false,
+    }
+}
+
+/// true if `expr` uses a column outside of an aggregate
+fn contains_bare_column(expr: &Expr) -> bool {
+    match expr {
+        Expr::Column(_) => true,
+        Expr::Alias(alias) => contains_bare_column(&alias.expr),
+        Expr::BinaryExpr(binary) => {
+            contains_bare_column(&binary.left) || contains_bare_column(&binary.right)
+        }
+        _ => false,
+    }
+}
+
+/// rewrites `expr` to be evaluated on top of the aggregation of a query.
+///
+/// The aggregates of `expr` are collected into `aggregates` and replaced by the output
+/// columns of the aggregation.
+fn rebase(expr: &Expr, aggregates: &mut Vec<Expr>) -> Result<Expr, TldrError> {
+    Ok(match expr {
+        Expr::AggregateFunction(_) => {
+            if !aggregates.contains(expr) {
+                aggregates.push(expr.clone());
+            }
+            Expr::Column(Column::from_name(expr.display_name().map_err(plan_error)?))
+        }
+        Expr::Alias(alias) => rebase(&alias.expr, aggregates)?.alias(alias.name.clone()),
+        Expr::BinaryExpr(binary) => binary_expr(
+            rebase(&binary.left, aggregates)?,
+            binary.op,
+            rebase(&binary.right, aggregates)?,
+        ),
+        _ => expr.clone(),
+    })
+}
+
+/// the output column of the aggregation of a query for the grouped expression `expr`.
+///
+/// A column keeps its table, any other expression, like `FactSales.SalesAmount * 2`, is an
+/// output column named like the expression.
+fn grouped(expr: &Expr) -> Result<Expr, TldrError> {
+    Ok(match expr {
+        Expr::Column(_) => expr.clone(),
+        _ => Expr::Column(Column::from_name(expr.display_name().map_err(plan_error)?)),
+    })
+}
+
+fn plan_error(e: DataFusionError) -> TldrError {
+    TldrError::TldrCouldNotPlanQuery { source: e }
+}
+
+fn run_error(query: &str) -> impl FnOnce(DataFusionError) -> TldrError + '_ {
+    move |e| TldrError::TldrCouldNotRunQuery {
+        query: query.to_string(),
+        source: e,
+    }
+}
+
+/// the batches of `df`, a result without rows is a single empty batch with the schema of `df`
+async fn collect(query: &str, df: DataFrame) -> Result<Vec<RecordBatch>, TldrError> {
+    let schema = Arc::new(Schema::from(df.schema()));
+    let batches = df.collect().await.map_err(run_error(query))?;
+
+    if batches.is_empty() {
+        return Ok(vec![RecordBatch::new_empty(schema)]);
+    }
+    Ok(batches)
+}
+
+#[cfg(test)]
+fn fixture_context(
+    extra_files: &str,
+    extra_relationships: &str,
+    extra_sections: &str,
+) -> Result<Context, TldrError> {
+    use crate::grammar::parser::ast_parser;
+
+    let script = format!(
+        "load_files (
+            (csv_file_name: \"fixtures/FactSales.csv\"),
+            (csv_file_name: \"fixtures/DimProduct.csv\"),
+            (csv_file_name: \"fixtures/DimProductSubcategory.csv\"),
+            (csv_file_name: \"fixtures/DimDate.csv\"),
+            (csv_file_name: \"fixtures/DimStore.csv\"),
+            {}
+        )
+        relationships (
+            FactSales.ProductKey -> DimProduct.ProductKey,
+            FactSales.DateKey -> DimDate.DateKey,
+            FactSales.StoreKey -> DimStore.StoreKey,
+            DimProduct.ProductSubcategoryKey -> DimProductSubcategory.ProductSubcategoryKey,
+            {}
+        )
+        {}",
+        extra_files, extra_relationships, extra_sections
+    );
+    let (_, ast) = ast_parser(Span::new(&script)).unwrap();
+    Context::convert_ast(&ast)
+}
+
+#[cfg(test)]
+async fn collect_pairs(context: &Context, query: &str) -> Vec<(String, f64)> {
+    let plan = context.plan_query(query).unwrap();
+    let batches = context
+        .ctx
+        .execute_logical_plan(plan)
+        .await
+        .unwrap()
+        .collect()
+        .await
+        .unwrap();
+
+    batch_pairs(batches)
+}
+
+/// the rows of batches with a string and a float column, sorted by the string
+#[cfg(test)]
+fn batch_pairs(batches: Vec<RecordBatch>) -> Vec<(String, f64)> {
+    use arrow::array::{Array, Float64Array, StringArray};
+
+    let mut ret = Vec::new();
+    for batch in batches {
+        let keys = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        let values = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        for i in 0..batch.num_rows() {
+            ret.push((keys.value(i).to_string(), values.value(i)));
+        }
+    }
+    ret.sort_by(|a, b| a.0.cmp(&b.0));
+    ret
+}
+
+#[tokio::test]
+async fn join_path_test() {
+    let context = fixture_context("", "", "").unwrap();
+
+    assert_eq!(
+        collect_pairs(&context, "DimProduct.Color, sum(FactSales.SalesAmount)").await,
+        vec![
+            ("Black".to_string(), 350.0),
+            ("Red".to_string(), 90.0),
+            ("White".to_string(), 300.0)
+        ]
+    );
+
+    // snowflake: FactSales -> DimProduct -> DimProductSubcategory
+    assert_eq!(
+        collect_pairs(
+            &context,
+            "DimProductSubcategory.ProductSubcategoryName, sum(FactSales.SalesAmount)"
+        )
+        .await,
+        vec![("Audio".to_string(), 140.0), ("Mobile".to_string(), 600.0)]
+    );
+}
+
+#[test]
+fn ambiguous_join_path_test() {
+    let context = fixture_context(
+        "(csv_file_name: \"fixtures/FactInventory.csv\")",
+        "FactInventory.ProductKey -> DimProduct.ProductKey,
+         FactInventory.StoreKey -> DimStore.StoreKey",
+        "",
+    )
+    .unwrap();
+
+    // both fact tables link products and stores
+    match context.plan_query("DimProduct.Color, DimStore.StoreName") {
+        Err(TldrError::TldrAmbiguousJoinPath { paths }) => assert_eq!(
+            paths,
+            vec![
+                "FactInventory: FactInventory.ProductKey -> DimProduct.ProductKey, FactInventory.StoreKey -> DimStore.StoreKey",
+                "FactSales: FactSales.ProductKey -> DimProduct.ProductKey, FactSales.StoreKey -> DimStore.StoreKey",
+            ]
+        ),
+        _ => panic!("expected an ambiguous join path"),
+    }
+
+    // naming the fact table resolves the ambiguity
+    assert!(context
+        .plan_query("DimProduct.Color, DimStore.StoreName, sum(FactSales.SalesQuantity)")
+        .is_ok());
+
+    assert!(matches!(
+        context.plan_query("DimProduct.Colour"),
+        Err(TldrError::TldrUnknownColumn { .. })
+    ));
+}
+
+#[tokio::test]
+async fn measures_test() {
+    let context = fixture_context(
+        "",
+        "",
+        "measures (
+            \"Total Sales\": sum(FactSales.SalesAmount),
+            \"Margin\": [Total Sales] - [Total Cost],
+            \"Total Cost\": sum(FactSales.TotalCost),
+        )",
+    )
+    .unwrap();
+
+    assert_eq!(
+        collect_pairs(&context, "DimProduct.Color, [Margin]").await,
+        vec![
+            ("Black".to_string(), 150.0),
+            ("Red".to_string(), 45.0),
+            ("White".to_string(), 100.0)
+        ]
+    );
+
+    let plan = context.plan_query("DimProduct.Color, [Margin]").unwrap();
+    assert_eq!(plan.schema().field(1).name(), "Margin");
+
+    // a computed expression is grouped by as a whole
+    assert_eq!(
+        collect_pairs(
+            &context,
+            "DimProduct.ProductName || ' - ' || DimProduct.Color, [Total Sales]"
+        )
+        .await,
+        vec![
+            ("Contoso Phone - Black".to_string(), 300.0),
+            ("Contoso Tablet - White".to_string(), 300.0),
+            ("Litware Radio - Red".to_string(), 90.0),
+            ("Litware Speaker - Black".to_string(), 50.0)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn sql_test() {
+    let context = fixture_context(
+        "",
+        "",
+        "calculated_columns (
+            FactSales.Margin = SalesAmount - TotalCost,
+        )
+        measures (
+            \"Total Margin\": sum(FactSales.Margin),
+        )",
+    )
+    .unwrap();
+
+    // calculated columns are columns of their tables
+    let batches = context
+        .sql(
+            "SELECT p.\"Color\", sum(s.\"Margin\") FROM \"FactSales\" s
+             JOIN \"DimProduct\" p ON s.\"ProductKey\" = p.\"ProductKey\"
+             GROUP BY p.\"Color\"",
+        )
+        .unwrap();
+    assert_eq!(
+        batch_pairs(batches),
+        vec![
+            ("Black".to_string(), 150.0),
+            ("Red".to_string(), 45.0),
+            ("White".to_string(), 100.0)
+        ]
+    );
+
+    // measures are views of their totals
+    let batches = context
+        .sql("SELECT 'total', * FROM measures.\"Total Margin\"")
+        .unwrap();
+    assert_eq!(batch_pairs(batches), vec![("total".to_string(), 295.0)]);
+
+    let batches = context
+        .execute_query(
+            r#"sql "SELECT \"Color\", CAST(count(*) AS DOUBLE) FROM \"DimProduct\"
+                    GROUP BY \"Color\"""#,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        batch_pairs(batches),
+        vec![
+            ("Black".to_string(), 2.0),
+            ("Red".to_string(), 1.0),
+            ("White".to_string(), 1.0)
+        ]
+    );
+
+    // the sql cannot change the model
+    assert!(matches!(
+        context.sql("CREATE TABLE copy AS SELECT * FROM \"DimProduct\""),
+        Err(TldrError::TldrCouldNotRunQuery { .. })
+    ));
+    assert!(context.sql("SELECT * FROM copy").is_err());
+}
+
+#[test]
+fn missing_measure_view_test() {
+    let context = fixture_context(
+        "(csv_file_name: \"fixtures/FactInventory.csv\")",
+        "",
+        "measures (
+            \"Total Sales\": sum(FactSales.SalesAmount),
+            \"Stock per Sale\": sum(FactInventory.OnHandQuantity) / sum(FactSales.SalesQuantity),
+            \"Stock per Sale %\": [Stock per Sale] * 100,
+        )",
+    )
+    .unwrap();
+
+    // FactInventory has no relationships, the measure over both fact tables has no view
+    assert!(context
+        .sql("SELECT * FROM measures.\"Total Sales\"")
+        .is_ok());
+    match context.sql("SELECT * FROM measures.\"Stock per Sale\"") {
+        Err(TldrError::TldrMissingMeasureView {
+            measure, reason, ..
+        }) => {
+            assert_eq!(measure, "Stock per Sale");
+            assert_eq!(
+                reason,
+                "the tables FactInventory, FactSales cannot be joined"
+            );
+        }
+        _ => panic!("expected a missing measure view"),
+    }
+    // the view is found by its name, not by a prefix of it
+    match context.sql("SELECT * FROM measures.\"Stock per Sale %\"") {
+        Err(TldrError::TldrMissingMeasureView { measure, .. }) => {
+            assert_eq!(measure, "Stock per Sale %")
+        }
+        _ => panic!("expected a missing measure view"),
+    }
+    assert!(matches!(
+        context.sql("SELECT * FROM measures.\"Unknown\""),
+        Err(TldrError::TldrCouldNotRunQuery { .. })
+    ));
+}
+
+#[test]
+fn invalid_measures_test() {
+    assert!(matches!(
+        fixture_context("", "", "measures ( \"A\": [B], \"B\": [A] + 1 )"),
+        Err(TldrError::TldrCyclicMeasure { .. })
+    ));
+    assert!(matches!(
+        fixture_context("", "", "measures ( \"A\": [Total Sales] )"),
+        Err(TldrError::TldrUnknownMeasure { .. })
+    ));
+    assert!(matches!(
+        fixture_context("", "", "measures ( \"A\": FactSales.SalesAmount )"),
+        Err(TldrError::TldrMeasureNotAggregated { .. })
+    ));
+    assert!(matches!(
+        fixture_context("", "", "measures ( \"A\": sum(DimProduct.Color) )"),
+        Err(TldrError::TldrMeasureTypeError { .. })
+    ));
+}
+
+#[tokio::test]
+async fn filter_propagation_test() {
+    let context = fixture_context("", "", "").unwrap();
+
+    // DimDate is not queried, its filter flows to FactSales
+    assert_eq!(
+        collect_pairs(
+            &context,
+            "DimProduct.Color, sum(FactSales.SalesAmount) where DimDate.CalendarYear = 2009"
+        )
+        .await,
+        vec![
+            ("Black".to_string(), 250.0),
+            ("Red".to_string(), 90.0),
+            ("White".to_string(), 300.0)
+        ]
+    );
+
+    // a filter on a queried table
+    assert_eq!(
+        collect_pairs(
+            &context,
+            "DimProduct.Color, sum(FactSales.SalesAmount) where DimProduct.Color <> 'Red'"
+        )
+        .await,
+        vec![("Black".to_string(), 350.0), ("White".to_string(), 300.0)]
+    );
+
+    // DimProductSubcategory -> DimProduct -> FactSales
+    assert_eq!(
+        collect_pairs(
+            &context,
+            "DimStore.StoreName, sum(FactSales.SalesAmount)
+             where DimProductSubcategory.ProductSubcategoryName = 'Audio'"
+        )
+        .await,
+        vec![
+            ("Contoso Berlin".to_string(), 90.0),
+            ("Contoso Seattle".to_string(), 50.0)
+        ]
+    );
+
+    assert!(matches!(
+        context.plan_query("DimProduct.Color where sum(FactSales.SalesAmount) > 100"),
+        Err(TldrError::TldrInvalidFilter { .. })
+    ));
+    assert!(matches!(
+        context.plan_query("DimProduct.Color where DimDate.CalendarYear = DimStore.StoreKey"),
+        Err(TldrError::TldrInvalidFilter { .. })
+    ));
+}
+
+#[tokio::test]
+async fn filter_direction_test() {
+    let query =
+        "DimProduct.Color, sum(FactSales.SalesAmount) where FactInventory.OnHandQuantity > 5";
+
+    // filters do not flow from FactInventory to DimProduct
+    let context = fixture_context(
+        "(csv_file_name: \"fixtures/FactInventory.csv\")",
+        "FactInventory.ProductKey -> DimProduct.ProductKey",
+        "",
+    )
+    .unwrap();
+    assert_eq!(
+        collect_pairs(&context, query).await,
+        vec![
+            ("Black".to_string(), 350.0),
+            ("Red".to_string(), 90.0),
+            ("White".to_string(), 300.0)
+        ]
+    );
+
+    // only product 1 has more than 5 items on hand
+    let context = fixture_context(
+        "(csv_file_name: \"fixtures/FactInventory.csv\")",
+        "FactInventory.ProductKey -> DimProduct.ProductKey (filter_direction: both)",
+        "",
+    )
+    .unwrap();
+    assert_eq!(
+        collect_pairs(&context, query).await,
+        vec![("Black".to_string(), 300.0)]
+    );
+}
+
+#[test]
+fn unrelated_filter_test() {
+    let context =
+        fixture_context("(csv_file_name: \"fixtures/FactInventory.csv\")", "", "").unwrap();
+
+    // FactInventory has no relationships, the filter would be dropped
+    match context.plan_query(
+        "DimProduct.Color, sum(FactSales.SalesAmount) where FactInventory.OnHandQuantity > 5",
+    ) {
+        Err(TldrError::TldrUnrelatedFilter { table, .. }) => assert_eq!(table, "FactInventory"),
+        _ => panic!("expected an unrelated filter"),
+    }
+}
+
+#[tokio::test]
+async fn calculated_columns_test() {
+    let context = fixture_context(
+        "",
+        "",
+        "calculated_columns (
+            DimProduct.Label = ProductName || ' (' || Color || ')',
+            FactSales.Margin = SalesAmount - TotalCost,
+            FactSales.MarginShare = FactSales.Margin / SalesAmount,
+        )
+        measures (
+            \"Total Margin\": sum(FactSales.Margin),
+        )",
+    )
+    .unwrap();
+
+    assert_eq!(
+        collect_pairs(&context, "DimProduct.Color, [Total Margin]").await,
+        vec![
+            ("Black".to_string(), 150.0),
+            ("Red".to_string(), 45.0),
+            ("White".to_string(), 100.0)
+        ]
+    );
+
+    assert_eq!(
+        collect_pairs(
+            &context,
+            "DimProduct.Label, sum(FactSales.Margin) where FactSales.MarginShare > 0.45"
+        )
+        .await,
+        vec![
+            ("Litware Radio (Red)".to_string(), 45.0),
+            ("Litware Speaker (Black)".to_string(), 30.0)
+        ]
+    );
+
+    assert!(matches!(
+        context.plan_query("ProductName"),
+        Err(TldrError::TldrUnqualifiedColumn { .. })
+    ));
+}
+
+#[test]
+fn invalid_calculated_columns_test() {
+    let invalid = |definition: &str| {
+        matches!(
+            fixture_context("", "", &format!("calculated_columns ( {} )", definition)),
+            Err(TldrError::TldrInvalidCalculatedColumn { .. })
+        )
+    };
+
+    assert!(invalid("DimProduct.Color = ProductName"));
+    assert!(invalid("DimProduct.Keys = sum(ProductKey)"));
+    assert!(invalid("DimProduct.Amount = FactSales.SalesAmount"));
+    assert!(invalid("DimProduct.Name = ProductName - 1"));
+}
+
+#[tokio::test]
+async fn named_query_test() {
+    let context = fixture_context(
+        "",
+        "",
+        "measures (
+            \"Total Sales\": sum(FactSales.SalesAmount),
+        )
+        queries (
+            \"sales_by_color\"(year: int32, color: string):
+                DimProduct.Color, [Total Sales]
+                where DimDate.CalendarYear = $year and DimProduct.Color <> $color;
+            \"colors\": DimProduct.Color;
+        )",
+    )
+    .unwrap();
+
+    assert_eq!(
+        context
+            .queries
+            .iter()
+            .map(|q| q.definition.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "\"sales_by_color\"(year: int32, color: string): \"DimProduct\".\"Color\", \
+             [Total Sales] where (\"DimDate\".\"CalendarYear\" = $year) and \
+             (\"DimProduct\".\"Color\" <> $color)",
+            "\"colors\": \"DimProduct\".\"Color\"",
+        ]
+    );
+
+    let batches = context
+        .run_query("sales_by_color", &[("year", "2009"), ("color", "White")])
+        .await
+        .unwrap();
+    assert_eq!(
+        batch_pairs(batches),
+        vec![("Black".to_string(), 250.0), ("Red".to_string(), 90.0)]
+    );
+
+    // a value is bound as a literal, it is never read as a part of the query
+    let batches = context
+        .run_query(
+            "sales_by_color",
+            &[("year", "2009"), ("color", "White' or '1' = '1")],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        batch_pairs(batches),
+        vec![
+            ("Black".to_string(), 250.0),
+            ("Red".to_string(), 90.0),
+            ("White".to_string(), 300.0)
+        ]
+    );
+
+    assert!(matches!(
+        context.plan_named_query("sales_by_color", &[("year", "2009")]),
+        Err(TldrError::TldrMissingParameter { parameter, .. }) if parameter == "color"
+    ));
+    assert!(matches!(
+        context.plan_named_query("colors", &[("year", "2009")]),
+        Err(TldrError::TldrUnknownParameter { .. })
+    ));
+    assert!(matches!(
+        context.plan_named_query("sales_by_color", &[("year", "20x9"), ("color", "Red")]),
+        Err(TldrError::TldrInvalidParameterValue { parameter, .. }) if parameter == "year"
+    ));
+    assert!(matches!(
+        context.plan_named_query("top_products", &[]),
+        Err(TldrError::TldrUnknownQuery { .. })
+    ));
+    assert!(matches!(
+        context.plan_query("DimProduct.Color where DimProduct.Color = $color"),
+        Err(TldrError::TldrUnexpectedParameter { .. })
+    ));
+}
+
+#[test]
+fn invalid_named_queries_test() {
+    assert!(matches!(
+        fixture_context(
+            "",
+            "",
+            "queries ( \"a\"(x: int32): DimProduct.Color where DimProduct.ProductKey = $y )"
+        ),
+        Err(TldrError::TldrInvalidQuery { .. })
+    ));
+    assert!(matches!(
+        fixture_context(
+            "",
+            "",
+            "queries ( \"a\": DimProduct.Color; \"a\": DimStore.StoreKey )"
+        ),
+        Err(TldrError::TldrInvalidQuery { .. })
+    ));
+    assert!(matches!(
+        fixture_context("", "", "queries ( \"a\"(x: int32): DimProduct.Missing )"),
+        Err(TldrError::TldrUnknownColumn { .. })
+    ));
+    assert!(matches!(
+        fixture_context(
+            "",
+            "",
+            "measures ( \"A\": sum(FactSales.SalesAmount) * $factor )"
+        ),
+        Err(TldrError::TldrUnexpectedParameter { .. })
+    ));
+}
+
+#[test]
+fn parameter_value_test() {
+    let parameter = |data_type, format: Option<&str>| QueryParameter {
+        name: "p".to_string(),
+        data_type,
+        format: format.map(str::to_string),
+    };
+
+    assert_eq!(
+        parameter_value(&parameter(DataType::Int32, None), "2009"),
+        Some(ScalarValue::Int32(Some(2009)))
+    );
+    assert_eq!(
+        parameter_value(&parameter(DataType::Int32, None), "20x9"),
+        None
+    );
+    assert_eq!(
+        parameter_value(&parameter(DataType::Date32, Some("%d.%m.%Y")), "31.12.2008"),
+        Some(ScalarValue::Date32(Some(14244)))
+    );
+    assert_eq!(
+        parameter_value(&parameter(DataType::Date32, None), "2008-12-31"),
+        Some(ScalarValue::Date32(Some(14244)))
+    );
+    assert_eq!(
+        parameter_value(&parameter(DataType::Utf8, None), "a'b"),
+        Some(ScalarValue::Utf8(Some("a'b".to_string())))
+    );
+}
@@ -0,0 +1,511 @@
+use crate::{
+    cli::{load_script, output_error, split_queries},
+    context::{Context, LoadOptions},
+    error::TldrError,
+};
+
+use arrow::util::pretty::pretty_format_batches;
+use rustyline::{
+    completion::Completer,
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::DefaultHistory,
+    validate::{ValidationContext, ValidationResult, Validator},
+    Editor, Helper,
+};
+use std::{
+    env,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+use tokio::runtime::Runtime;
+
+/// the file in the home directory that keeps the input of earlier sessions
+const HISTORY_FILE_NAME: &str = ".tldr_history";
+
+const HELP: &str = "\
+queries end with a semicolon and may span several lines, e.g.
+    DimProduct.Color, sum(FactSales.SalesAmount) where DimDate.CalendarYear = 2009;
+raw DataFusion sql is run as sql \"...\", with its double quotes escaped by a backslash, e.g.
+    sql \"SELECT \\\"Color\\\", count(*) FROM \\\"DimProduct\\\" GROUP BY \\\"Color\\\"\";
+
+.tables             lists the tables
+.schema TABLE       shows the columns of a table
+.measures           lists the measures
+.reload             loads the script again
+.timing [on|off]    shows how long queries take
+.help               shows this help
+.quit               ends the session";
+
+const META_COMMANDS: &[&str] = &[
+    ".tables",
+    ".schema",
+    ".measures",
+    ".reload",
+    ".timing",
+    ".help",
+    ".quit",
+];
+
+/// the words of the query language that are completed besides the names of the model
+const KEYWORDS: &[&str] = &[
+    "where", "and", "sum(", "min(", "max(", "avg(", "count(", "sql",
+];
+
+/// what the session does after an input
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Step {
+    Continue,
+    Reloaded,
+    Quit,
+}
+
+/// an interactive session that keeps the model of a script loaded between queries
+pub struct Repl {
+    script: PathBuf,
+    options: LoadOptions,
+    context: Context,
+    runtime: Runtime,
+    timing: bool,
+}
+
+impl Repl {
+    /// loads the model of the tldr script `script` as given by `options`, `.reload` loads it
+    /// the same way
+    pub fn new(script: &Path, options: LoadOptions) -> Result<Repl, TldrError> {
+        let runtime =
+            Runtime::new().map_err(|e| TldrError::TldrCouldNotStartRuntime { source: e })?;
+        let context = runtime.block_on(async { load_script(script, &options) })?;
+
+        Ok(Repl {
+            script: script.to_path_buf(),
+            options,
+            context,
+            runtime,
+            timing: false,
+        })
+    }
+
+    /// reads queries and meta commands until the input ends or `.quit` is given.
+    ///
+    /// The input is kept in `~/.tldr_history`. An error of a query is printed, the session
+    /// goes on.
+    pub fn run(&mut self) -> Result<(), TldrError> {
+        let mut editor = Editor::<ReplHelper, DefaultHistory>::new()
+            .map_err(|e| TldrError::TldrCouldNotReadInput { source: e })?;
+        editor.set_helper(Some(ReplHelper::new(&self.context)));
+
+        let history = env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE_NAME));
+        if let Some(history) = &history {
+            // there is no history before the first session
+            let _ = editor.load_history(history);
+        }
+
+        println!(
+            "loaded {} tables from {}, .help lists the commands",
+            self.context.tables.len(),
+            self.script.display()
+        );
+        loop {
+            let input = match editor.readline("tldr> ") {
+                Ok(input) => input,
+                // ctrl-c discards the input, ctrl-d ends the session
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(e) => return Err(TldrError::TldrCouldNotReadInput { source: e }),
+            };
+            if input.trim().is_empty() {
+                continue;
+            }
+            let _ = editor.add_history_entry(input.as_str());
+
+            match self.execute(&input, &mut io::stdout()) {
+                Ok(Step::Continue) => {}
+                // the completions follow the tables of the reloaded script
+                Ok(Step::Reloaded) => editor.set_helper(Some(ReplHelper::new(&self.context))),
+                Ok(Step::Quit) => break,
+                Err(e) => eprintln!("{:?}", miette::Report::new(e)),
+            }
+        }
+
+        if let Some(history) = &history {
+            // losing the history does not lose any results
+            let _ = editor.save_history(history);
+        }
+        Ok(())
+    }
+
+    /// runs a meta command or the queries of `input` and writes their output to `out`
+    fn execute(&mut self, input: &str, out: &mut dyn Write) -> Result<Step, TldrError> {
+        let input = input.trim();
+        if let Some(command) = input.strip_prefix('.') {
+            return self.meta_command(command, out);
+        }
+
+        for query in split_queries(input) {
+            let start = Instant::now();
+            let batches = self.runtime.block_on(self.context.execute_query(&query))?;
+            let table = pretty_format_batches(&batches).map_err(output_error("stdout"))?;
+            writeln!(out, "{}", table).map_err(output_error("stdout"))?;
+            if self.timing {
+                let rows = batches.iter().map(|b| b.num_rows()).sum::<usize>();
+                writeln!(
+                    out,
+                    "{} rows in {:.3}s",
+                    rows,
+                    start.elapsed().as_secs_f64()
+                )
+                .map_err(output_error("stdout"))?;
+            }
+        }
+        Ok(Step::Continue)
+    }
+
+    fn meta_command(&mut self, command: &str, out: &mut dyn Write) -> Result<Step, TldrError> {
+        let (name, argument) = command
+            .split_once(char::is_whitespace)
+            .map(|(n, a)| (n, a.trim()))
+            .unwrap_or((command, ""));
+
+        let mut lines = Vec::new();
+        let step = match (name, argument) {
+            ("tables", "") => {
+                let mut names = self.context.tables.keys().collect::<Vec<_>>();
+                names.sort();
+                lines.extend(names.into_iter().cloned());
+                Step::Continue
+            }
+            ("schema", table) if !table.is_empty() => {
+                lines.extend(self.schema(table.trim_matches('"'))?);
+                Step::Continue
+            }
+            ("measures", "") => {
+                for m in &self.context.measures {
+                    lines.push(format!("[{}]: {}", m.name, m.definition));
+                }
+                Step::Continue
+            }
+            ("reload", "") => {
+                // the loaded model stays if the script cannot be loaded
+                self.context = self
+                    .runtime
+                    .block_on(async { load_script(&self.script, &self.options) })?;
+                lines.push(format!("loaded {} tables", self.context.tables.len()));
+                Step::Reloaded
+            }
+            ("timing", "" | "on" | "off") => {
+                self.timing = match argument {
+                    "on" => true,
+                    "off" => false,
+                    _ => !self.timing,
+                };
+                let state = if self.timing { "on" } else { "off" };
+                lines.push(format!("timing is {}", state));
+                Step::Continue
+            }
+            ("help", "") => {
+                lines.push(HELP.to_string());
+                Step::Continue
+            }
+            ("quit" | "exit", "") => Step::Quit,
+            _ => {
+                return Err(TldrError::TldrUnknownCommand {
+                    command: format!(".{}", command),
+                })
+            }
+        };
+
+        for line in lines {
+            writeln!(out, "{}", line).map_err(output_error("stdout"))?;
+        }
+        Ok(step)
+    }
+
+    /// the columns of `table` with their types, one per line
+    fn schema(&self, table: &str) -> Result<Vec<String>, TldrError> {
+        let schema = self
+            .context
+            .tables
+            .get(table)
+            .ok_or_else(|| TldrError::TldrUnknownTable {
+                table: table.to_string(),
+            })?
+            .schema();
+        let width = schema
+            .fields()
+            .iter()
+            .map(|f| f.name().len())
+            .max()
+            .unwrap_or(0);
+
+        Ok(schema
+            .fields()
+            .iter()
+            .map(|f| {
+                let nullable = if f.is_nullable() { "" } else { " not null" };
+                format!("{:width$}  {}{}", f.name(), f.data_type(), nullable)
+            })
+            .collect())
+    }
+}
+
+/// completes the names of the model and continues a query on the next line until it ends
+/// with a semicolon
+struct ReplHelper {
+    tables: Vec<String>,
+    words: Vec<String>,
+}
+
+impl ReplHelper {
+    fn new(context: &Context) -> ReplHelper {
+        let mut tables = Vec::new();
+        let mut words = KEYWORDS.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        for (name, table) in &context.tables {
+            tables.push(quote(name));
+            for field in table.schema().fields() {
+                words.push(format!("{}.{}", quote(name), quote(field.name())));
+            }
+        }
+        words.extend(tables.iter().cloned());
+        words.extend(context.measures.iter().map(|m| format!("[{}]", m.name)));
+        tables.sort();
+        words.sort();
+        words.dedup();
+
+        ReplHelper { tables, words }
+    }
+
+    /// the start of the word before `pos` and the words it can be completed to
+    fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let line = &line[..pos];
+        if line.starts_with('.') && !line.contains(char::is_whitespace) {
+            return (0, matching(META_COMMANDS.iter().copied(), line));
+        }
+
+        let start = word_start(line);
+        let word = &line[start..];
+        let words = if line.starts_with(".schema") {
+            &self.tables
+        } else {
+            &self.words
+        };
+        (start, matching(words.iter().map(String::as_str), word))
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if is_complete(ctx.input()) {
+            Ok(ValidationResult::Valid(None))
+        } else {
+            Ok(ValidationResult::Incomplete)
+        }
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// names that are not identifiers are quoted, e.g. the tables of a schema
+fn quote(name: &str) -> String {
+    let mut chars = name.chars();
+    let identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if identifier {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name)
+    }
+}
+
+/// the words that start with `prefix`, ignoring case and the quote of a quoted name
+fn matching<'a>(words: impl Iterator<Item = &'a str>, prefix: &str) -> Vec<String> {
+    let prefix = prefix.to_lowercase();
+    words
+        .filter(|w| {
+            let w = w.to_lowercase();
+            w.starts_with(&prefix) || w.strip_prefix('"').is_some_and(|w| w.starts_with(&prefix))
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// the start of the last word of `line`, a quoted name or a measure may contain spaces
+fn word_start(line: &str) -> usize {
+    let mut start = 0;
+    let mut closing = None;
+    for (i, c) in line.char_indices() {
+        match closing {
+            Some(q) if c == q => closing = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' => closing = Some(c),
+                '[' => closing = Some(']'),
+                c if c.is_alphanumeric() || c == '_' || c == '.' => {}
+                _ => start = i + c.len_utf8(),
+            },
+        }
+    }
+    start
+}
+
+/// a query is complete once it ends with a semicolon outside of strings and quoted names,
+/// meta commands are complete at the end of their line
+fn is_complete(input: &str) -> bool {
+    let input = input.trim();
+    if input.is_empty() || input.starts_with('.') {
+        return true;
+    }
+
+    let mut closing = None;
+    let mut escaped = false;
+    for c in input.chars() {
+        match closing {
+            // the sql of a sql statement escapes its double quotes
+            Some('"') if c == '\\' && !escaped => {
+                escaped = true;
+                continue;
+            }
+            Some(q) if c == q && !escaped => closing = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => closing = Some(c),
+            None => {}
+        }
+        escaped = false;
+    }
+    closing.is_none() && input.ends_with(';')
+}
+
+#[test]
+fn repl_test() {
+    let dir = std::env::temp_dir().join(format!("tldr_repl_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("model.tldr");
+    std::fs::write(
+        &script,
+        "load_files (
+            (csv_file_name: \"fixtures/FactSales.csv\"),
+            (csv_file_name: \"fixtures/DimProduct.csv\", schema: \"contoso\"),
+        )
+        relationships (
+            FactSales.ProductKey -> \"contoso.DimProduct\".ProductKey,
+        )
+        measures (
+            \"Total Sales\": sum(FactSales.SalesAmount),
+        )",
+    )
+    .unwrap();
+    let mut repl = Repl::new(&script, LoadOptions { parallelism: 2 }).unwrap();
+
+    let mut execute = |input: &str| {
+        let mut out = Vec::new();
+        let step = repl.execute(input, &mut out).unwrap();
+        (step, String::from_utf8(out).unwrap())
+    };
+    assert_eq!(
+        execute(".tables"),
+        (
+            Step::Continue,
+            "FactSales\ncontoso.DimProduct\n".to_string()
+        )
+    );
+    assert_eq!(
+        execute(".schema \"contoso.DimProduct\"").1,
+        "ProductKey             Int64\n\
+         ProductName            Utf8\n\
+         Color                  Utf8\n\
+         ProductSubcategoryKey  Int64\n"
+    );
+    assert_eq!(
+        execute(".measures").1,
+        "[Total Sales]: sum(\"FactSales\".\"SalesAmount\")\n"
+    );
+    assert_eq!(execute(".timing").1, "timing is on\n");
+    let (_, output) = execute(
+        "\"contoso.DimProduct\".Color, [Total Sales]
+         where \"contoso.DimProduct\".Color = 'Red';",
+    );
+    assert!(output.contains("| Red   | 90.0        |"), "{}", output);
+    assert!(output.contains("1 rows in "), "{}", output);
+    let (_, output) = execute(
+        "sql \"SELECT \\\"Color\\\" FROM contoso.\\\"DimProduct\\\"
+             WHERE \\\"ProductKey\\\" = 4\";",
+    );
+    assert!(output.contains("| Red   |"), "{}", output);
+    assert_eq!(execute(".reload").0, Step::Reloaded);
+    assert_eq!(execute(".quit").0, Step::Quit);
+    assert!(matches!(
+        repl.execute(".schema Missing", &mut Vec::new()),
+        Err(TldrError::TldrUnknownTable { .. })
+    ));
+    assert!(matches!(
+        repl.execute(".drop FactSales", &mut Vec::new()),
+        Err(TldrError::TldrUnknownCommand { .. })
+    ));
+
+    let helper = ReplHelper::new(&repl.context);
+    assert_eq!(
+        helper.candidates(".ti", 3),
+        (0, vec![".timing".to_string()])
+    );
+    assert_eq!(
+        helper.candidates("FactSales.Sales", 15),
+        (
+            0,
+            vec![
+                "FactSales.SalesAmount".to_string(),
+                "FactSales.SalesKey".to_string(),
+                "FactSales.SalesQuantity".to_string(),
+            ]
+        )
+    );
+    let line = "sum(FactSales.SalesAmount), \"contoso.DimProduct\".co";
+    assert_eq!(
+        helper.candidates(line, line.len()),
+        (28, vec!["\"contoso.DimProduct\".Color".to_string()])
+    );
+    let line = "FactSales.StoreKey, [total";
+    assert_eq!(
+        helper.candidates(line, line.len()),
+        (20, vec!["[Total Sales]".to_string()])
+    );
+    assert_eq!(
+        helper.candidates(".schema con", 11),
+        (8, vec!["\"contoso.DimProduct\"".to_string()])
+    );
+
+    assert!(is_complete(".tables"));
+    assert!(is_complete(
+        "FactSales.StoreKey,\n sum(FactSales.SalesAmount);"
+    ));
+    assert!(!is_complete("FactSales.StoreKey,"));
+    assert!(!is_complete(
+        "FactSales.StoreKey where \"contoso.DimProduct\".Color = 'a;"
+    ));
+    assert!(!is_complete("sql \"SELECT \\\";"));
+    assert!(is_complete("sql \"SELECT \\\"a;\\\" FROM t\";"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,312 @@
+use crate::{
+    context::Context,
+    error::{BoxedError, TldrError},
+    grammar::{
+        ast::{Cardinality, FileDescriptorData, FilterDirection},
+        parser::parse_script,
+    },
+};
+
+use arrow::{ipc::writer::FileWriter, record_batch::RecordBatch};
+use std::{
+    fs::{self, File},
+    path::Path,
+};
+
+/// the file of a snapshot that holds the model, a tldr script that loads the tables from the
+/// arrow files next to it
+pub const MANIFEST_FILE_NAME: &str = "manifest.tldr";
+
+impl Context {
+    /// writes every table of the context to an arrow ipc file in `dir`, along with a manifest
+    /// of the relationships, measures and named queries of the model.
+    ///
+    /// Calculated columns are written as columns of their tables. Tables that are scanned
+    /// lazily are read completely, `load_snapshot` loads them into memory like all others.
+    /// The names of the manifest are quoted, a table or column whose name holds a double quote
+    /// cannot be saved.
+    pub async fn save_snapshot(&self, dir: impl AsRef<Path>) -> Result<(), TldrError> {
+        self.check_manifest_names()?;
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir).map_err(snapshot_error(dir))?;
+
+        let mut names = self.tables.keys().collect::<Vec<_>>();
+        names.sort();
+
+        let mut files = Vec::new();
+        for (i, name) in names.into_iter().enumerate() {
+            // names like a/b and a_b are the same once the separators are replaced
+            let file_name = format!("{}_{}.arrow", i, name.replace(['/', '\\'], "_"));
+            self.write_table(name, &dir.join(&file_name)).await?;
+            files.push((name.as_str(), file_name));
+        }
+
+        let path = dir.join(MANIFEST_FILE_NAME);
+        fs::write(&path, self.manifest(&files)).map_err(snapshot_error(&path))
+    }
+
+    /// loads a snapshot written by `save_snapshot`.
+    ///
+    /// The tables are read from their arrow files, neither parsed nor cast again.
+    pub fn load_snapshot(dir: impl AsRef<Path>) -> Result<Context, TldrError> {
+        let dir = dir.as_ref();
+        let path = dir.join(MANIFEST_FILE_NAME);
+        if !path.is_file() {
+            return Err(TldrError::TldrFileNotfound {
+                path: path.display().to_string(),
+            });
+        }
+        let manifest = fs::read_to_string(&path).map_err(|e| TldrError::TldrCouldNotReadFile {
+            path: path.display().to_string(),
+            source: e.into(),
+        })?;
+        let mut ast = parse_script(&manifest)?;
+
+        // the files of the manifest are relative to the snapshot
+        let paths = ast
+            .file_descriptors
+            .iter()
+            .map(|d| dir.join(d.file_path()).to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        for (descriptor, path) in ast.file_descriptors.iter_mut().zip(&paths) {
+            if let FileDescriptorData::Arrow(data) = descriptor {
+                data.arrow_file_path = path.as_str();
+            }
+        }
+
+        Context::convert_ast(&ast)
+    }
+
+    /// reads all rows of the table `name` and writes them to the arrow ipc file `path`
+    async fn write_table(&self, name: &str, path: &Path) -> Result<(), TldrError> {
+        let table = self
+            .tables
+            .get(name)
+            .ok_or_else(|| TldrError::TldrUnknownTable {
+                table: name.to_string(),
+            })?;
+        let schema = table.schema();
+        let batches = self
+            .ctx
+            .read_table(table.clone())
+            .map_err(snapshot_error(path))?
+            .collect()
+            .await
+            .map_err(snapshot_error(path))?;
+
+        let file = File::create(path).map_err(snapshot_error(path))?;
+        let mut writer = FileWriter::try_new(file, &schema).map_err(snapshot_error(path))?;
+        for batch in batches {
+            // the batches of a view do not necessarily have the schema of the view
+            let batch = RecordBatch::try_new(schema.clone(), batch.columns().to_vec())
+                .map_err(snapshot_error(path))?;
+            writer.write(&batch).map_err(snapshot_error(path))?;
+        }
+        writer.finish().map_err(snapshot_error(path))
+    }
+
+    /// fails on a name that cannot be quoted in the manifest, measures and queries are already
+    /// written in the dsl
+    fn check_manifest_names(&self) -> Result<(), TldrError> {
+        let mut names = self.tables.keys().map(String::as_str).chain(
+            self.relationships
+                .iter()
+                .flat_map(|r| [r.from_column.as_str(), r.to_column.as_str()]),
+        );
+        match names.find(|n| n.contains('"')) {
+            Some(name) => Err(TldrError::TldrUnquotableName {
+                name: name.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// the tldr script of the model, `files` are the arrow files of the tables by their name
+    fn manifest(&self, files: &[(&str, String)]) -> String {
+        let mut lines = vec!["load_files (".to_string()];
+        for (name, file_name) in files {
+            let (schema, table) = match self.table_references.get(*name) {
+                Some(reference) => (reference.schema(), reference.table()),
+                None => (None, *name),
+            };
+            let schema = schema
+                .map(|s| format!(", schema: \"{}\"", s))
+                .unwrap_or_default();
+            lines.push(format!(
+                "    (arrow_file_name: \"{}\", table_name: \"{}\"{}),",
+                file_name, table, schema
+            ));
+        }
+        lines.push(")".to_string());
+
+        if !self.relationships.is_empty() {
+            lines.push("relationships (".to_string());
+            for r in &self.relationships {
+                let cardinality = match r.cardinality {
+                    Cardinality::ManyToOne => "many_to_one",
+                    Cardinality::OneToOne => "one_to_one",
+                    Cardinality::ManyToMany => "many_to_many",
+                };
+                let filter_direction = match r.filter_direction {
+                    FilterDirection::Single => "single",
+                    FilterDirection::Both => "both",
+                };
+                lines.push(format!(
+                    "    \"{}\".\"{}\" -> \"{}\".\"{}\" (cardinality: {}, filter_direction: {}),",
+                    r.from_table,
+                    r.from_column,
+                    r.to_table,
+                    r.to_column,
+                    cardinality,
+                    filter_direction
+                ));
+            }
+            lines.push(")".to_string());
+        }
+
+        if !self.measures.is_empty() {
+            lines.push("measures (".to_string());
+            for m in &self.measures {
+                lines.push(format!("    \"{}\": {},", m.name, m.definition));
+            }
+            lines.push(")".to_string());
+        }
+
+        if !self.queries.is_empty() {
+            lines.push("queries (".to_string());
+            for q in &self.queries {
+                lines.push(format!("    {};", q.definition));
+            }
+            lines.push(")".to_string());
+        }
+
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+fn snapshot_error<E: Into<BoxedError>>(path: &Path) -> impl FnOnce(E) -> TldrError + '_ {
+    move |e| TldrError::TldrCouldNotWriteSnapshot {
+        path: path.display().to_string(),
+        source: e.into(),
+    }
+}
+
+#[tokio::test]
+async fn snapshot_test() {
+    use crate::grammar::ast::{Ast, CSVData};
+    use arrow::util::pretty::pretty_format_batches;
+
+    let script = "load_files (
+            (csv_file_name: \"fixtures/FactSales.csv\"),
+            (csv_file_name: \"fixtures/DimProduct.csv\", schema: \"contoso\"),
+            (csv_file_name: \"fixtures/Outages.csv\", delimiter: \";\", materialize: false,
+             field_types: ( \"OutageDate\": date(format: \"%Y-%m-%d\") )),
+        )
+        relationships (
+            FactSales.ProductKey -> \"contoso.DimProduct\".ProductKey (filter_direction: both),
+        )
+        calculated_columns (
+            FactSales.Margin = SalesAmount - TotalCost,
+        )
+        measures (
+            \"Total Margin\": sum(FactSales.Margin),
+            \"Half Margin\": [Total Margin] / 2.0,
+        )
+        queries (
+            \"margin_of\"(color: string): [Total Margin] where \"contoso.DimProduct\".Color = $color;
+        )";
+    let ast = parse_script(script).unwrap();
+    let context = Context::convert_ast(&ast).unwrap();
+
+    let dir = std::env::temp_dir().join(format!("tldr_snapshot_test_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    context.save_snapshot(&dir).await.unwrap();
+    let snapshot = Context::load_snapshot(&dir).unwrap();
+
+    let mut tables = snapshot.tables.keys().collect::<Vec<_>>();
+    tables.sort();
+    assert_eq!(tables, vec!["FactSales", "Outages", "contoso.DimProduct"]);
+    for (name, table) in &context.tables {
+        let types = |t: &std::sync::Arc<dyn datafusion::datasource::TableProvider>| {
+            t.schema()
+                .fields()
+                .iter()
+                .map(|f| (f.name().clone(), f.data_type().clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(types(table), types(&snapshot.tables[name]), "{}", name);
+    }
+    assert_eq!(snapshot.relationships, context.relationships);
+    assert_eq!(
+        snapshot
+            .measures
+            .iter()
+            .map(|m| (m.name.as_str(), m.definition.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("Total Margin", "sum(\"FactSales\".\"Margin\")"),
+            ("Half Margin", "([Total Margin] / 2.0)"),
+        ]
+    );
+    assert_eq!(snapshot.queries, context.queries);
+
+    let query = "\"contoso.DimProduct\".Color, [Half Margin]";
+    let mut results = Vec::new();
+    for context in [&context, &snapshot] {
+        let plan = context.plan_query(query).unwrap();
+        let batches = context
+            .ctx
+            .execute_logical_plan(plan)
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let mut lines = pretty_format_batches(&batches)
+            .unwrap()
+            .to_string()
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        lines.sort();
+        results.push(lines);
+    }
+    assert_eq!(results[0], results[1]);
+
+    assert!(matches!(
+        Context::load_snapshot(dir.join("missing")),
+        Err(TldrError::TldrFileNotfound { .. })
+    ));
+
+    // the tables have the same file name once the slash is replaced
+    let script = "load_files (
+            (csv_file_name: \"fixtures/FactSales.csv\", table_name: \"a/b\"),
+            (csv_file_name: \"fixtures/DimProduct.csv\", table_name: \"a_b\"),
+        )";
+    let ast = parse_script(script).unwrap();
+    let context = Context::convert_ast(&ast).unwrap();
+    let _ = fs::remove_dir_all(&dir);
+    context.save_snapshot(&dir).await.unwrap();
+    let snapshot = Context::load_snapshot(&dir).unwrap();
+    assert_eq!(snapshot.tables["a/b"].schema().field(0).name(), "SalesKey");
+    assert_eq!(
+        snapshot.tables["a_b"].schema().field(0).name(),
+        "ProductKey"
+    );
+
+    // a double quote cannot be written into the manifest, the table is named like its file
+    let path = dir.join("Sa\"les.csv");
+    fs::copy("fixtures/FactSales.csv", &path).unwrap();
+    let sales = CSVData::new(path.to_str().unwrap());
+    let ast = Ast {
+        file_descriptors: vec![FileDescriptorData::CSV(sales)],
+        ..Default::default()
+    };
+    let context = Context::convert_ast(&ast).unwrap();
+    assert!(matches!(
+        context.save_snapshot(dir.join("quoted")).await,
+        Err(TldrError::TldrUnquotableName { .. })
+    ));
+    fs::remove_dir_all(&dir).unwrap();
+}
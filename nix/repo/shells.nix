@@ -11,6 +11,7 @@
   dev = lib.dev.mkShell {
     packages = [
       nixpkgs.pkg-config
+      nixpkgs.unixODBC
     ];
     language.rust = {
       packageSet = cell.rust;
@@ -46,6 +47,11 @@
         # https://github.com/rust-lang/rust-analyzer/blob/7f1234492e3164f9688027278df7e915bc1d919c/crates/project-model/src/sysroot.rs#L196-L211
         value = "${cell.rust.toolchain}/lib/rustlib/src/rust/library";
       }
+      {
+        # the odbc tests load the contoso fixture through the sqlite odbc driver
+        name = "TLDR_SQLITE_ODBC_DRIVER";
+        value = "${nixpkgs.unixODBCDrivers.sqlite}/${nixpkgs.unixODBCDrivers.sqlite.driver}";
+      }
       {
         name = "PKG_CONFIG_PATH";
         value = l.makeSearchPath "lib/pkgconfig" hello.packages.default.buildInputs;